@@ -0,0 +1,140 @@
+//! Alpha-equivalence, i.e., equality up to the renaming of binders.
+//! For `Exp` the binders are the lambda arguments, e.g., `λx. x` and `λy. y`;
+//! for `Type` every type variable is (implicitly) bound at the top, e.g.,
+//! `X3 -> X3` and `a -> a`.
+//! Note that the type annotations inside an `Exp` are compared as they are,
+//! since their type variables are shared across the whole term.
+
+use core::fmt;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use crate::{nameless::Term, type_::tarrow::TArrow, type_::Type, Exp};
+
+/// Anything with a canonical representative for its alpha-equivalence class.
+pub trait AlphaCanonical {
+    type Key: Eq + Hash + Ord;
+
+    /// two values are alpha-equivalent iff they have the same key.
+    fn alpha_key(&self) -> Self::Key;
+}
+
+impl AlphaCanonical for Exp {
+    type Key = Term;
+
+    fn alpha_key(&self) -> Self::Key {
+        Term::from_exp(self)
+    }
+}
+
+impl AlphaCanonical for Type {
+    type Key = Type;
+
+    fn alpha_key(&self) -> Self::Key {
+        self.canonical()
+    }
+}
+
+impl Exp {
+    pub fn alpha_eq(&self, other: &Exp) -> bool {
+        self.alpha_key() == other.alpha_key()
+    }
+}
+
+impl Type {
+    pub fn alpha_eq(&self, other: &Type) -> bool {
+        self.canonical() == other.canonical()
+    }
+
+    /// rename the type variables by their first appearance
+    /// (from left to right), i.e., `t0`, `t1`, etc.
+    pub fn canonical(&self) -> Type {
+        fn canonical_inner(ty: &Type, renaming: &mut HashMap<String, String>) -> Type {
+            match ty {
+                Type::TVar(v) => {
+                    let next = format!("t{}", renaming.len());
+                    Type::TVar(renaming.entry(v.clone()).or_insert(next).clone())
+                }
+                Type::TArrow(arrow) => {
+                    let ty1 = canonical_inner(&arrow.ty1, renaming);
+                    let ty2 = canonical_inner(&arrow.ty2, renaming);
+                    TArrow::build(ty1, ty2)
+                }
+                ty => ty.clone(),
+            }
+        }
+
+        canonical_inner(self, &mut HashMap::new())
+    }
+}
+
+/// A wrapper whose `Eq`, `Hash` and `Ord` are alpha-invariant,
+/// e.g., to deduplicate terms in a `HashSet`, or as the key of a cache.
+/// The canonical key is computed once upon construction.
+#[derive(Clone)]
+pub struct Alpha<T: AlphaCanonical> {
+    value: T,
+    key: T::Key,
+}
+
+impl<T: AlphaCanonical> Alpha<T> {
+    pub fn new(value: T) -> Self {
+        let key = value.alpha_key();
+        Self { value, key }
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: AlphaCanonical> From<T> for Alpha<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: AlphaCanonical> PartialEq for Alpha<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T: AlphaCanonical> Eq for Alpha<T> {}
+
+impl<T: AlphaCanonical> Hash for Alpha<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+impl<T: AlphaCanonical> PartialOrd for Alpha<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: AlphaCanonical> Ord for Alpha<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<T: AlphaCanonical + fmt::Debug> fmt::Debug for Alpha<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Alpha").field(&self.value).finish()
+    }
+}
+
+impl<T: AlphaCanonical + fmt::Display> fmt::Display for Alpha<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
@@ -0,0 +1,195 @@
+//! A-normal form (ANF), i.e., every intermediate result is named, and
+//! every operand is an *atom*, i.e., a variable, a constant or a lambda:
+//!
+//! ```text
+//! a ::= x | n | true | false | λx. e
+//! c ::= a | a a | if a then e else e | is_zero a | incr a | decr a | a + a
+//! e ::= c | let x = c in e
+//! ```
+//!
+//! There is no `let` in `Exp`, so `let x = c in e` is encoded as `(λx. e) c`,
+//! which is told apart from `a a` by `c` not being an atom.
+//! The operands are named from left to right, i.e., in the order the
+//! call-by-value evaluator would reduce them, e.g.,
+//!
+//! ```text
+//! incr ((λx. x) 1) + 2   ==>   let t1 = (λx. x) 1 in
+//!                              let t2 = incr t1 in
+//!                              t2 + 2
+//! ```
+
+use crate::{
+    expr::{add::Add, app::App, cond::Cond, lambda::Lambda, var::Var},
+    subst::FreshNames,
+    type_::Type,
+    Exp,
+};
+
+/// `let x = c in ..`, with the (optional) annotation of `x`.
+type Binding = (String, Option<Type>, Exp);
+
+/// `(λx. body) c`, i.e., `let x = c in body`.
+fn build_let((arg, ty, c): Binding, body: Exp) -> Exp {
+    App::build(Lambda { arg, exp: body, ty }.into(), c)
+}
+
+/// `(lambda, c)` if `e` is (the encoding of) `let x = c in body`.
+fn as_let(e: &Exp) -> Option<(&Lambda, &Exp)> {
+    match e {
+        Exp::App(app) => match &app.t1 {
+            Exp::Lambda(lambda) if !app.t2.ref_is_atom() => Some((lambda, &app.t2)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+struct Anf {
+    fresh: FreshNames,
+}
+
+impl Anf {
+    fn anf(&mut self, e: &Exp) -> Exp {
+        let mut bindings = vec![];
+        let mut e = e;
+        // an existing let in tail position is kept as it is,
+        // i.e., the conversion is idempotent
+        while let Some((lambda, c)) = as_let(e) {
+            let c = self.complex(c, &mut bindings);
+            bindings.push((lambda.arg.clone(), lambda.ty.clone(), c));
+            e = &lambda.exp;
+        }
+        let c = self.complex(e, &mut bindings);
+        bindings
+            .into_iter()
+            .rev()
+            .fold(c, |body, binding| build_let(binding, body))
+    }
+
+    /// the atom for `e`, naming it (after its operands) if it is not one.
+    fn atom(&mut self, e: &Exp, bindings: &mut Vec<Binding>) -> Exp {
+        let c = self.complex(e, bindings);
+        if c.ref_is_atom() {
+            return c;
+        }
+        let x = self.fresh.fresh("t");
+        bindings.push((x.clone(), None, c));
+        Var::build(&x)
+    }
+
+    fn complex(&mut self, e: &Exp, bindings: &mut Vec<Binding>) -> Exp {
+        // a let elsewhere is flattened, with its binder renamed
+        // so that it never captures anything after it
+        if let Some((lambda, c)) = as_let(e) {
+            let c = self.complex(c, bindings);
+            let x = self.fresh.fresh(&lambda.arg);
+            let body = lambda
+                .exp
+                .clone()
+                .ref_substitute(lambda.arg.clone(), Var::build(&x));
+            bindings.push((x, lambda.ty.clone(), c));
+            return self.complex(&body, bindings);
+        }
+        match e {
+            Exp::Var(_) | Exp::True | Exp::False | Exp::Nat(_) => e.clone(),
+            Exp::Lambda(lambda) => Lambda {
+                arg: lambda.arg.clone(),
+                exp: self.anf(&lambda.exp),
+                ty: lambda.ty.clone(),
+            }
+            .into(),
+            Exp::App(app) => {
+                let t1 = self.atom(&app.t1, bindings);
+                let t2 = self.atom(&app.t2, bindings);
+                App::build(t1, t2)
+            }
+            Exp::Cond(cond) => {
+                let r#if = self.atom(&cond.r#if, bindings);
+                Cond::build(r#if, self.anf(&cond.r#then), self.anf(&cond.r#else))
+            }
+            Exp::IsZero(t) => Exp::IsZero(Box::new(self.atom(t, bindings))),
+            Exp::Incr(t) => Exp::Incr(Box::new(self.atom(t, bindings))),
+            Exp::Decr(t) => Exp::Decr(Box::new(self.atom(t, bindings))),
+            Exp::Add(add) => {
+                let t1 = self.atom(&add.t1, bindings);
+                let t2 = self.atom(&add.t2, bindings);
+                Add::build(t1, t2)
+            }
+        }
+    }
+}
+
+/// `e` with the lets, if any, one per line, e.g.,
+/// `let t1 = incr (1) in\n(f) (t1)`.
+fn fmt_lets(e: &Exp, indent: usize, out: &mut String) {
+    let mut e = e;
+    while let Some((lambda, c)) = as_let(e) {
+        match &lambda.ty {
+            Some(ty) => out.push_str(&format!("let {}: {} = ", lambda.arg, ty)),
+            None => out.push_str(&format!("let {} = ", lambda.arg)),
+        }
+        fmt_lets(c, indent + 4, out);
+        out.push_str(&format!(" in\n{}", " ".repeat(indent)));
+        e = &lambda.exp;
+    }
+    match e {
+        // a lambda body starts over on the next line
+        Exp::Lambda(lambda) if as_let(&lambda.exp).is_some() => {
+            out.push_str(&format!("λ{}. (\n{}", lambda.arg, " ".repeat(indent + 2)));
+            fmt_lets(&lambda.exp, indent + 2, out);
+            out.push(')');
+        }
+        e => out.push_str(&e.to_string()),
+    }
+}
+
+impl Exp {
+    /// whether the current expression is an atom, i.e.,
+    /// a variable, a constant or a lambda abstraction.
+    pub fn ref_is_atom(&self) -> bool {
+        matches!(
+            self,
+            Exp::Var(_) | Exp::True | Exp::False | Exp::Nat(_) | Exp::Lambda(_)
+        )
+    }
+
+    /// convert to A-normal form, i.e., name every intermediate result.
+    pub fn ref_to_anf(&self) -> Exp {
+        Anf {
+            fresh: FreshNames::avoiding(&[self]),
+        }
+        .anf(self)
+    }
+
+    /// whether the current expression is in A-normal form.
+    pub fn ref_is_anf(&self) -> bool {
+        fn atom(e: &Exp) -> bool {
+            match e {
+                Exp::Lambda(lambda) => lambda.exp.ref_is_anf(),
+                e => e.ref_is_atom(),
+            }
+        }
+        fn complex(e: &Exp) -> bool {
+            match e {
+                Exp::App(app) => atom(&app.t1) && atom(&app.t2),
+                Exp::Cond(cond) => {
+                    atom(&cond.r#if) && cond.r#then.ref_is_anf() && cond.r#else.ref_is_anf()
+                }
+                Exp::IsZero(t) | Exp::Incr(t) | Exp::Decr(t) => atom(t),
+                Exp::Add(add) => atom(&add.t1) && atom(&add.t2),
+                e => atom(e),
+            }
+        }
+        match as_let(self) {
+            Some((lambda, c)) => complex(c) && lambda.exp.ref_is_anf(),
+            None => complex(self),
+        }
+    }
+
+    /// show the (encoded) lets as `let x = c in e`, one per line.
+    pub fn ref_display_anf(&self) -> String {
+        let mut out = String::new();
+        fmt_lets(self, 0, &mut out);
+        out
+    }
+}
@@ -0,0 +1,369 @@
+//! The environment-based big-step evaluator.
+//! Rather than substituting the argument into the body (and cloning the
+//! whole term) on every beta reduction, the argument is *bound* in an
+//! environment, and a lambda evaluates to a *closure*, i.e., the lambda
+//! together with the environment it was defined in.
+//! Under call-by-name (or call-by-need) the environment binds *thunks*,
+//! i.e., the unevaluated argument with its own environment.
+//!
+//! The evaluation counts the reductions the small-step evaluator would
+//! take, so both the normal form (after `read_back`) and the number of
+//! steps agree with `ref_eval_to_normal_form`.
+
+use core::fmt;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    expr::{add::Add, app::App, lambda::Lambda},
+    limits::{Budget, EvalConfig},
+    stlc_err::StlcError,
+    type_::Type,
+    Exp, Strategy,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The result of the big-step evaluation.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Closure(Box<Closure>),
+    True,
+    False,
+    Nat(u32),
+}
+
+/// A lambda abstraction with the environment it was defined in.
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub arg: String,
+    pub ty: Option<Type>,
+    pub body: Exp,
+    pub scope: Scope,
+}
+
+/// The environment, i.e., a persistent linked list of bindings
+/// with the innermost one at the front.
+#[derive(Debug, Clone, Default)]
+pub struct Scope(Option<Rc<Frame>>);
+
+#[derive(Debug)]
+struct Frame {
+    name: String,
+    binding: Binding,
+    next: Scope,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Binding {
+    Value(Value),
+    Thunk(Rc<RefCell<Thunk>>),
+}
+
+/// An argument not evaluated yet, with the environment it was passed in.
+#[derive(Debug)]
+pub enum Thunk {
+    Delayed(Exp, Scope),
+    /// only under call-by-need, i.e., the thunk is evaluated at most once.
+    Forced(Value),
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn extend(&self, name: String, binding: Binding) -> Self {
+        Self(Some(Rc::new(Frame {
+            name,
+            binding,
+            next: self.clone(),
+        })))
+    }
+
+    pub(crate) fn lookup(&self, name: &str) -> Option<&Binding> {
+        let mut scope = self;
+        while let Some(frame) = &scope.0 {
+            if frame.name == name {
+                return Some(&frame.binding);
+            }
+            scope = &frame.next;
+        }
+        None
+    }
+
+    /// the bindings, innermost first (shadowed ones included).
+    pub(crate) fn bindings(&self) -> Vec<(&str, &Binding)> {
+        let mut bindings = vec![];
+        let mut scope = self;
+        while let Some(frame) = &scope.0 {
+            bindings.push((frame.name.as_str(), &frame.binding));
+            scope = &frame.next;
+        }
+        bindings
+    }
+
+    /// `e` with every free variable bound in the scope replaced by
+    /// (the read back of) its binding.
+    pub fn close(&self, e: &Exp) -> Exp {
+        let substs: Vec<(String, Exp)> = e
+            .free_vars()
+            .into_iter()
+            .filter_map(|v| {
+                let s = match self.lookup(&v)? {
+                    Binding::Value(value) => value.read_back(),
+                    Binding::Thunk(thunk) => match &*thunk.borrow() {
+                        Thunk::Delayed(e, scope) => scope.close(e),
+                        Thunk::Forced(value) => value.read_back(),
+                    },
+                };
+                Some((v, s))
+            })
+            .collect();
+        e.clone().substitute_many(&substs)
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bindings = self.bindings();
+        if bindings.is_empty() {
+            return write!(f, "∅");
+        }
+        write!(f, "{{")?;
+        for (i, (name, binding)) in bindings.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match binding {
+                Binding::Value(value) => write!(f, "{} ↦ {}", name, value)?,
+                Binding::Thunk(thunk) => match &*thunk.borrow() {
+                    // the thunk is shown as it is, i.e., without its own scope
+                    Thunk::Delayed(e, _) => write!(f, "{} ↦ ⟨{}⟩", name, e)?,
+                    Thunk::Forced(value) => write!(f, "{} ↦ {}", name, value)?,
+                },
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+impl Value {
+    /// convert back to an `Exp`, i.e., close the lambda of a closure.
+    pub fn read_back(&self) -> Exp {
+        match self {
+            Value::Closure(closure) => closure.scope.close(
+                &Lambda {
+                    arg: closure.arg.clone(),
+                    exp: closure.body.clone(),
+                    ty: closure.ty.clone(),
+                }
+                .into(),
+            ),
+            Value::True => Exp::True,
+            Value::False => Exp::False,
+            Value::Nat(n) => Exp::Nat(*n),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.read_back())
+    }
+}
+
+struct BigStep {
+    strategy: Strategy,
+    steps: u32,
+    budget: Budget,
+    /// how deep the evaluation of the operands nests.
+    nesting: usize,
+}
+
+impl BigStep {
+    /// count one reduction, the current expression being reported.
+    fn tick(&mut self, e: &Exp, scope: &Scope) -> Result<()> {
+        self.budget.check(self.steps, || scope.close(e))?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// evaluate an operand, i.e., a nested (non-tail) evaluation.
+    fn nested(&mut self, e: &Exp, scope: &Scope) -> Result<Value> {
+        self.budget
+            .check_nesting(self.nesting, self.steps, || scope.close(e))?;
+        self.nesting += 1;
+        let value = self.eval(e, scope);
+        self.nesting -= 1;
+        value
+    }
+
+    fn stuck(&self, e: Exp) -> StlcError {
+        match self.strategy {
+            Strategy::CallByValue => StlcError::StuckExpressionCbv(format!("{}", e)),
+            _ => StlcError::StuckExpressionCbn(format!("{}", e)),
+        }
+    }
+
+    fn nat(&mut self, e: &Exp, scope: &Scope) -> Result<u32> {
+        match self.nested(e, scope)? {
+            Value::Nat(n) => Ok(n),
+            v => Err(StlcError::InvalidExpression(format!("{}", v))),
+        }
+    }
+
+    /// note: the tail calls (i.e., the body of the applied closure and the
+    /// chosen branch) loop instead of recursing, so that a diverging term
+    /// like `(λx. x x) (λx. x x)` runs into the limit rather than overflowing,
+    /// while the operands are `nested` within the depth limit.
+    fn eval(&mut self, e: &Exp, scope: &Scope) -> Result<Value> {
+        let mut e = e.clone();
+        let mut scope = scope.clone();
+        loop {
+            (e, scope) = match &e {
+                Exp::App(app) => {
+                    let closure = match self.nested(&app.t1, &scope)? {
+                        Value::Closure(closure) => closure,
+                        v => {
+                            return Err(self.stuck(App::build(v.read_back(), scope.close(&app.t2))))
+                        }
+                    };
+                    let binding = match self.strategy {
+                        Strategy::CallByValue => Binding::Value(self.nested(&app.t2, &scope)?),
+                        _ => Binding::Thunk(Rc::new(RefCell::new(Thunk::Delayed(
+                            app.t2.clone(),
+                            scope.clone(),
+                        )))),
+                    };
+                    self.tick(&e, &scope)?;
+                    let scope = closure.scope.extend(closure.arg, binding);
+                    (closure.body, scope)
+                }
+                Exp::Cond(cond) => {
+                    let branch = match self.nested(&cond.r#if, &scope)? {
+                        Value::True => &cond.r#then,
+                        Value::False => &cond.r#else,
+                        v => return Err(StlcError::non_boolean_if(v)),
+                    };
+                    self.tick(&e, &scope)?;
+                    (branch.clone(), scope)
+                }
+                _ => return self.eval_non_tail(&e, &scope),
+            }
+        }
+    }
+
+    fn eval_non_tail(&mut self, e: &Exp, scope: &Scope) -> Result<Value> {
+        match e {
+            Exp::Var(v) => match scope.lookup(v) {
+                None => Err(StlcError::InvalidExpression(v.clone())),
+                Some(Binding::Value(value)) => Ok(value.clone()),
+                Some(Binding::Thunk(thunk)) => {
+                    let (e, thunk_scope) = match &*thunk.borrow() {
+                        Thunk::Forced(value) => return Ok(value.clone()),
+                        Thunk::Delayed(e, scope) => (e.clone(), scope.clone()),
+                    };
+                    let value = self.nested(&e, &thunk_scope)?;
+                    if self.strategy == Strategy::CallByNeed {
+                        *thunk.borrow_mut() = Thunk::Forced(value.clone());
+                    }
+                    Ok(value)
+                }
+            },
+            Exp::Lambda(lambda) => Ok(Value::Closure(Box::new(Closure {
+                arg: lambda.arg.clone(),
+                ty: lambda.ty.clone(),
+                body: lambda.exp.clone(),
+                scope: scope.clone(),
+            }))),
+            Exp::True => Ok(Value::True),
+            Exp::False => Ok(Value::False),
+            Exp::Nat(n) => Ok(Value::Nat(*n)),
+            Exp::App(_) | Exp::Cond(_) => self.eval(e, scope),
+            Exp::IsZero(t) => {
+                let n = self.nat(t, scope)?;
+                self.tick(e, scope)?;
+                Ok(if n == 0 { Value::True } else { Value::False })
+            }
+            Exp::Incr(t) => {
+                let n = self.nat(t, scope)?;
+                self.tick(e, scope)?;
+                Ok(Value::Nat(n.saturating_add(1)))
+            }
+            Exp::Decr(t) => {
+                let n = self.nat(t, scope)?;
+                self.tick(e, scope)?;
+                Ok(Value::Nat(n.saturating_sub(1)))
+            }
+            Exp::Add(add) => {
+                let n1 = match self.nested(&add.t1, scope)? {
+                    Value::Nat(n) => n,
+                    v => return Err(self.stuck(Add::build(v.read_back(), scope.close(&add.t2)))),
+                };
+                let n2 = match self.nested(&add.t2, scope)? {
+                    Value::Nat(n) => n,
+                    v => return Err(self.stuck(Add::build(Exp::Nat(n1), v.read_back()))),
+                };
+                self.tick(e, scope)?;
+                Ok(Value::Nat(n1.saturating_add(n2)))
+            }
+        }
+    }
+}
+
+impl Exp {
+    /// evaluate to a value with environments and closures, returns
+    /// the value with the number of (small) steps it takes.
+    /// note: only call-by-value, call-by-name and call-by-need are supported,
+    /// since the others reduce under binders.
+    pub fn ref_eval_big_step(&self, strategy: Strategy) -> Result<(Value, u32)> {
+        self.ref_eval_big_step_with(strategy, &EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// the same as `ref_eval_big_step` within the steps and the timeout of
+    /// `config`, reporting the expression being evaluated.
+    /// note: the depth limit bounds how deep the evaluation of the operands
+    /// nests instead, and the size limit is not checked, since the term
+    /// itself is never built up, see `Scope::close`.
+    pub fn ref_eval_big_step_with(
+        &self,
+        strategy: Strategy,
+        config: &EvalConfig,
+    ) -> Result<(Value, u32)> {
+        if !matches!(
+            strategy,
+            Strategy::CallByValue | Strategy::CallByName | Strategy::CallByNeed
+        ) {
+            return Err(StlcError::InvalidExpression(format!(
+                "{} is not supported by the big-step evaluator",
+                strategy
+            )));
+        }
+        let mut machine = BigStep {
+            strategy,
+            steps: 0,
+            budget: Budget::new(config),
+            nesting: 0,
+        };
+        let value = machine.eval(self, &Scope::new())?;
+        Ok((value, machine.steps))
+    }
+
+    /// same as `ref_eval_to_normal_form`, but by the big-step evaluator.
+    pub fn ref_eval_to_normal_form_big_step(&self, strategy: Strategy) -> Result<(Exp, u32)> {
+        let (value, steps) = self.ref_eval_big_step(strategy)?;
+        Ok((value.read_back(), steps))
+    }
+
+    /// same as `ref_eval_to_normal_form_with`, but by the big-step evaluator,
+    /// see `ref_eval_big_step_with`.
+    pub fn ref_eval_to_normal_form_big_step_with(
+        &self,
+        strategy: Strategy,
+        config: &EvalConfig,
+    ) -> Result<(Exp, u32)> {
+        let (value, steps) = self.ref_eval_big_step_with(strategy, config)?;
+        Ok((value.read_back(), steps))
+    }
+}
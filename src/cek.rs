@@ -0,0 +1,465 @@
+//! The CEK abstract machine, i.e., the state consists of
+//! - C(ontrol): either the expression in focus (to be evaluated),
+//!   or the value just computed (to be returned).
+//! - E(nvironment): the bindings for the free variables of the focus.
+//! - K(ontinuation): what to do with the value afterwards, as a stack of
+//!   frames, each being one layer of the evaluation context, e.g.,
+//!   `□ t2` (evaluate the function first), or `incr □`.
+//!
+//! Every transition only looks at the top of the continuation, so the
+//! machine runs in a loop without using the Rust stack at all, and the
+//! evaluation context that the small-step rules find by recursion is
+//! always right there, e.g.,
+//!
+//! ```text
+//! ▸ (λx. incr (x)) (1) | ∅ | halt
+//! ▸ λx. incr (x) | ∅ | (□) (1) · halt
+//! ◂ λx. incr (x) | (□) (1) · halt
+//! ▸ 1 | ∅ | (λx. incr (x)) (□) · halt
+//! ◂ 1 | (λx. incr (x)) (□) · halt
+//! ▸ incr (x) | {x ↦ 1} | halt
+//! ▸ x | {x ↦ 1} | incr (□) · halt
+//! ◂ 1 | incr (□) · halt
+//! ◂ 2 | halt
+//! ```
+//!
+//! where `▸` means evaluating the expression and `◂` means returning the value.
+//! Under call-by-name the argument is not evaluated but bound as a thunk,
+//! e.g., `{x ↦ ⟨1⟩}`, which gets evaluated every time `x` is in focus.
+
+use core::fmt;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    bigstep::{Binding, Closure, Scope, Thunk, Value},
+    expr::{add::Add, app::App, cond::Cond},
+    limits::{Budget, EvalConfig, REPORTED_FRAMES},
+    stlc_err::StlcError,
+    Exp, Strategy,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The control component.
+#[derive(Debug, Clone)]
+pub enum Control {
+    /// evaluate the expression under the current environment.
+    Eval(Exp),
+    /// return the value to the top of the continuation.
+    Return(Value),
+}
+
+/// A frame of the continuation, i.e., one layer of the evaluation context.
+#[derive(Debug, Clone)]
+pub enum Kont {
+    /// `□ t2`, i.e., evaluating the function, the argument comes later.
+    AppArg(Exp, Scope),
+    /// `v □`, i.e., evaluating the argument (call-by-value only).
+    AppFun(Closure),
+    /// `if □ then t2 else t3`
+    Cond(Exp, Exp, Scope),
+    /// `is_zero □`
+    IsZero,
+    /// `incr □`
+    Incr,
+    /// `decr □`
+    Decr,
+    /// `□ + t2`
+    AddLeft(Exp, Scope),
+    /// `n + □`
+    AddRight(u32),
+    /// overwrite the thunk with the value (call-by-need only).
+    Update(Rc<RefCell<Thunk>>),
+}
+
+impl Kont {
+    /// fill the hole with `hole`.
+    pub fn plug(&self, hole: &str) -> String {
+        match self {
+            Kont::AppArg(t2, _) => format!("({}) ({})", hole, t2),
+            Kont::AppFun(closure) => {
+                format!("({}) ({})", Value::Closure(closure.clone().into()), hole)
+            }
+            Kont::Cond(t2, t3, _) => format!("if ({}) then ({}) else ({})", hole, t2, t3),
+            Kont::IsZero => format!("is_zero ({})", hole),
+            Kont::Incr => format!("incr ({})", hole),
+            Kont::Decr => format!("decr ({})", hole),
+            Kont::AddLeft(t2, _) => format!("({}) + ({})", hole, t2),
+            Kont::AddRight(n) => format!("({}) + ({})", n, hole),
+            Kont::Update(_) => format!("upd {}", hole),
+        }
+    }
+}
+
+impl Kont {
+    /// fill the hole with `hole`, i.e., the term with the environment closed.
+    pub fn plug_exp(&self, hole: Exp) -> Exp {
+        match self {
+            Kont::AppArg(t2, scope) => App::build(hole, scope.close(t2)),
+            Kont::AppFun(closure) => {
+                App::build(Value::Closure(closure.clone().into()).read_back(), hole)
+            }
+            Kont::Cond(t2, t3, scope) => Cond::build(hole, scope.close(t2), scope.close(t3)),
+            Kont::IsZero => Exp::IsZero(Box::new(hole)),
+            Kont::Incr => Exp::Incr(Box::new(hole)),
+            Kont::Decr => Exp::Decr(Box::new(hole)),
+            Kont::AddLeft(t2, scope) => Add::build(hole, scope.close(t2)),
+            Kont::AddRight(n) => Add::build(Exp::Nat(*n), hole),
+            Kont::Update(_) => hole,
+        }
+    }
+}
+
+impl fmt::Display for Kont {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.plug("□"))
+    }
+}
+
+/// A snapshot of the machine.
+#[derive(Debug, Clone)]
+pub struct State {
+    pub control: Control,
+    pub scope: Scope,
+    /// the top of the stack is the *last* one.
+    pub kont: Vec<Kont>,
+}
+
+impl State {
+    /// the whole evaluation context, i.e., every frame plugged
+    /// into the one below it, e.g., `incr ((□) (1))`.
+    pub fn context(&self) -> String {
+        self.kont
+            .iter()
+            .rev()
+            .fold("□".to_string(), |hole, kont| kont.plug(&hole))
+    }
+
+    /// the whole term, i.e., `e` plugged into the evaluation context.
+    pub fn plug(&self, e: Exp) -> Exp {
+        self.kont
+            .iter()
+            .rev()
+            .fold(e, |hole, kont| kont.plug_exp(hole))
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(self.control, Control::Return(_)) && self.kont.is_empty()
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.snapshot())
+    }
+}
+
+/// A state rendered at the moment it was taken.
+/// note: unlike cloning the `State`, it is not affected by the machine
+/// running further, e.g., a thunk forced later under call-by-need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// the expression (when evaluating) or the value (when returning).
+    pub focus: String,
+    pub returning: bool,
+    /// the environment, only meaningful when evaluating.
+    pub scope: String,
+    /// the top of the stack is the *first* one.
+    pub kont: Vec<String>,
+    pub context: String,
+}
+
+impl State {
+    pub fn snapshot(&self) -> Snapshot {
+        let (focus, returning) = match &self.control {
+            Control::Eval(e) => (e.to_string(), false),
+            Control::Return(v) => (v.to_string(), true),
+        };
+        Snapshot {
+            focus,
+            returning,
+            scope: self.scope.to_string(),
+            kont: self.kont.iter().rev().map(|k| k.to_string()).collect(),
+            context: self.context(),
+        }
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.returning {
+            write!(f, "◂ {} | ", self.focus)?;
+        } else {
+            write!(f, "▸ {} | {} | ", self.focus, self.scope)?;
+        }
+        for kont in &self.kont {
+            write!(f, "{} · ", kont)?;
+        }
+        write!(f, "halt")
+    }
+}
+
+/// The machine itself.
+#[derive(Debug, Clone)]
+pub struct Cek {
+    state: State,
+    strategy: Strategy,
+    /// the number of reductions so far, i.e., the steps the
+    /// small-step evaluator would have taken.
+    steps: u32,
+    budget: Budget,
+}
+
+impl Cek {
+    /// note: only call-by-value, call-by-name and call-by-need are supported.
+    pub fn new(e: &Exp, strategy: Strategy) -> Result<Self> {
+        Self::new_with(e, strategy, &EvalConfig::default())
+    }
+
+    /// the same as `new` within the steps and the timeout of `config`,
+    /// reporting the term reached, i.e., the redex plugged into (at most)
+    /// `REPORTED_FRAMES` of the evaluation context, see `State::plug`.
+    /// note: the other limits are not checked, since the term itself
+    /// is never built up.
+    pub fn new_with(e: &Exp, strategy: Strategy, config: &EvalConfig) -> Result<Self> {
+        if !matches!(
+            strategy,
+            Strategy::CallByValue | Strategy::CallByName | Strategy::CallByNeed
+        ) {
+            return Err(StlcError::InvalidExpression(format!(
+                "{} is not supported by the CEK machine",
+                strategy
+            )));
+        }
+        Ok(Self {
+            state: State {
+                control: Control::Eval(e.clone()),
+                scope: Scope::new(),
+                kont: vec![],
+            },
+            strategy,
+            steps: 0,
+            budget: Budget::new(config),
+        })
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    fn stuck(&self, e: Exp) -> StlcError {
+        match self.strategy {
+            Strategy::CallByValue => StlcError::StuckExpressionCbv(format!("{}", e)),
+            _ => StlcError::StuckExpressionCbn(format!("{}", e)),
+        }
+    }
+
+    fn eval(&mut self, e: Exp, scope: Scope) {
+        self.state.control = Control::Eval(e);
+        self.state.scope = scope;
+    }
+
+    fn ret(&mut self, v: Value) {
+        self.state.control = Control::Return(v);
+    }
+
+    /// one transition of the machine, returns `false` if already final.
+    pub fn step(&mut self) -> Result<bool> {
+        if self.state.is_final() {
+            return Ok(false);
+        }
+        let control = std::mem::replace(&mut self.state.control, Control::Return(Value::True));
+        match control {
+            Control::Eval(e) => self.step_eval(e)?,
+            Control::Return(v) => {
+                let kont = self.state.kont.pop().unwrap();
+                self.step_return(v, kont)?
+            }
+        }
+        Ok(true)
+    }
+
+    fn step_eval(&mut self, e: Exp) -> Result<()> {
+        let scope = self.state.scope.clone();
+        match e {
+            Exp::Var(v) => match scope.lookup(&v) {
+                None => return Err(StlcError::InvalidExpression(v)),
+                Some(Binding::Value(value)) => self.ret(value.clone()),
+                Some(Binding::Thunk(thunk)) => {
+                    let delayed = match &*thunk.borrow() {
+                        Thunk::Forced(value) => Err(value.clone()),
+                        Thunk::Delayed(e, scope) => Ok((e.clone(), scope.clone())),
+                    };
+                    match delayed {
+                        Err(value) => self.ret(value),
+                        Ok((e, scope)) => {
+                            if self.strategy == Strategy::CallByNeed {
+                                self.state.kont.push(Kont::Update(thunk.clone()));
+                            }
+                            self.eval(e, scope);
+                        }
+                    }
+                }
+            },
+            Exp::Lambda(lambda) => self.ret(Value::Closure(Box::new(Closure {
+                arg: lambda.arg,
+                ty: lambda.ty,
+                body: lambda.exp,
+                scope,
+            }))),
+            Exp::True => self.ret(Value::True),
+            Exp::False => self.ret(Value::False),
+            Exp::Nat(n) => self.ret(Value::Nat(n)),
+            Exp::App(app) => {
+                self.state.kont.push(Kont::AppArg(app.t2, scope));
+                self.eval(app.t1, self.state.scope.clone());
+            }
+            Exp::Cond(cond) => {
+                self.state
+                    .kont
+                    .push(Kont::Cond(cond.r#then, cond.r#else, scope));
+                self.eval(cond.r#if, self.state.scope.clone());
+            }
+            Exp::IsZero(t) => {
+                self.state.kont.push(Kont::IsZero);
+                self.eval(*t, scope);
+            }
+            Exp::Incr(t) => {
+                self.state.kont.push(Kont::Incr);
+                self.eval(*t, scope);
+            }
+            Exp::Decr(t) => {
+                self.state.kont.push(Kont::Decr);
+                self.eval(*t, scope);
+            }
+            Exp::Add(add) => {
+                self.state.kont.push(Kont::AddLeft(add.t2, scope));
+                self.eval(add.t1, self.state.scope.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// count one reduction of `redex`, in the current evaluation context.
+    fn tick(&mut self, redex: impl FnOnce() -> Exp) -> Result<()> {
+        self.budget.check(self.steps, || {
+            let innermost = self.state.kont.iter().rev().take(REPORTED_FRAMES);
+            innermost.fold(redex(), |hole, kont| kont.plug_exp(hole))
+        })?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn step_return(&mut self, v: Value, kont: Kont) -> Result<()> {
+        match kont {
+            Kont::AppArg(t2, scope) => {
+                let Value::Closure(closure) = v else {
+                    return Err(self.stuck(App::build(v.read_back(), scope.close(&t2))));
+                };
+                if self.strategy == Strategy::CallByValue {
+                    self.state.kont.push(Kont::AppFun(*closure));
+                    self.eval(t2, scope);
+                } else {
+                    self.tick(|| {
+                        App::build(
+                            Value::Closure(closure.clone()).read_back(),
+                            scope.close(&t2),
+                        )
+                    })?;
+                    let thunk = Binding::Thunk(Rc::new(RefCell::new(Thunk::Delayed(t2, scope))));
+                    let scope = closure.scope.extend(closure.arg, thunk);
+                    self.eval(closure.body, scope);
+                }
+            }
+            Kont::AppFun(closure) => {
+                self.tick(|| {
+                    App::build(
+                        Value::Closure(closure.clone().into()).read_back(),
+                        v.read_back(),
+                    )
+                })?;
+                let scope = closure.scope.extend(closure.arg, Binding::Value(v));
+                self.eval(closure.body, scope);
+            }
+            Kont::Cond(t2, t3, scope) => match v {
+                Value::True | Value::False => {
+                    let branch = matches!(v, Value::True);
+                    self.tick(|| Cond::build(v.read_back(), scope.close(&t2), scope.close(&t3)))?;
+                    self.eval(if branch { t2 } else { t3 }, scope);
+                }
+                v => return Err(StlcError::non_boolean_if(v)),
+            },
+            Kont::IsZero | Kont::Incr | Kont::Decr => {
+                let Value::Nat(n) = v else {
+                    return Err(StlcError::InvalidExpression(format!("{}", v)));
+                };
+                self.tick(|| Kont::plug_exp(&kont, Exp::Nat(n)))?;
+                self.ret(match kont {
+                    Kont::IsZero if n == 0 => Value::True,
+                    Kont::IsZero => Value::False,
+                    Kont::Incr => Value::Nat(n.saturating_add(1)),
+                    _ => Value::Nat(n.saturating_sub(1)),
+                });
+            }
+            Kont::AddLeft(t2, scope) => {
+                let Value::Nat(n) = v else {
+                    return Err(self.stuck(Add::build(v.read_back(), scope.close(&t2))));
+                };
+                self.state.kont.push(Kont::AddRight(n));
+                self.eval(t2, scope);
+            }
+            Kont::AddRight(n1) => {
+                let Value::Nat(n2) = v else {
+                    return Err(self.stuck(Add::build(Exp::Nat(n1), v.read_back())));
+                };
+                self.tick(|| Add::build(Exp::Nat(n1), Exp::Nat(n2)))?;
+                self.ret(Value::Nat(n1.saturating_add(n2)));
+            }
+            Kont::Update(thunk) => {
+                *thunk.borrow_mut() = Thunk::Forced(v.clone());
+                self.ret(v);
+            }
+        }
+        Ok(())
+    }
+
+    /// run till the final state, returns the value with the number of reductions.
+    pub fn run(mut self) -> Result<(Value, u32)> {
+        while self.step()? {}
+        match self.state.control {
+            Control::Return(v) => Ok((v, self.steps)),
+            Control::Eval(_) => unreachable!(),
+        }
+    }
+}
+
+impl Exp {
+    /// same as `ref_eval_to_normal_form`, but by the CEK machine.
+    pub fn ref_eval_cek(&self, strategy: Strategy) -> Result<(Exp, u32)> {
+        self.ref_eval_cek_with(strategy, &EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// same as `ref_eval_to_normal_form_with`, but by the CEK machine,
+    /// see `Cek::new_with`.
+    pub fn ref_eval_cek_with(&self, strategy: Strategy, config: &EvalConfig) -> Result<(Exp, u32)> {
+        let (value, steps) = Cek::new_with(self, strategy, config)?.run()?;
+        Ok((value.read_back(), steps))
+    }
+
+    /// (the snapshot of) every state of the machine from the initial one,
+    /// at most `limit` of them.
+    /// note: the trace ends early (without an error) when the machine fails.
+    pub fn ref_trace_cek(&self, strategy: Strategy, limit: usize) -> Result<Vec<Snapshot>> {
+        let mut machine = Cek::new(self, strategy)?;
+        let mut trace = vec![machine.state().snapshot()];
+        while trace.len() < limit && matches!(machine.step(), Ok(true)) {
+            trace.push(machine.state().snapshot());
+        }
+        Ok(trace)
+    }
+}
@@ -0,0 +1,401 @@
+//! Closure conversion & lambda lifting, i.e., every lambda becomes a closed
+//! top-level function taking an explicit environment record (the values
+//! of its free variables) besides its argument, e.g.,
+//!
+//! ```text
+//! (λx. λy. x + y) 1 2   ==>   f0(env, x) = ⟨f1, [x]⟩
+//!                             f1(env, y) = (env[0]) + (y)
+//!                             main = ((⟨f0, []⟩) (1)) (2)
+//! ```
+//!
+//! A lambda evaluates to a closure record, i.e., the function together
+//! with the captured values, and a call passes the record along.
+//! The program runs by call-by-value (see `firstorder`), and counts the
+//! reductions like `ref_eval_one_step_cbv` does, so the pass could be
+//! checked against `ref_eval_to_normal_form(Strategy::CallByValue)`,
+//! steps included.
+
+use core::fmt;
+use std::rc::Rc;
+
+use crate::{
+    expr::{add::Add, app::App, cond::Cond},
+    firstorder::{Evaluator, Target, View},
+    limits::EvalConfig,
+    stlc_err::StlcError,
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The body of a top-level function (or the main expression).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Code {
+    /// the argument of the function, or a free variable of the whole program.
+    Var(String),
+    /// the `i`-th captured value of the environment record.
+    Env(usize),
+    /// build the closure record of the `fun`-th function.
+    MakeClosure {
+        fun: usize,
+        env: Vec<Code>,
+    },
+    /// call the closure with the argument.
+    Apply(Box<Code>, Box<Code>),
+    Cond(Box<Code>, Box<Code>, Box<Code>),
+    True,
+    False,
+    Nat(u32),
+    IsZero(Box<Code>),
+    Incr(Box<Code>),
+    Decr(Box<Code>),
+    Add(Box<Code>, Box<Code>),
+}
+
+/// A closed top-level function, i.e., `name(env, param) = body`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Def {
+    pub name: String,
+    pub param: String,
+    /// the names of the captured variables, i.e., `env[i]` is `captured[i]`.
+    pub captured: Vec<String>,
+    pub body: Code,
+    /// the lambda it is lifted from.
+    pub source: Exp,
+}
+
+/// The top-level functions plus the main expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub defs: Vec<Def>,
+    pub main: Code,
+    /// the term it is converted from.
+    pub source: Exp,
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Code::Var(v) => write!(f, "{}", v),
+            Code::Env(i) => write!(f, "env[{}]", i),
+            Code::MakeClosure { fun, env } => {
+                write!(f, "⟨f{}, [", fun)?;
+                for (i, c) in env.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "]⟩")
+            }
+            Code::Apply(t1, t2) => write!(f, "({}) ({})", t1, t2),
+            Code::Cond(t1, t2, t3) => write!(f, "if {} then {} else {}", t1, t2, t3),
+            Code::True => write!(f, "true"),
+            Code::False => write!(f, "false"),
+            Code::Nat(n) => write!(f, "{}", n),
+            Code::IsZero(t) => write!(f, "is_zero ({})", t),
+            Code::Incr(t) => write!(f, "incr ({})", t),
+            Code::Decr(t) => write!(f, "decr ({})", t),
+            Code::Add(t1, t2) => write!(f, "({}) + ({})", t1, t2),
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for def in &self.defs {
+            writeln!(f, "{}(env, {}) = {}", def.name, def.param, def.body)?;
+        }
+        write!(f, "main = {}", self.main)
+    }
+}
+
+impl Code {
+    fn vars(&self, acc: &mut Vec<String>) {
+        match self {
+            Code::Var(v) => acc.push(v.clone()),
+            Code::Env(_) | Code::True | Code::False | Code::Nat(_) => (),
+            Code::MakeClosure { env, .. } => env.iter().for_each(|c| c.vars(acc)),
+            Code::Apply(t1, t2) | Code::Add(t1, t2) => {
+                t1.vars(acc);
+                t2.vars(acc);
+            }
+            Code::Cond(t1, t2, t3) => {
+                t1.vars(acc);
+                t2.vars(acc);
+                t3.vars(acc);
+            }
+            Code::IsZero(t) | Code::Incr(t) | Code::Decr(t) => t.vars(acc),
+        }
+    }
+}
+
+impl Def {
+    /// whether the body refers to nothing but its argument and its environment.
+    pub fn is_closed(&self) -> bool {
+        let mut vars = vec![];
+        self.body.vars(&mut vars);
+        vars.iter().all(|v| *v == self.param)
+    }
+}
+
+/// What the current function could refer to.
+struct Scope<'a> {
+    param: Option<&'a str>,
+    captured: &'a [String],
+}
+
+impl Scope<'_> {
+    fn lookup(&self, v: &str) -> Code {
+        if self.param == Some(v) {
+            return Code::Var(v.to_string());
+        }
+        match self.captured.iter().position(|c| c == v) {
+            Some(i) => Code::Env(i),
+            // free in the whole program
+            None => Code::Var(v.to_string()),
+        }
+    }
+}
+
+struct Converter {
+    defs: Vec<Def>,
+}
+
+impl Converter {
+    fn convert(&mut self, e: &Exp, scope: &Scope) -> Code {
+        let go = |cv: &mut Self, e: &Exp| Box::new(cv.convert(e, scope));
+        match e {
+            Exp::Var(v) => scope.lookup(v),
+            Exp::Lambda(lambda) => {
+                // capture what is both in scope and free in the lambda
+                let captured: Vec<String> = scope
+                    .param
+                    .into_iter()
+                    .chain(scope.captured.iter().map(String::as_str))
+                    .filter(|v| e.ref_appears_free_in(v))
+                    .map(String::from)
+                    .collect();
+                let fun = self.defs.len();
+                // reserved first, so that the outer function comes first
+                self.defs.push(Def {
+                    name: format!("f{}", fun),
+                    param: lambda.arg.clone(),
+                    captured: captured.clone(),
+                    body: Code::True,
+                    source: e.clone(),
+                });
+                let inner = Scope {
+                    param: Some(&lambda.arg),
+                    captured: &captured,
+                };
+                self.defs[fun].body = self.convert(&lambda.exp, &inner);
+                let env = captured.iter().map(|v| scope.lookup(v)).collect();
+                Code::MakeClosure { fun, env }
+            }
+            Exp::App(app) => Code::Apply(go(self, &app.t1), go(self, &app.t2)),
+            Exp::Cond(cond) => Code::Cond(
+                go(self, &cond.r#if),
+                go(self, &cond.r#then),
+                go(self, &cond.r#else),
+            ),
+            Exp::True => Code::True,
+            Exp::False => Code::False,
+            Exp::Nat(n) => Code::Nat(*n),
+            Exp::IsZero(t) => Code::IsZero(go(self, t)),
+            Exp::Incr(t) => Code::Incr(go(self, t)),
+            Exp::Decr(t) => Code::Decr(go(self, t)),
+            Exp::Add(add) => Code::Add(go(self, &add.t1), go(self, &add.t2)),
+        }
+    }
+}
+
+/// A value of the interpreter.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nat(u32),
+    Bool(bool),
+    /// the function with its environment record.
+    Closure(usize, Rc<Vec<Value>>),
+}
+
+/// The activation of a function, i.e., its environment record and argument.
+pub(crate) struct Frame {
+    env: Rc<Vec<Value>>,
+    param: Option<(String, Value)>,
+}
+
+impl Target for Program {
+    type Code = Code;
+    type Env = Frame;
+    type Value = Value;
+
+    fn view(code: &Code) -> View<'_, Code> {
+        match code {
+            Code::Var(_) | Code::Env(_) => View::Var,
+            Code::MakeClosure { env, .. } => View::Record(env),
+            Code::Apply(t1, t2) => View::Apply(t1, t2),
+            Code::Cond(t1, t2, t3) => View::Cond(t1, t2, t3),
+            Code::True => View::True,
+            Code::False => View::False,
+            Code::Nat(n) => View::Nat(*n),
+            Code::IsZero(t) => View::IsZero(t),
+            Code::Incr(t) => View::Incr(t),
+            Code::Decr(t) => View::Decr(t),
+            Code::Add(t1, t2) => View::Add(t1, t2),
+        }
+    }
+
+    fn lookup(&self, var: &Code, frame: &Frame) -> Result<Value> {
+        match (var, &frame.param) {
+            (Code::Var(v), Some((param, value))) if param == v => Ok(value.clone()),
+            (Code::Env(i), _) => Ok(frame.env[*i].clone()),
+            _ => Err(StlcError::InvalidExpression(format!("{}", var))),
+        }
+    }
+
+    fn record(&self, record: &Code, env: Vec<Value>) -> Value {
+        let Code::MakeClosure { fun, .. } = record else {
+            unreachable!("viewed as a record")
+        };
+        Value::Closure(*fun, Rc::new(env))
+    }
+
+    fn is_function(f: &Value) -> bool {
+        matches!(f, Value::Closure(..))
+    }
+
+    fn call(&self, f: Value, arg: Value) -> (&Code, Frame) {
+        let Value::Closure(fun, env) = f else {
+            unreachable!("checked by `is_function`")
+        };
+        let def = &self.defs[fun];
+        let frame = Frame {
+            env,
+            param: Some((def.param.clone(), arg)),
+        };
+        (&def.body, frame)
+    }
+
+    fn nat(value: &Value) -> Option<u32> {
+        match value {
+            Value::Nat(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn bool(value: &Value) -> Option<bool> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn from_nat(n: u32) -> Value {
+        Value::Nat(n)
+    }
+
+    fn from_bool(b: bool) -> Value {
+        Value::Bool(b)
+    }
+
+    fn read_back(&self, value: &Value) -> Exp {
+        Program::read_back(self, value)
+    }
+
+    /// i.e., with the argument and the captured values substituted.
+    fn read_back_code(&self, code: &Code, frame: &Frame) -> Exp {
+        let go = |c: &Code| self.read_back_code(c, frame);
+        match code {
+            Code::Var(v) => match &frame.param {
+                Some((param, value)) if param == v => self.read_back(value),
+                _ => Exp::Var(v.clone()),
+            },
+            Code::Env(i) => self.read_back(&frame.env[*i]),
+            Code::MakeClosure { fun, env } => {
+                let def = &self.defs[*fun];
+                let substs: Vec<(String, Exp)> = def
+                    .captured
+                    .iter()
+                    .zip(env)
+                    .map(|(v, c)| (v.clone(), go(c)))
+                    .collect();
+                def.source.clone().substitute_many(&substs)
+            }
+            Code::Apply(t1, t2) => App::build(go(t1), go(t2)),
+            Code::Cond(t1, t2, t3) => Cond::build(go(t1), go(t2), go(t3)),
+            Code::True => Exp::True,
+            Code::False => Exp::False,
+            Code::Nat(n) => Exp::Nat(*n),
+            Code::IsZero(t) => Exp::IsZero(Box::new(go(t))),
+            Code::Incr(t) => Exp::Incr(Box::new(go(t))),
+            Code::Decr(t) => Exp::Decr(Box::new(go(t))),
+            Code::Add(t1, t2) => Add::build(go(t1), go(t2)),
+        }
+    }
+
+    /// always call-by-value.
+    fn stuck(&self, e: Exp) -> StlcError {
+        StlcError::StuckExpressionCbv(format!("{}", e))
+    }
+}
+
+impl Program {
+    /// convert back to an `Exp`, i.e., the source lambda of a closure
+    /// with the captured values substituted.
+    pub fn read_back(&self, value: &Value) -> Exp {
+        match value {
+            Value::Nat(n) => Exp::Nat(*n),
+            Value::Bool(true) => Exp::True,
+            Value::Bool(false) => Exp::False,
+            Value::Closure(fun, env) => {
+                let def = &self.defs[*fun];
+                let substs: Vec<(String, Exp)> = def
+                    .captured
+                    .iter()
+                    .zip(env.iter())
+                    .map(|(v, value)| (v.clone(), self.read_back(value)))
+                    .collect();
+                def.source.clone().substitute_many(&substs)
+            }
+        }
+    }
+
+    /// run by call-by-value, returns the value
+    /// with the number of reductions.
+    pub fn run(&self) -> Result<(Exp, u32)> {
+        self.run_with(&EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// the same as `run` within the steps, the timeout & the depth of
+    /// `config`, reporting the code being reduced with its environment
+    /// substituted.
+    pub fn run_with(&self, config: &EvalConfig) -> Result<(Exp, u32)> {
+        let main = Frame {
+            env: Rc::new(vec![]),
+            param: None,
+        };
+        let (value, steps) = Evaluator::new(self, config).run(&self.main, &main)?;
+        Ok((self.read_back(&value), steps))
+    }
+}
+
+impl Exp {
+    /// closure convert & lambda lift the current expression.
+    pub fn ref_closure_convert(&self) -> Program {
+        let mut converter = Converter { defs: vec![] };
+        let main = converter.convert(
+            self,
+            &Scope {
+                param: None,
+                captured: &[],
+            },
+        );
+        Program {
+            defs: converter.defs,
+            main,
+            source: self.clone(),
+        }
+    }
+}
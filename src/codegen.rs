@@ -0,0 +1,246 @@
+//! Compile a closed, well-typed `Exp` to standalone Rust source, e.g.,
+//!
+//! ```text
+//! (λx: int. incr x) 1   ==>   pub fn main_() -> u32 {
+//!                                 (std::rc::Rc::new(move |v_x: u32| -> u32 { ... })
+//!                                     as std::rc::Rc<dyn Fn(u32) -> u32>)(1u32)
+//!                             }
+//! ```
+//!
+//! `int` is `u32` (with the saturating arithmetic of the evaluator), `bool`
+//! is `bool`, and `T1 -> T2` is `Rc<dyn Fn(T1) -> T2>` rather than a `Box`,
+//! since a function, like any other value, may be used more than once.
+//!
+//! The lambdas are either all annotated (and checked by `ty_synth`), or none
+//! of them, in which case the types are inferred, i.e., `ref_annotate_term`,
+//! `ref_infer_constraints` and `ref_unify`. Any type variable left after the
+//! inference (or in the annotations), e.g., the one of `λx. x`, defaults to `int`.
+
+use crate::{
+    expr::{add::Add, app::App, cond::Cond, lambda::Lambda},
+    stlc_err::StlcError,
+    subst::FreshNames,
+    type_::{tarrow::TArrow, Env, TySubst, Type},
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// whether each lambda (in order) is annotated.
+fn annotations(e: &Exp, acc: &mut Vec<bool>) {
+    match e {
+        Exp::Var(_) | Exp::True | Exp::False | Exp::Nat(_) => (),
+        Exp::Lambda(lambda) => {
+            acc.push(lambda.ty.is_some());
+            annotations(&lambda.exp, acc);
+        }
+        Exp::App(app) => {
+            annotations(&app.t1, acc);
+            annotations(&app.t2, acc);
+        }
+        Exp::Cond(cond) => {
+            annotations(&cond.r#if, acc);
+            annotations(&cond.r#then, acc);
+            annotations(&cond.r#else, acc);
+        }
+        Exp::IsZero(t) | Exp::Incr(t) | Exp::Decr(t) => annotations(t, acc),
+        Exp::Add(add) => {
+            annotations(&add.t1, acc);
+            annotations(&add.t2, acc);
+        }
+    }
+}
+
+/// `e` with `f` applied to every annotation.
+fn map_annotations(e: &Exp, f: &impl Fn(&Type) -> Type) -> Exp {
+    match e {
+        Exp::Var(_) | Exp::True | Exp::False | Exp::Nat(_) => e.clone(),
+        Exp::Lambda(lambda) => Lambda {
+            arg: lambda.arg.clone(),
+            exp: map_annotations(&lambda.exp, f),
+            ty: lambda.ty.as_ref().map(f),
+        }
+        .into(),
+        Exp::App(app) => App::build(map_annotations(&app.t1, f), map_annotations(&app.t2, f)),
+        Exp::Cond(cond) => Cond::build(
+            map_annotations(&cond.r#if, f),
+            map_annotations(&cond.r#then, f),
+            map_annotations(&cond.r#else, f),
+        ),
+        Exp::IsZero(t) => Exp::IsZero(Box::new(map_annotations(t, f))),
+        Exp::Incr(t) => Exp::Incr(Box::new(map_annotations(t, f))),
+        Exp::Decr(t) => Exp::Decr(Box::new(map_annotations(t, f))),
+        Exp::Add(add) => Add::build(map_annotations(&add.t1, f), map_annotations(&add.t2, f)),
+    }
+}
+
+/// replace every remaining type variable with `int`.
+fn default_to_int(ty: &Type) -> Type {
+    match ty {
+        Type::TVar(_) => Type::TInt,
+        Type::TArrow(arrow) => {
+            TArrow::build(default_to_int(&arrow.ty1), default_to_int(&arrow.ty2))
+        }
+        ty => ty.clone(),
+    }
+}
+
+fn rust_type(ty: &Type) -> Result<String> {
+    match ty {
+        Type::TInt => Ok("u32".to_string()),
+        Type::TBool => Ok("bool".to_string()),
+        Type::TArrow(arrow) => Ok(format!(
+            "std::rc::Rc<dyn Fn({}) -> {}>",
+            rust_type(&arrow.ty1)?,
+            rust_type(&arrow.ty2)?
+        )),
+        ty => Err(StlcError::InvalidExpression(format!(
+            "no rust type for `{}`",
+            ty
+        ))),
+    }
+}
+
+/// a valid (and keyword-free) rust identifier for the variable,
+/// e.g., `x'` is `v_x_27`.
+fn rust_ident(v: &str) -> String {
+    let mut ident = "v_".to_string();
+    for c in v.chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() => ident.push(c),
+            '_' => ident.push_str("__"),
+            c => ident.push_str(&format!("_{:x}", c as u32)),
+        }
+    }
+    ident
+}
+
+/// the rust expression of an (elaborated) `e` with its type.
+fn emit(e: &Exp, env: &Env) -> Result<(String, Type)> {
+    match e {
+        Exp::Var(v) => match env.lookup(v) {
+            Some(ty) => Ok((format!("{}.clone()", rust_ident(v)), ty)),
+            None => Err(StlcError::InvalidExpression(v.clone())),
+        },
+        Exp::Lambda(lambda) => {
+            let t1 = lambda.get_type_unchecked();
+            let mut inner = env.clone();
+            inner.insert(lambda.arg.clone(), t1.clone());
+            let (body, t2) = emit(&lambda.exp, &inner)?;
+            // the closure moves a clone of what it captures, the original is still in use
+            let captures: String = e
+                .free_vars()
+                .iter()
+                .map(|v| format!("let {0} = {0}.clone(); ", rust_ident(v)))
+                .collect();
+            let ty = TArrow::build(t1.clone(), t2.clone());
+            Ok((
+                format!(
+                    "{{ {}std::rc::Rc::new(move |{}: {}| -> {} {{ {} }}) as {} }}",
+                    captures,
+                    rust_ident(&lambda.arg),
+                    rust_type(&t1)?,
+                    rust_type(&t2)?,
+                    body,
+                    rust_type(&ty)?
+                ),
+                ty,
+            ))
+        }
+        Exp::App(app) => {
+            let (t1, ty) = emit(&app.t1, env)?;
+            let (t2, _) = emit(&app.t2, env)?;
+            let Type::TArrow(arrow) = ty else {
+                return Err(StlcError::InvalidExpression(format!(
+                    "expect an arrow type, actual: {}",
+                    ty
+                )));
+            };
+            Ok((format!("({})({})", t1, t2), arrow.ty2))
+        }
+        Exp::Cond(cond) => {
+            let (r#if, _) = emit(&cond.r#if, env)?;
+            let (r#then, ty) = emit(&cond.r#then, env)?;
+            let (r#else, _) = emit(&cond.r#else, env)?;
+            Ok((
+                format!("if {} {{ {} }} else {{ {} }}", r#if, r#then, r#else),
+                ty,
+            ))
+        }
+        Exp::True => Ok(("true".to_string(), Type::TBool)),
+        Exp::False => Ok(("false".to_string(), Type::TBool)),
+        Exp::Nat(n) => Ok((format!("{}u32", n), Type::TInt)),
+        Exp::IsZero(t) => Ok((format!("({} == 0)", emit(t, env)?.0), Type::TBool)),
+        Exp::Incr(t) => Ok((
+            format!("({}).saturating_add(1)", emit(t, env)?.0),
+            Type::TInt,
+        )),
+        Exp::Decr(t) => Ok((
+            format!("({}).saturating_sub(1)", emit(t, env)?.0),
+            Type::TInt,
+        )),
+        Exp::Add(add) => Ok((
+            format!(
+                "({}).saturating_add({})",
+                emit(&add.t1, env)?.0,
+                emit(&add.t2, env)?.0
+            ),
+            Type::TInt,
+        )),
+    }
+}
+
+impl Exp {
+    /// annotate every lambda with its (inferred) type, returns the fully
+    /// annotated term (alpha-equivalent to the current one) with its type.
+    pub fn ref_elaborate(&self) -> Result<(Exp, Type)> {
+        let mut acc = vec![];
+        annotations(self, &mut acc);
+        let e = if acc.iter().all(|typed| *typed) {
+            self.clone()
+        } else if acc.iter().all(|typed| !typed) {
+            // `ref_infer_constraints` expects every binder to be distinct
+            let mut e = self
+                .clone()
+                .rename_binders(&mut FreshNames::avoiding(&[self]));
+            // note: not `ref_annotate_term`, whose `ref_typed` check rejects
+            // an untyped lambda applied to, e.g., a number
+            let n = e.ref_annotate_term_inner(0);
+            let ill_typed = || StlcError::InvalidExpression(format!("ill-typed: {}", self));
+            let (_, _, tc) = e
+                .ref_infer_constraints(&mut Env::new(), n)
+                .ok_or_else(ill_typed)?;
+            let sigma: TySubst = Type::ref_unify(tc.inner()).ok_or_else(ill_typed)?;
+            map_annotations(&e, &|ty| {
+                let mut ty = ty.clone();
+                ty.ref_apply_ty_subst(&sigma);
+                ty
+            })
+        } else {
+            return Err(StlcError::InvalidExpression(format!(
+                "expect either all or none of the lambdas to be annotated: {}",
+                self
+            )));
+        };
+        let e = map_annotations(&e, &default_to_int);
+        let ty = e
+            .ty_synth(&Env::new())
+            .map_err(|err| StlcError::InvalidExpression(err.to_string()))?;
+        Ok((e, ty))
+    }
+
+    /// the rust source of `pub fn <name>() -> T`, where `T` is the
+    /// rust type of the term, e.g., `u32` for `int`.
+    pub fn ref_to_rust(&self, name: &str) -> Result<String> {
+        let (e, ty) = self.ref_elaborate()?;
+        let (body, _) = emit(&e, &Env::new())?;
+        Ok(format!(
+            "/// generated from `{}`, of type `{}`.\n#[allow(unused)]\npub fn {}() -> {} {{\n    {}\n}}\n",
+            self,
+            ty,
+            name,
+            rust_type(&ty)?,
+            body
+        ))
+    }
+}
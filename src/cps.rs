@@ -0,0 +1,239 @@
+//! The continuation-passing style (CPS) transformation, i.e., Plotkin's
+//! translations, where every term takes its continuation `k` explicitly:
+//!
+//! ```text
+//!                call-by-value                      call-by-name
+//! [x]          = λk. k x                            x
+//! [λx. M]      = λk. k (λx. [M])                    λk. k (λx. [M])
+//! [M N]        = λk. [M] (λm. [N] (λn. m n k))      λk. [M] (λm. m [N] k)
+//! [c]          = λk. k c
+//! [incr M]     = λk. [M] (λv. k (incr v))           (same for is_zero & decr)
+//! [M + N]      = λk. [M] (λm. [N] (λn. k (m + n)))
+//! [if M then N else P] = λk. [M] (λb. if b then [N] k else [P] k)
+//! ```
+//!
+//! Every application in the result has a value, a variable, or a primitive
+//! applied to variables (e.g., `k (incr v)` & `k (m + n)`) as its argument.
+//! The latter is treated as trivial, since it takes a single reduction that
+//! never diverges (nor gets stuck, if well typed), whether before or after
+//! the call. So the result evaluates to the same value under *any*
+//! strategy, i.e., the evaluation order is fixed by the translation instead.
+//!
+//! With the answer type `R`, the value type `A` is translated to `⟦A⟧`,
+//!
+//! ```text
+//! ⟦int⟧ = int, ⟦bool⟧ = bool,
+//! ⟦A -> B⟧ = ⟦A⟧ -> C(B)             (call-by-value)
+//! ⟦A -> B⟧ = C(A) -> C(B)            (call-by-name)
+//! where C(A) = (⟦A⟧ -> R) -> R
+//! ```
+//!
+//! and a term of type `A` is translated to a term of type `C(A)`.
+
+use crate::{
+    expr::{add::Add, app::App, cond::Cond, lambda::Lambda, var::Var},
+    stlc_err::StlcError,
+    subst::FreshNames,
+    type_::{tarrow::TArrow, Env, Type},
+    Exp, Strategy,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// whether `strategy` takes the call-by-value translation.
+fn by_value(strategy: Strategy) -> bool {
+    matches!(strategy, Strategy::CallByValue | Strategy::ApplicativeOrder)
+}
+
+impl Type {
+    /// the CPS translation of the value type, i.e., `⟦A⟧`.
+    pub fn ref_cps(&self, strategy: Strategy, answer: &Type) -> Type {
+        match self {
+            Type::TArrow(arrow) if by_value(strategy) => TArrow::build(
+                arrow.ty1.ref_cps(strategy, answer),
+                arrow.ty2.ref_cps_computation(strategy, answer),
+            ),
+            Type::TArrow(arrow) => TArrow::build(
+                arrow.ty1.ref_cps_computation(strategy, answer),
+                arrow.ty2.ref_cps_computation(strategy, answer),
+            ),
+            ty => ty.clone(),
+        }
+    }
+
+    /// the type of the translated term, i.e., `C(A) = (⟦A⟧ -> R) -> R`.
+    pub fn ref_cps_computation(&self, strategy: Strategy, answer: &Type) -> Type {
+        TArrow::build(self.ref_cps_continuation(strategy, answer), answer.clone())
+    }
+
+    /// the type of the continuation, i.e., `⟦A⟧ -> R`.
+    fn ref_cps_continuation(&self, strategy: Strategy, answer: &Type) -> Type {
+        TArrow::build(self.ref_cps(strategy, answer), answer.clone())
+    }
+}
+
+struct Cps {
+    strategy: Strategy,
+    /// the answer type, only for the typed translation.
+    answer: Option<Type>,
+    fresh: FreshNames,
+}
+
+impl Cps {
+    /// `λx: T. body`, annotated only for the typed translation.
+    fn abs(&self, x: &str, ty: impl FnOnce(&Type) -> Type, body: Exp) -> Exp {
+        match &self.answer {
+            Some(answer) => Lambda::build_with_type(x, body, ty(answer)),
+            None => Lambda::build(x, body),
+        }
+    }
+
+    /// `[e]` with the (source) type of `e`, which is only known for
+    /// the typed translation, i.e., when every lambda is annotated.
+    fn go(&mut self, e: &Exp, env: &Env) -> Result<(Exp, Option<Type>)> {
+        let strategy = self.strategy;
+        let k = self.fresh.fresh("k");
+        // λk: ⟦A⟧ -> R. body
+        let with_k = |cps: &Self, ty: &Option<Type>, body: Exp| {
+            cps.abs(
+                &k,
+                |r| ty.as_ref().unwrap().ref_cps_continuation(strategy, r),
+                body,
+            )
+        };
+        // λk. k v
+        let ret = |cps: &Self, v: Exp, ty: Option<Type>| {
+            let e = with_k(cps, &ty, App::build(Var::build(&k), v));
+            Ok((e, ty))
+        };
+        match e {
+            Exp::Var(v) => {
+                let ty = env.lookup(v);
+                if self.answer.is_some() && ty.is_none() {
+                    return Err(StlcError::InvalidExpression(v.clone()));
+                }
+                if by_value(strategy) {
+                    ret(self, e.clone(), ty)
+                } else {
+                    Ok((e.clone(), ty))
+                }
+            }
+            Exp::True | Exp::False => ret(self, e.clone(), Some(Type::TBool)),
+            Exp::Nat(_) => ret(self, e.clone(), Some(Type::TInt)),
+            Exp::Lambda(lambda) => {
+                let t1 = lambda.ty.clone();
+                let mut inner = env.clone();
+                if let Some(t1) = &t1 {
+                    inner.insert(lambda.arg.clone(), t1.clone());
+                }
+                let (body, t2) = self.go(&lambda.exp, &inner)?;
+                let arg_ty = |r: &Type| {
+                    let t1 = t1.as_ref().unwrap();
+                    if by_value(strategy) {
+                        t1.ref_cps(strategy, r)
+                    } else {
+                        t1.ref_cps_computation(strategy, r)
+                    }
+                };
+                let f = self.abs(&lambda.arg, arg_ty, body);
+                let ty = t1.zip(t2).map(|(t1, t2)| TArrow::build(t1, t2));
+                ret(self, f, ty)
+            }
+            Exp::App(app) => {
+                let (t1, f_ty) = self.go(&app.t1, env)?;
+                let (t2, arg_ty) = self.go(&app.t2, env)?;
+                let ty = match &f_ty {
+                    Some(Type::TArrow(arrow)) => Some(arrow.ty2.clone()),
+                    Some(ty) => {
+                        return Err(StlcError::InvalidExpression(format!(
+                            "expect an arrow type, actual: {}",
+                            ty
+                        )))
+                    }
+                    None => None,
+                };
+                let m = self.fresh.fresh("m");
+                let body = if by_value(strategy) {
+                    // [M] (λm. [N] (λn. m n k))
+                    let n = self.fresh.fresh("n");
+                    let call =
+                        App::build(App::build(Var::build(&m), Var::build(&n)), Var::build(&k));
+                    let on_n =
+                        self.abs(&n, |r| arg_ty.as_ref().unwrap().ref_cps(strategy, r), call);
+                    App::build(t2, on_n)
+                } else {
+                    // [M] (λm. m [N] k)
+                    App::build(App::build(Var::build(&m), t2), Var::build(&k))
+                };
+                let on_m = self.abs(&m, |r| f_ty.as_ref().unwrap().ref_cps(strategy, r), body);
+                Ok((with_k(self, &ty, App::build(t1, on_m)), ty))
+            }
+            Exp::Cond(cond) => {
+                let (r#if, _) = self.go(&cond.r#if, env)?;
+                let (r#then, ty) = self.go(&cond.r#then, env)?;
+                let (r#else, _) = self.go(&cond.r#else, env)?;
+                let b = self.fresh.fresh("b");
+                let branches = Cond::build(
+                    Var::build(&b),
+                    App::build(r#then, Var::build(&k)),
+                    App::build(r#else, Var::build(&k)),
+                );
+                let on_b = self.abs(&b, |_| Type::TBool, branches);
+                Ok((with_k(self, &ty, App::build(r#if, on_b)), ty))
+            }
+            Exp::IsZero(t) | Exp::Incr(t) | Exp::Decr(t) => {
+                let (t, _) = self.go(t, env)?;
+                let v = self.fresh.fresh("v");
+                let (op, ty) = match e {
+                    Exp::IsZero(_) => (Exp::IsZero(Box::new(Var::build(&v))), Type::TBool),
+                    Exp::Incr(_) => (Exp::Incr(Box::new(Var::build(&v))), Type::TInt),
+                    _ => (Exp::Decr(Box::new(Var::build(&v))), Type::TInt),
+                };
+                let on_v = self.abs(&v, |_| Type::TInt, App::build(Var::build(&k), op));
+                let ty = Some(ty);
+                Ok((with_k(self, &ty, App::build(t, on_v)), ty))
+            }
+            Exp::Add(add) => {
+                let (t1, _) = self.go(&add.t1, env)?;
+                let (t2, _) = self.go(&add.t2, env)?;
+                let m = self.fresh.fresh("m");
+                let n = self.fresh.fresh("n");
+                let sum = App::build(Var::build(&k), Add::build(Var::build(&m), Var::build(&n)));
+                let on_n = self.abs(&n, |_| Type::TInt, sum);
+                let on_m = self.abs(&m, |_| Type::TInt, App::build(t2, on_n));
+                let ty = Some(Type::TInt);
+                Ok((with_k(self, &ty, App::build(t1, on_m)), ty))
+            }
+        }
+    }
+}
+
+impl Exp {
+    /// the CPS translation, i.e., call-by-value for `CallByValue` and
+    /// `ApplicativeOrder`, and call-by-name for the others.
+    /// note: apply the result to the identity continuation `λx. x` to run it.
+    pub fn ref_cps(&self, strategy: Strategy) -> Exp {
+        let mut cps = Cps {
+            strategy,
+            answer: None,
+            fresh: FreshNames::avoiding(&[self]),
+        };
+        // without the types, nothing could go wrong
+        cps.go(self, &Env::new()).unwrap().0
+    }
+
+    /// the typed CPS translation with the answer type `answer`, i.e., every
+    /// lambda (continuations included) is annotated, and a term of type `A`
+    /// is translated to a term of type `(⟦A⟧ -> R) -> R`.
+    /// note: the current term is elaborated first, see `ref_elaborate`.
+    pub fn ref_cps_typed(&self, strategy: Strategy, answer: &Type) -> Result<(Exp, Type)> {
+        let (e, ty) = self.ref_elaborate()?;
+        let mut cps = Cps {
+            strategy,
+            answer: Some(answer.clone()),
+            fresh: FreshNames::avoiding(&[&e]),
+        };
+        let (e, _) = cps.go(&e, &Env::new())?;
+        Ok((e, ty.ref_cps_computation(strategy, answer)))
+    }
+}
@@ -0,0 +1,446 @@
+//! Defunctionalization, i.e., every lambda is replaced by a tagged record
+//! of its free variables, and every application goes through a single
+//! generated `apply`, which dispatches on the tag, e.g.,
+//!
+//! ```text
+//! (λf. f 1) (λx. incr x)   ==>   apply(Lam0, f) = apply(f, 1)
+//!                                apply(Lam1, x) = incr (x)
+//!                                main = apply(Lam0, Lam1)
+//! ```
+//!
+//! The result is first-order, i.e., there is no function value left,
+//! only records and the two dispatchers.
+//! Under call-by-name, an argument (other than a value or a variable) is
+//! delayed the same way, i.e., as a `Delay` record evaluated by `force`.
+//! The program runs on the evaluator shared with `closure`, see `firstorder`.
+
+use core::fmt;
+use std::rc::Rc;
+
+use crate::{
+    expr::{add::Add, app::App, cond::Cond},
+    firstorder::{Evaluator, Target, View},
+    limits::EvalConfig,
+    stlc_err::StlcError,
+    Exp, Strategy,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The first-order target language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Var(String),
+    /// the record of a lambda, i.e., its tag with its free variables.
+    Lam {
+        tag: usize,
+        fields: Vec<Term>,
+    },
+    /// the record of a delayed argument, only under call-by-name.
+    Delay {
+        tag: usize,
+        fields: Vec<Term>,
+    },
+    /// `apply(f, a)`
+    Apply(Box<Term>, Box<Term>),
+    /// `force(t)`, i.e., `t` itself if it is not a `Delay`.
+    Force(Box<Term>),
+    Cond(Box<Term>, Box<Term>, Box<Term>),
+    True,
+    False,
+    Nat(u32),
+    IsZero(Box<Term>),
+    Incr(Box<Term>),
+    Decr(Box<Term>),
+    Add(Box<Term>, Box<Term>),
+}
+
+/// A case of a dispatcher, e.g., `apply(Lam1(y), x) = (x) + (y)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub tag: usize,
+    pub fields: Vec<String>,
+    /// the argument, i.e., `None` for the cases of `force`.
+    pub param: Option<String>,
+    pub body: Term,
+    /// the lambda (or the delayed argument) it is generated from.
+    pub source: Exp,
+}
+
+/// The dispatchers plus the main term.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub strategy: Strategy,
+    pub apply: Vec<Case>,
+    pub force: Vec<Case>,
+    pub main: Term,
+    /// the term it is generated from.
+    pub source: Exp,
+}
+
+fn fmt_record(f: &mut fmt::Formatter<'_>, name: &str, tag: usize, fields: &[Term]) -> fmt::Result {
+    write!(f, "{}{}", name, tag)?;
+    if fields.is_empty() {
+        return Ok(());
+    }
+    write!(f, "(")?;
+    for (i, t) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", t)?;
+    }
+    write!(f, ")")
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Var(v) => write!(f, "{}", v),
+            Term::Lam { tag, fields } => fmt_record(f, "Lam", *tag, fields),
+            Term::Delay { tag, fields } => fmt_record(f, "Delay", *tag, fields),
+            Term::Apply(t1, t2) => write!(f, "apply({}, {})", t1, t2),
+            Term::Force(t) => write!(f, "force({})", t),
+            Term::Cond(t1, t2, t3) => write!(f, "if {} then {} else {}", t1, t2, t3),
+            Term::True => write!(f, "true"),
+            Term::False => write!(f, "false"),
+            Term::Nat(n) => write!(f, "{}", n),
+            Term::IsZero(t) => write!(f, "is_zero ({})", t),
+            Term::Incr(t) => write!(f, "incr ({})", t),
+            Term::Decr(t) => write!(f, "decr ({})", t),
+            Term::Add(t1, t2) => write!(f, "({}) + ({})", t1, t2),
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pattern = |name, case: &Case| {
+            let fields: Vec<Term> = case.fields.iter().cloned().map(Term::Var).collect();
+            match name {
+                "Lam" => Term::Lam {
+                    tag: case.tag,
+                    fields,
+                },
+                _ => Term::Delay {
+                    tag: case.tag,
+                    fields,
+                },
+            }
+        };
+        for case in &self.apply {
+            let param = case.param.as_deref().unwrap_or_default();
+            writeln!(
+                f,
+                "apply({}, {}) = {}",
+                pattern("Lam", case),
+                param,
+                case.body
+            )?;
+        }
+        for case in &self.force {
+            writeln!(f, "force({}) = {}", pattern("Delay", case), case.body)?;
+        }
+        write!(f, "main = {}", self.main)
+    }
+}
+
+struct Defunc {
+    by_name: bool,
+    apply: Vec<Case>,
+    force: Vec<Case>,
+}
+
+impl Defunc {
+    /// the variables in scope free in `e`, i.e., the fields of its record.
+    fn fields(e: &Exp, scope: &[String]) -> Vec<String> {
+        let mut fields: Vec<String> = vec![];
+        for v in scope {
+            if !fields.contains(v) && e.ref_appears_free_in(v) {
+                fields.push(v.clone());
+            }
+        }
+        fields
+    }
+
+    /// reserve a case of the dispatcher, returns its tag.
+    fn case(&mut self, lam: bool, fields: &[String], param: Option<&str>, source: &Exp) -> usize {
+        let cases = if lam {
+            &mut self.apply
+        } else {
+            &mut self.force
+        };
+        cases.push(Case {
+            tag: cases.len(),
+            fields: fields.to_vec(),
+            param: param.map(String::from),
+            body: Term::True,
+            source: source.clone(),
+        });
+        cases.len() - 1
+    }
+
+    fn lambda(&mut self, e: &Exp, arg: &str, body: &Exp, scope: &[String]) -> Term {
+        let fields = Self::fields(e, scope);
+        let tag = self.case(true, &fields, Some(arg), e);
+        let mut inner = vec![arg.to_string()];
+        inner.extend_from_slice(scope);
+        self.apply[tag].body = self.convert(body, &inner);
+        let fields = fields.into_iter().map(Term::Var).collect();
+        Term::Lam { tag, fields }
+    }
+
+    fn delay(&mut self, e: &Exp, scope: &[String]) -> Term {
+        let fields = Self::fields(e, scope);
+        let tag = self.case(false, &fields, None, e);
+        self.force[tag].body = self.convert(e, scope);
+        let fields = fields.into_iter().map(Term::Var).collect();
+        Term::Delay { tag, fields }
+    }
+
+    fn convert(&mut self, e: &Exp, scope: &[String]) -> Term {
+        let by_name = self.by_name;
+        let mut go = |e: &Exp| Box::new(self.convert(e, scope));
+        match e {
+            Exp::Var(v) if by_name => Term::Force(Box::new(Term::Var(v.clone()))),
+            Exp::Var(v) => Term::Var(v.clone()),
+            Exp::Lambda(lambda) => self.lambda(e, &lambda.arg, &lambda.exp, scope),
+            Exp::App(app) if by_name => {
+                let t1 = go(&app.t1);
+                let t2 = match &app.t2 {
+                    // nothing to delay for a value, and a variable is passed along as it is
+                    Exp::Var(v) => Term::Var(v.clone()),
+                    t2 if t2.ref_is_value() => *go(t2),
+                    t2 => self.delay(t2, scope),
+                };
+                Term::Apply(t1, Box::new(t2))
+            }
+            Exp::App(app) => Term::Apply(go(&app.t1), go(&app.t2)),
+            Exp::Cond(cond) => Term::Cond(go(&cond.r#if), go(&cond.r#then), go(&cond.r#else)),
+            Exp::True => Term::True,
+            Exp::False => Term::False,
+            Exp::Nat(n) => Term::Nat(*n),
+            Exp::IsZero(t) => Term::IsZero(go(t)),
+            Exp::Incr(t) => Term::Incr(go(t)),
+            Exp::Decr(t) => Term::Decr(go(t)),
+            Exp::Add(add) => Term::Add(go(&add.t1), go(&add.t2)),
+        }
+    }
+}
+
+/// A value of the evaluator, i.e., a number, a boolean or a record.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nat(u32),
+    Bool(bool),
+    Lam(usize, Rc<Vec<Value>>),
+    Delay(usize, Rc<Vec<Value>>),
+}
+
+/// the variables bound in the current case, i.e., the fields and the argument.
+type Bindings = Vec<(String, Value)>;
+
+fn bind(case: &Case, fields: &[Value], arg: Option<Value>) -> Bindings {
+    let mut env: Bindings = case
+        .fields
+        .iter()
+        .cloned()
+        .zip(fields.iter().cloned())
+        .collect();
+    if let (Some(param), Some(arg)) = (&case.param, arg) {
+        env.push((param.clone(), arg));
+    }
+    env
+}
+
+impl Target for Program {
+    type Code = Term;
+    type Env = Bindings;
+    type Value = Value;
+
+    fn view(t: &Term) -> View<'_, Term> {
+        match t {
+            Term::Var(_) => View::Var,
+            Term::Lam { fields, .. } | Term::Delay { fields, .. } => View::Record(fields),
+            Term::Apply(t1, t2) => View::Apply(t1, t2),
+            Term::Force(t) => View::Force(t),
+            Term::Cond(t1, t2, t3) => View::Cond(t1, t2, t3),
+            Term::True => View::True,
+            Term::False => View::False,
+            Term::Nat(n) => View::Nat(*n),
+            Term::IsZero(t) => View::IsZero(t),
+            Term::Incr(t) => View::Incr(t),
+            Term::Decr(t) => View::Decr(t),
+            Term::Add(t1, t2) => View::Add(t1, t2),
+        }
+    }
+
+    /// the innermost one, i.e., the argument, shadows the fields.
+    fn lookup(&self, var: &Term, env: &Bindings) -> Result<Value> {
+        let Term::Var(v) = var else {
+            unreachable!("viewed as a variable")
+        };
+        match env.iter().rev().find(|(name, _)| name == v) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(StlcError::InvalidExpression(v.clone())),
+        }
+    }
+
+    fn record(&self, record: &Term, fields: Vec<Value>) -> Value {
+        match record {
+            Term::Lam { tag, .. } => Value::Lam(*tag, Rc::new(fields)),
+            Term::Delay { tag, .. } => Value::Delay(*tag, Rc::new(fields)),
+            _ => unreachable!("viewed as a record"),
+        }
+    }
+
+    fn is_function(f: &Value) -> bool {
+        matches!(f, Value::Lam(..))
+    }
+
+    /// i.e., the case of `apply` for the tag.
+    fn call(&self, f: Value, arg: Value) -> (&Term, Bindings) {
+        let Value::Lam(tag, fields) = f else {
+            unreachable!("checked by `is_function`")
+        };
+        let case = &self.apply[tag];
+        (&case.body, bind(case, &fields, Some(arg)))
+    }
+
+    /// i.e., the case of `force` for the tag.
+    fn force(&self, value: Value) -> std::result::Result<(&Term, Bindings), Value> {
+        let Value::Delay(tag, fields) = value else {
+            return Err(value);
+        };
+        let case = &self.force[tag];
+        Ok((&case.body, bind(case, &fields, None)))
+    }
+
+    fn nat(value: &Value) -> Option<u32> {
+        match value {
+            Value::Nat(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn bool(value: &Value) -> Option<bool> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn from_nat(n: u32) -> Value {
+        Value::Nat(n)
+    }
+
+    fn from_bool(b: bool) -> Value {
+        Value::Bool(b)
+    }
+
+    fn read_back(&self, value: &Value) -> Exp {
+        Program::read_back(self, value)
+    }
+
+    /// i.e., with the bound variables substituted.
+    fn read_back_code(&self, t: &Term, env: &Bindings) -> Exp {
+        let go = |t: &Term| self.read_back_code(t, env);
+        let close = |case: &Case, fields: &[Term]| {
+            let substs: Vec<(String, Exp)> = case
+                .fields
+                .iter()
+                .zip(fields)
+                .map(|(v, t)| (v.clone(), go(t)))
+                .collect();
+            case.source.clone().substitute_many(&substs)
+        };
+        match t {
+            Term::Var(v) => match env.iter().rev().find(|(name, _)| name == v) {
+                Some((_, value)) => self.read_back(value),
+                None => Exp::Var(v.clone()),
+            },
+            Term::Lam { tag, fields } => close(&self.apply[*tag], fields),
+            Term::Delay { tag, fields } => close(&self.force[*tag], fields),
+            Term::Apply(t1, t2) => App::build(go(t1), go(t2)),
+            Term::Force(t) => go(t),
+            Term::Cond(t1, t2, t3) => Cond::build(go(t1), go(t2), go(t3)),
+            Term::True => Exp::True,
+            Term::False => Exp::False,
+            Term::Nat(n) => Exp::Nat(*n),
+            Term::IsZero(t) => Exp::IsZero(Box::new(go(t))),
+            Term::Incr(t) => Exp::Incr(Box::new(go(t))),
+            Term::Decr(t) => Exp::Decr(Box::new(go(t))),
+            Term::Add(t1, t2) => Add::build(go(t1), go(t2)),
+        }
+    }
+
+    fn stuck(&self, e: Exp) -> StlcError {
+        match self.strategy {
+            Strategy::CallByValue => StlcError::StuckExpressionCbv(format!("{}", e)),
+            _ => StlcError::StuckExpressionCbn(format!("{}", e)),
+        }
+    }
+}
+
+impl Program {
+    /// convert back to an `Exp`, i.e., the source of a record
+    /// with its fields substituted.
+    pub fn read_back(&self, value: &Value) -> Exp {
+        let close = |case: &Case, fields: &[Value]| {
+            let substs: Vec<(String, Exp)> = case
+                .fields
+                .iter()
+                .zip(fields)
+                .map(|(v, value)| (v.clone(), self.read_back(value)))
+                .collect();
+            case.source.clone().substitute_many(&substs)
+        };
+        match value {
+            Value::Nat(n) => Exp::Nat(*n),
+            Value::Bool(true) => Exp::True,
+            Value::Bool(false) => Exp::False,
+            Value::Lam(tag, fields) => close(&self.apply[*tag], fields),
+            Value::Delay(tag, fields) => close(&self.force[*tag], fields),
+        }
+    }
+
+    /// evaluate the main term, returns the value with the number of
+    /// reductions, i.e., the same as `ref_eval_to_normal_form`.
+    pub fn run(&self) -> Result<(Exp, u32)> {
+        self.run_with(&EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// `run` within the limits of `config` but the size & the cycles,
+    /// reporting the code being reduced, read back like a value.
+    pub fn run_with(&self, config: &EvalConfig) -> Result<(Exp, u32)> {
+        let (value, steps) = Evaluator::new(self, config).run(&self.main, &vec![])?;
+        Ok((self.read_back(&value), steps))
+    }
+}
+
+impl Exp {
+    /// defunctionalize for `strategy`.
+    /// note: only call-by-value and call-by-name are supported.
+    pub fn ref_defunctionalize(&self, strategy: Strategy) -> Result<Program> {
+        if !matches!(strategy, Strategy::CallByValue | Strategy::CallByName) {
+            return Err(StlcError::InvalidExpression(format!(
+                "{} is not supported by defunctionalization",
+                strategy
+            )));
+        }
+        let mut defunc = Defunc {
+            by_name: strategy == Strategy::CallByName,
+            apply: vec![],
+            force: vec![],
+        };
+        let main = defunc.convert(self, &[]);
+        Ok(Program {
+            strategy,
+            apply: defunc.apply,
+            force: defunc.force,
+            main,
+            source: self.clone(),
+        })
+    }
+}
@@ -0,0 +1,294 @@
+//! Big-step (natural) semantics for call-by-value, i.e., `t ⇓ v`,
+//! by substitution, e.g.,
+//!
+//! ```text
+//!   λx. incr x ⇓ λx. incr x      1 ⇓ 1      incr 1 ⇓ 2
+//! ---------------------------------------------------- B-App
+//!                  (λx. incr x) 1 ⇓ 2
+//! ```
+//!
+//! Unlike `bigstep` (with environments & closures), every premise here is
+//! a judgement about closed terms, the same ones the small-step evaluator
+//! goes through.
+//!
+//! The derivation is built with an explicit stack (of the judgements
+//! under construction), so that a deep one does not overflow the stack.
+
+use core::fmt;
+
+use super::{latex, text, Judgement, ProofTree};
+use crate::{
+    alpha::AlphaCanonical,
+    expr::{add::Add, app::App},
+    limits::{EvalConfig, Limits},
+    nameless::Term,
+    stlc_err::StlcError,
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The evaluation rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EvalRule {
+    /// `v ⇓ v`
+    Value,
+    App,
+    IfTrue,
+    IfFalse,
+    IsZeroZero,
+    IsZeroSucc,
+    Incr,
+    Decr,
+    Add,
+}
+
+impl fmt::Display for EvalRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EvalRule::Value => "Value",
+            EvalRule::App => "App",
+            EvalRule::IfTrue => "IfTrue",
+            EvalRule::IfFalse => "IfFalse",
+            EvalRule::IsZeroZero => "IsZeroZero",
+            EvalRule::IsZeroSucc => "IsZeroSucc",
+            EvalRule::Incr => "Incr",
+            EvalRule::Decr => "Decr",
+            EvalRule::Add => "Add",
+        };
+        write!(f, "B-{}", name)
+    }
+}
+
+/// `t ⇓ v`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    pub term: Exp,
+    pub value: Exp,
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ⇓ {}", text(&self.term), text(&self.value))
+    }
+}
+
+impl Judgement for Evaluation {
+    fn to_latex(&self) -> String {
+        format!(
+            "{} \\Downarrow {}",
+            latex(&text(&self.term)),
+            latex(&text(&self.value))
+        )
+    }
+}
+
+/// the evaluation derivation.
+pub type EvalDerivation = ProofTree<EvalRule, Evaluation>;
+
+impl EvalDerivation {
+    /// the value, i.e., the right hand side of the conclusion.
+    pub fn value(&self) -> &Exp {
+        &self.conclusion.value
+    }
+
+    /// the number of reductions, i.e., every rule but `B-Value`,
+    /// the same as the small-step evaluator counts.
+    pub fn steps(&self) -> u32 {
+        let steps: u32 = self.premises.iter().map(EvalDerivation::steps).sum();
+        match self.rule {
+            EvalRule::Value => steps,
+            _ => steps + 1,
+        }
+    }
+
+    /// whether iterating `ref_eval_one_step_cbv` on the term reaches
+    /// the same value, in the same number of steps.
+    pub fn agrees_with_small_step(&self) -> bool {
+        let mut e = self.conclusion.term.clone();
+        for _ in 0..self.steps() {
+            match e.ref_eval_one_step_cbv() {
+                Ok(next) => e = next,
+                Err(_) => return false,
+            }
+        }
+        e.ref_is_value() && e == *self.value()
+    }
+}
+
+/// What a judgement under construction needs next.
+enum Next {
+    /// the derivation of another premise, `true` if it takes a step,
+    /// i.e., the body of a function, or the branch chosen.
+    Premise(Exp, bool),
+    /// the value, `true` if the rule itself takes a step.
+    Conclude(EvalRule, Exp, bool),
+}
+
+/// A judgement under construction, i.e., its premises derived so far.
+struct Frame {
+    term: Exp,
+    premises: Vec<EvalDerivation>,
+    /// the steps taken before it, and its alpha-invariant key, if any,
+    /// to detect a term whose derivation needs itself.
+    steps: u32,
+    key: Option<Term>,
+}
+
+impl Frame {
+    fn next(&self) -> Result<Next> {
+        let values: Vec<&Exp> = self.premises.iter().map(EvalDerivation::value).collect();
+        let next = match (&self.term, values.as_slice()) {
+            (Exp::Var(v), _) => return Err(StlcError::InvalidExpression(v.clone())),
+            (e, _) if e.ref_is_value() => Next::Conclude(EvalRule::Value, e.clone(), false),
+            //   t1 ⇓ λx. t12    t2 ⇓ v2    [x := v2] t12 ⇓ v
+            // -------------------------------------------------
+            //                  t1 t2 ⇓ v
+            // note: `t1` is checked before `t2` is evaluated,
+            // the same as the small-step evaluator gets stuck.
+            (Exp::App(app), []) => Next::Premise(app.t1.clone(), false),
+            (Exp::App(app), [Exp::Lambda(_)]) => Next::Premise(app.t2.clone(), false),
+            (Exp::App(app), [v1]) => {
+                return Err(StlcError::StuckExpressionCbv(format!(
+                    "{}",
+                    App::build((*v1).clone(), app.t2.clone())
+                )))
+            }
+            (Exp::App(_), [Exp::Lambda(lambda), v2]) => Next::Premise(
+                lambda
+                    .exp
+                    .clone()
+                    .ref_substitute(lambda.arg.clone(), (*v2).clone()),
+                true,
+            ),
+            (Exp::App(_), [_, _, v]) => Next::Conclude(EvalRule::App, (*v).clone(), false),
+            //  t1 ⇓ true    t2 ⇓ v          t1 ⇓ false    t3 ⇓ v
+            // ------------------------     -------------------------
+            // if t1 then t2 else t3 ⇓ v    if t1 then t2 else t3 ⇓ v
+            (Exp::Cond(cond), []) => Next::Premise(cond.r#if.clone(), false),
+            (Exp::Cond(cond), [Exp::True]) => Next::Premise(cond.r#then.clone(), true),
+            (Exp::Cond(cond), [Exp::False]) => Next::Premise(cond.r#else.clone(), true),
+            (Exp::Cond(_), [v]) => return Err(StlcError::non_boolean_if(v)),
+            (Exp::Cond(_), [Exp::True, v]) => Next::Conclude(EvalRule::IfTrue, (*v).clone(), false),
+            (Exp::Cond(_), [_, v]) => Next::Conclude(EvalRule::IfFalse, (*v).clone(), false),
+            (Exp::IsZero(t) | Exp::Incr(t) | Exp::Decr(t), []) => {
+                Next::Premise((**t).clone(), false)
+            }
+            (Exp::IsZero(_), [Exp::Nat(0)]) => {
+                Next::Conclude(EvalRule::IsZeroZero, Exp::True, true)
+            }
+            (Exp::IsZero(_), [Exp::Nat(_)]) => {
+                Next::Conclude(EvalRule::IsZeroSucc, Exp::False, true)
+            }
+            (Exp::Incr(_), [Exp::Nat(n)]) => {
+                Next::Conclude(EvalRule::Incr, Exp::Nat(n.saturating_add(1)), true)
+            }
+            (Exp::Decr(_), [Exp::Nat(n)]) => {
+                Next::Conclude(EvalRule::Decr, Exp::Nat(n.saturating_sub(1)), true)
+            }
+            (Exp::IsZero(_) | Exp::Incr(_) | Exp::Decr(_), [v]) => {
+                return Err(StlcError::InvalidExpression(format!("{}", v)))
+            }
+            (Exp::Add(add), []) => Next::Premise(add.t1.clone(), false),
+            (Exp::Add(add), [Exp::Nat(_)]) => Next::Premise(add.t2.clone(), false),
+            (Exp::Add(_), [Exp::Nat(n1), Exp::Nat(n2)]) => {
+                Next::Conclude(EvalRule::Add, Exp::Nat(n1.saturating_add(*n2)), true)
+            }
+            (Exp::Add(add), [v1]) => {
+                return Err(StlcError::StuckExpressionCbv(format!(
+                    "{}",
+                    Add::build((*v1).clone(), add.t2.clone())
+                )))
+            }
+            (Exp::Add(_), [v1, v2]) => {
+                return Err(StlcError::StuckExpressionCbv(format!(
+                    "{}",
+                    Add::build((*v1).clone(), (*v2).clone())
+                )))
+            }
+            _ => unreachable!("every premise is derived in order"),
+        };
+        Ok(next)
+    }
+}
+
+fn derive(e: &Exp, config: &EvalConfig) -> Result<EvalDerivation> {
+    // the cycles are the judgements under construction, see below
+    let window = config.cycle_window;
+    let config = EvalConfig {
+        cycle_window: 0,
+        ..config.clone()
+    };
+    let mut limits = Limits::new(&config);
+    let mut steps = 0;
+    let frame = |term: Exp, steps: u32| Frame {
+        key: (window > 0 && !term.ref_is_value()).then(|| term.alpha_key()),
+        term,
+        premises: vec![],
+        steps,
+    };
+    let mut stack = vec![frame(e.clone(), 0)];
+    loop {
+        let (term, step) = match stack.last().unwrap().next()? {
+            Next::Premise(term, step) => (term, step),
+            Next::Conclude(rule, value, step) => {
+                if step {
+                    let term = &stack.last().unwrap().term;
+                    limits.check(term, steps, || term.clone())?;
+                    steps += 1;
+                }
+                let frame = stack.pop().unwrap();
+                let tree = ProofTree::new(
+                    rule,
+                    Evaluation {
+                        term: frame.term,
+                        value,
+                    },
+                    frame.premises,
+                );
+                match stack.last_mut() {
+                    Some(parent) => parent.premises.push(tree),
+                    None => return Ok(tree),
+                }
+                continue;
+            }
+        };
+        if step {
+            limits.check(&term, steps, || term.clone())?;
+            steps += 1;
+        }
+        let next = frame(term, steps);
+        // a term whose derivation needs itself (up to alpha-equivalence)
+        // has no (finite) derivation
+        if let Some(key) = &next.key {
+            let seen = stack
+                .iter()
+                .rev()
+                .take(window)
+                .find(|f| f.key.as_ref() == Some(key));
+            if let Some(seen) = seen {
+                return Err(StlcError::Diverges {
+                    cycle: steps - seen.steps,
+                    term: next.term,
+                });
+            }
+        }
+        stack.push(next);
+    }
+}
+
+impl Exp {
+    /// evaluate by call-by-value, returns the derivation of `self ⇓ v`,
+    /// see `ref_eval_derivation_with` for the limits.
+    pub fn ref_eval_derivation(&self) -> Result<EvalDerivation> {
+        self.ref_eval_derivation_with(&EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// the same as `ref_eval_derivation` within `config`, where the steps
+    /// are the reductions (see `EvalDerivation::steps`) taken so far,
+    /// and a cycle is a judgement needing itself, e.g., `ω ⇓ v`.
+    pub fn ref_eval_derivation_with(&self, config: &EvalConfig) -> Result<EvalDerivation> {
+        derive(self, config)
+    }
+}
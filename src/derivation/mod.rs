@@ -0,0 +1,174 @@
+//! Derivation trees, i.e., a judgement together with the rule concluding
+//! it from its premises, rendered as
+//!
+//! - an indented ASCII tree, the conclusion first, e.g.,
+//!   ```text
+//!   ⊢ (λx: int. incr x) 1 : int    [T-App]
+//!     ⊢ λx: int. incr x : int → int    [T-Abs]
+//!       x: int ⊢ incr x : int    [T-Incr]
+//!         x: int ⊢ x : int    [T-Var]
+//!     ⊢ 1 : int    [T-Num]
+//!   ```
+//! - LaTeX, either for `bussproofs` (i.e., `prooftree`) or `mathpartir`.
+
+use core::fmt;
+
+use crate::{pretty::PrettyConfig, Exp};
+
+/// the big-step evaluation derivations.
+pub mod eval;
+
+/// the typing derivations.
+pub mod typing;
+
+/// The conclusion of a rule, e.g., `Γ ⊢ t : T`.
+pub trait Judgement: fmt::Display {
+    /// the judgement in LaTeX (math mode).
+    fn to_latex(&self) -> String;
+}
+
+/// A derivation, i.e., `conclusion` by `rule` from `premises`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofTree<R, J> {
+    pub rule: R,
+    pub conclusion: J,
+    pub premises: Vec<ProofTree<R, J>>,
+}
+
+/// a term on a single line, see `PrettyConfig`.
+/// note: the width is measured as `isize` by the layout algorithm.
+pub(crate) fn text(e: &Exp) -> String {
+    e.pretty(&PrettyConfig::new(true, isize::MAX as usize))
+}
+
+/// the text (of a term or a type) in LaTeX, i.e., typewriter
+/// with the special characters escaped.
+pub(crate) fn latex(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            'λ' => out.push_str("\\lambda "),
+            '→' => out.push_str("\\to "),
+            ' ' => out.push_str("\\ "),
+            '\\' => out.push_str("\\backslash "),
+            '_' | '{' | '}' | '#' | '$' | '%' | '&' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    format!("\\mathtt{{{}}}", out)
+}
+
+impl<R: fmt::Display, J: Judgement> ProofTree<R, J> {
+    pub fn new(rule: R, conclusion: J, premises: Vec<ProofTree<R, J>>) -> Self {
+        Self {
+            rule,
+            conclusion,
+            premises,
+        }
+    }
+
+    /// the number of rules applied.
+    pub fn size(&self) -> usize {
+        1 + self.premises.iter().map(ProofTree::size).sum::<usize>()
+    }
+
+    /// the length of the longest path to an axiom, i.e., 1 for an axiom.
+    pub fn height(&self) -> usize {
+        1 + self
+            .premises
+            .iter()
+            .map(ProofTree::height)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// every rule applied, in preorder.
+    pub fn rules(&self) -> Vec<&R> {
+        let mut rules = vec![&self.rule];
+        for premise in &self.premises {
+            rules.extend(premise.rules());
+        }
+        rules
+    }
+
+    fn fmt_ascii(&self, indent: usize, out: &mut String) {
+        out.push_str(&format!(
+            "{}{}    [{}]\n",
+            " ".repeat(indent),
+            self.conclusion,
+            self.rule
+        ));
+        for premise in &self.premises {
+            premise.fmt_ascii(indent + 2, out);
+        }
+    }
+
+    /// the indented ASCII tree, see the module level documentation.
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::new();
+        self.fmt_ascii(0, &mut out);
+        out
+    }
+
+    fn fmt_bussproofs(&self, out: &mut String) {
+        for premise in &self.premises {
+            premise.fmt_bussproofs(out);
+        }
+        let inference = match self.premises.len() {
+            0 => {
+                out.push_str("\\AxiomC{}\n");
+                "UnaryInfC"
+            }
+            1 => "UnaryInfC",
+            2 => "BinaryInfC",
+            3 => "TrinaryInfC",
+            4 => "QuaternaryInfC",
+            _ => "QuinaryInfC",
+        };
+        out.push_str(&format!("\\RightLabel{{\\scriptsize {}}}\n", self.rule));
+        out.push_str(&format!(
+            "\\{}{{${}$}}\n",
+            inference,
+            self.conclusion.to_latex()
+        ));
+    }
+
+    /// LaTeX for the `bussproofs` package.
+    /// note: `bussproofs` takes at most five premises per rule.
+    pub fn to_bussproofs(&self) -> String {
+        let mut out = String::from("\\begin{prooftree}\n");
+        self.fmt_bussproofs(&mut out);
+        out.push_str("\\end{prooftree}\n");
+        out
+    }
+
+    fn fmt_mathpartir(&self, indent: usize, out: &mut String) {
+        let pad = " ".repeat(indent);
+        out.push_str(&format!(
+            "{}\\inferrule*[right={}]\n{}{{",
+            pad, self.rule, pad
+        ));
+        if !self.premises.is_empty() {
+            out.push('\n');
+            for (i, premise) in self.premises.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(&format!("{}  \\\\\n", pad));
+                }
+                premise.fmt_mathpartir(indent + 2, out);
+            }
+            out.push_str(&pad);
+        }
+        out.push_str(&format!("}}\n{}{{{}}}\n", pad, self.conclusion.to_latex()));
+    }
+
+    /// LaTeX for the `mathpartir` package.
+    pub fn to_mathpartir(&self) -> String {
+        let mut out = String::from("\\begin{mathpar}\n");
+        self.fmt_mathpartir(0, &mut out);
+        out.push_str("\\end{mathpar}\n");
+        out
+    }
+}
@@ -0,0 +1,165 @@
+//! Typing derivations for (well-typed) terms, by the rules of the handouts,
+//! i.e., the ones `ref_ty_check` follows.
+
+use core::fmt;
+
+use super::{latex, text, Judgement, ProofTree};
+use crate::{
+    pretty::PrettyConfig,
+    type_::{check::TyError, Env, Type},
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, TyError>;
+
+/// The typing rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TyRule {
+    True,
+    False,
+    Num,
+    Var,
+    Abs,
+    App,
+    If,
+    IsZero,
+    Incr,
+    Decr,
+    Add,
+}
+
+impl fmt::Display for TyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TyRule::True => "True",
+            TyRule::False => "False",
+            TyRule::Num => "Num",
+            TyRule::Var => "Var",
+            TyRule::Abs => "Abs",
+            TyRule::App => "App",
+            TyRule::If => "If",
+            TyRule::IsZero => "IsZero",
+            TyRule::Incr => "Incr",
+            TyRule::Decr => "Decr",
+            TyRule::Add => "Add",
+        };
+        write!(f, "T-{}", name)
+    }
+}
+
+/// `Γ ⊢ t : T`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Typing {
+    pub context: Env,
+    pub term: Exp,
+    pub ty: Type,
+}
+
+impl fmt::Display for Typing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = PrettyConfig::default();
+        for (i, (v, ty)) in self.context.bindings().iter().enumerate() {
+            let sep = if i > 0 { ", " } else { "" };
+            write!(f, "{}{}: {}", sep, v, ty.pretty(&config))?;
+        }
+        if !self.context.bindings().is_empty() {
+            write!(f, " ")?;
+        }
+        write!(f, "⊢ {} : {}", text(&self.term), self.ty.pretty(&config))
+    }
+}
+
+impl Judgement for Typing {
+    fn to_latex(&self) -> String {
+        let config = PrettyConfig::default();
+        let context: Vec<String> = self
+            .context
+            .bindings()
+            .iter()
+            .map(|(v, ty)| format!("{} : {}", latex(v), latex(&ty.pretty(&config))))
+            .collect();
+        let context = match context.is_empty() {
+            true => String::new(),
+            false => format!("{} ", context.join(", ")),
+        };
+        format!(
+            "{}\\vdash {} : {}",
+            context,
+            latex(&text(&self.term)),
+            latex(&self.ty.pretty(&config))
+        )
+    }
+}
+
+/// the typing derivation.
+pub type TyDerivation = ProofTree<TyRule, Typing>;
+
+/// the derivation of `env ⊢ e : ty`, which is known to hold.
+fn derive(e: &Exp, ty: &Type, env: &Env) -> TyDerivation {
+    let int = |t: &Exp| derive(t, &Type::TInt, env);
+    let (rule, premises) = match e {
+        Exp::True => (TyRule::True, vec![]),
+        Exp::False => (TyRule::False, vec![]),
+        Exp::Nat(_) => (TyRule::Num, vec![]),
+        Exp::Var(_) => (TyRule::Var, vec![]),
+        Exp::Lambda(lambda) => {
+            let Type::TArrow(t) = ty else {
+                unreachable!("a lambda abstraction has an arrow type");
+            };
+            let mut inner = env.clone();
+            inner.insert(lambda.arg.clone(), t.ty1.clone());
+            (TyRule::Abs, vec![derive(&lambda.exp, &t.ty2, &inner)])
+        }
+        Exp::App(app) => {
+            // the function type is always synthesized, see `check`
+            let t1 = app
+                .t1
+                .ty_synth(env)
+                .expect("the function type is synthesized by the checker");
+            let Type::TArrow(t) = &t1 else {
+                unreachable!("the function has an arrow type");
+            };
+            (
+                TyRule::App,
+                vec![derive(&app.t1, &t1, env), derive(&app.t2, &t.ty1, env)],
+            )
+        }
+        Exp::Cond(cond) => (
+            TyRule::If,
+            vec![
+                derive(&cond.r#if, &Type::TBool, env),
+                derive(&cond.r#then, ty, env),
+                derive(&cond.r#else, ty, env),
+            ],
+        ),
+        Exp::IsZero(t) => (TyRule::IsZero, vec![int(t)]),
+        Exp::Incr(t) => (TyRule::Incr, vec![int(t)]),
+        Exp::Decr(t) => (TyRule::Decr, vec![int(t)]),
+        Exp::Add(add) => (TyRule::Add, vec![int(&add.t1), int(&add.t2)]),
+    };
+    ProofTree::new(
+        rule,
+        Typing {
+            context: env.clone(),
+            term: e.clone(),
+            ty: ty.clone(),
+        },
+        premises,
+    )
+}
+
+impl Exp {
+    /// the typing derivation of the current expression under `env`,
+    /// checked against `ty` if any, otherwise synthesized,
+    /// i.e., `ty_check_against` (or `ty_synth`) with the evidence.
+    pub fn ref_ty_derivation(&self, ty: Option<&Type>, env: &Env) -> Result<TyDerivation> {
+        let ty = match ty {
+            Some(ty) => {
+                self.ty_check_against(ty, env)?;
+                ty.clone()
+            }
+            None => self.ty_synth(env)?,
+        };
+        Ok(derive(self, &ty, env))
+    }
+}
@@ -0,0 +1,250 @@
+//! Graphviz (DOT) export, i.e., the syntax tree of a term, and the graph
+//! of every one-step reduction from it, e.g., `dot -Tsvg` on
+//! `(λx. λy. incr y) ω 1` shows the call-by-value edge looping back to
+//! the term itself, next to the call-by-name path down to `2`.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    alpha::Alpha,
+    expr::{add::Add, app::App, cond::Cond, lambda::Lambda, path::Path},
+    Exp, Strategy,
+};
+
+/// escape a label, i.e., `"` and `\`.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn label(e: &Exp) -> String {
+    match e {
+        Exp::Var(v) => v.clone(),
+        Exp::Lambda(lambda) => match &lambda.ty {
+            Some(ty) => format!("λ{}: {}", lambda.arg, ty),
+            None => format!("λ{}", lambda.arg),
+        },
+        Exp::App(_) => "app".to_string(),
+        Exp::Cond(_) => "if".to_string(),
+        Exp::True => "true".to_string(),
+        Exp::False => "false".to_string(),
+        Exp::Nat(n) => n.to_string(),
+        Exp::IsZero(_) => "is_zero".to_string(),
+        Exp::Incr(_) => "incr".to_string(),
+        Exp::Decr(_) => "decr".to_string(),
+        Exp::Add(_) => "+".to_string(),
+    }
+}
+
+fn ast_nodes(e: &Exp, next: &mut usize, out: &mut String) -> usize {
+    let id = *next;
+    *next += 1;
+    out.push_str(&format!("  n{} [label=\"{}\"];\n", id, escape(&label(e))));
+    let names: &[&str] = match e {
+        Exp::Cond(_) => &["if", "then", "else"],
+        _ => &[],
+    };
+    for (i, child) in e.children().into_iter().enumerate() {
+        let child = ast_nodes(child, next, out);
+        match names.get(i) {
+            Some(name) => out.push_str(&format!("  n{} -> n{} [label=\"{}\"];\n", id, child, name)),
+            None => out.push_str(&format!("  n{} -> n{};\n", id, child)),
+        }
+    }
+    id
+}
+
+impl Exp {
+    /// the syntax tree in DOT, one node per subterm in preorder,
+    /// i.e., `n0` is the whole term.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph ast {\n  node [shape=box];\n");
+        ast_nodes(self, &mut 0, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    /// `self` with its `i`-th child (see `Path`) replaced.
+    fn with_child(&self, i: usize, child: Exp) -> Exp {
+        match (self, i) {
+            (Exp::Lambda(lambda), 0) => Lambda {
+                arg: lambda.arg.clone(),
+                exp: child,
+                ty: lambda.ty.clone(),
+            }
+            .into(),
+            (Exp::App(app), 0) => App::build(child, app.t2.clone()),
+            (Exp::App(app), 1) => App::build(app.t1.clone(), child),
+            (Exp::Add(add), 0) => Add::build(child, add.t2.clone()),
+            (Exp::Add(add), 1) => Add::build(add.t1.clone(), child),
+            (Exp::Cond(cond), 0) => Cond::build(child, cond.r#then.clone(), cond.r#else.clone()),
+            (Exp::Cond(cond), 1) => Cond::build(cond.r#if.clone(), child, cond.r#else.clone()),
+            (Exp::Cond(cond), 2) => Cond::build(cond.r#if.clone(), cond.r#then.clone(), child),
+            (Exp::IsZero(_), 0) => Exp::IsZero(Box::new(child)),
+            (Exp::Incr(_), 0) => Exp::Incr(Box::new(child)),
+            (Exp::Decr(_), 0) => Exp::Decr(Box::new(child)),
+            _ => self.clone(),
+        }
+    }
+
+    /// every one-step reduction, at any position (under lambdas as well),
+    /// with the path to its redex, from the outermost one.
+    pub fn ref_reducts(&self) -> Vec<(Path, Exp)> {
+        let mut reducts = vec![];
+        let root = match self {
+            // any argument, i.e., full beta-reduction
+            Exp::App(app) => match &app.t1 {
+                Exp::Lambda(lambda) => Some(
+                    lambda
+                        .exp
+                        .clone()
+                        .ref_substitute(lambda.arg.clone(), app.t2.clone()),
+                ),
+                _ => None,
+            },
+            Exp::Cond(cond) => match cond.r#if {
+                Exp::True => Some(cond.r#then.clone()),
+                Exp::False => Some(cond.r#else.clone()),
+                _ => None,
+            },
+            Exp::IsZero(t) => match **t {
+                Exp::Nat(n) => Some(if n == 0 { Exp::True } else { Exp::False }),
+                _ => None,
+            },
+            Exp::Incr(t) => match **t {
+                Exp::Nat(n) => Some(Exp::Nat(n.saturating_add(1))),
+                _ => None,
+            },
+            Exp::Decr(t) => match **t {
+                Exp::Nat(n) => Some(Exp::Nat(n.saturating_sub(1))),
+                _ => None,
+            },
+            Exp::Add(add) => match (&add.t1, &add.t2) {
+                (Exp::Nat(n1), Exp::Nat(n2)) => Some(Exp::Nat(n1.saturating_add(*n2))),
+                _ => None,
+            },
+            _ => None,
+        };
+        reducts.extend(root.map(|e| (vec![], e)));
+        for (i, child) in self.children().into_iter().enumerate() {
+            for (mut path, e) in child.ref_reducts() {
+                path.insert(0, i);
+                reducts.push((path, self.with_child(i, e)));
+            }
+        }
+        reducts
+    }
+
+    /// explore every reduction from the current expression, breadth first,
+    /// with at most `limit` terms (up to alpha-equivalence).
+    pub fn ref_reduction_graph(&self, limit: usize) -> ReductionGraph {
+        let mut graph = ReductionGraph {
+            nodes: vec![self.clone()],
+            edges: vec![],
+            truncated: false,
+        };
+        let mut index: HashMap<Alpha<Exp>, usize> = HashMap::new();
+        index.insert(Alpha::new(self.clone()), 0);
+        let mut queue = VecDeque::from([0]);
+        while let Some(from) = queue.pop_front() {
+            let e = graph.nodes[from].clone();
+            // the steps the evaluator would take
+            let taken: Vec<(Strategy, Path)> = [Strategy::CallByValue, Strategy::CallByName]
+                .into_iter()
+                .filter(|&strategy| {
+                    !e.ref_is_value() && e.clone().ref_eval_multi_step(1, strategy).is_ok()
+                })
+                .map(|strategy| (strategy, e.ref_redex_path(strategy)))
+                .collect();
+            for (path, reduct) in e.ref_reducts() {
+                let key = Alpha::new(reduct);
+                let to = match index.get(&key) {
+                    Some(&to) => to,
+                    None if graph.nodes.len() < limit => {
+                        graph.nodes.push(key.get().clone());
+                        index.insert(key, graph.nodes.len() - 1);
+                        queue.push_back(graph.nodes.len() - 1);
+                        graph.nodes.len() - 1
+                    }
+                    None => {
+                        graph.truncated = true;
+                        continue;
+                    }
+                };
+                let strategies = taken
+                    .iter()
+                    .filter(|(_, p)| *p == path)
+                    .map(|(strategy, _)| *strategy)
+                    .collect();
+                graph.edges.push(Edge {
+                    from,
+                    to,
+                    path,
+                    strategies,
+                });
+            }
+        }
+        graph
+    }
+}
+
+/// A reduction, i.e., `nodes[from]` reduces to `nodes[to]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    /// the path to the redex, see `Path`.
+    pub path: Path,
+    /// the strategies (call-by-value and/or call-by-name) taking this step.
+    pub strategies: Vec<Strategy>,
+}
+
+/// The reduction graph, `nodes[0]` being the term it starts from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReductionGraph {
+    pub nodes: Vec<Exp>,
+    pub edges: Vec<Edge>,
+    /// whether some reduct is left out because of the limit.
+    pub truncated: bool,
+}
+
+impl ReductionGraph {
+    /// the terms without any reduction, i.e., the normal forms.
+    /// note: a term left unexplored (see `truncated`) has none either.
+    pub fn normal_forms(&self) -> Vec<&Exp> {
+        (0..self.nodes.len())
+            .filter(|&i| self.edges.iter().all(|edge| edge.from != i))
+            .map(|i| &self.nodes[i])
+            .collect()
+    }
+
+    /// the graph in DOT, the normal forms with a double border,
+    /// the call-by-value steps in blue, and the call-by-name ones in red.
+    pub fn to_dot(&self) -> String {
+        let normal: Vec<bool> = (0..self.nodes.len())
+            .map(|i| self.edges.iter().all(|edge| edge.from != i))
+            .collect();
+        let mut out = String::from("digraph reductions {\n  node [shape=box];\n");
+        for (i, e) in self.nodes.iter().enumerate() {
+            let peripheries = if normal[i] { ", peripheries=2" } else { "" };
+            out.push_str(&format!(
+                "  n{} [label=\"{}\"{}];\n",
+                i,
+                escape(&e.to_string()),
+                peripheries
+            ));
+        }
+        for edge in &self.edges {
+            let by_value = edge.strategies.contains(&Strategy::CallByValue);
+            let by_name = edge.strategies.contains(&Strategy::CallByName);
+            let attrs = match (by_value, by_name) {
+                (true, true) => " [label=\"cbv, cbn\", color=\"blue:red\"]",
+                (true, false) => " [label=\"cbv\", color=blue]",
+                (false, true) => " [label=\"cbn\", color=red]",
+                (false, false) => " [color=gray]",
+            };
+            out.push_str(&format!("  n{} -> n{}{};\n", edge.from, edge.to, attrs));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
@@ -24,3 +24,6 @@ pub mod var;
 
 /// add expression
 pub mod add;
+
+/// subterm positions
+pub mod path;
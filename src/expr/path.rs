@@ -0,0 +1,35 @@
+use crate::Exp;
+
+/// The position of a subterm, i.e., the indices of the children
+/// to descend into starting from the root, the indices are
+///
+/// - `Lambda`: 0 for the body
+/// - `App` & `Add`: 0 for `t1`, 1 for `t2`
+/// - `Cond`: 0 for the if clause, 1 for then, 2 for else
+/// - `IsZero`, `Incr` & `Decr`: 0 for the operand
+///
+/// e.g., the path of `y` in `λx. x (incr y)` is `[0, 1, 0]`.
+pub type Path = Vec<usize>;
+
+impl Exp {
+    /// the direct subterms, in the order described in `Path`.
+    pub fn children(&self) -> Vec<&Exp> {
+        match self {
+            Exp::Lambda(lambda) => vec![&lambda.exp],
+            Exp::App(app) => vec![&app.t1, &app.t2],
+            Exp::Add(add) => vec![&add.t1, &add.t2],
+            Exp::Cond(cond) => vec![&cond.r#if, &cond.r#then, &cond.r#else],
+            Exp::IsZero(e) | Exp::Incr(e) | Exp::Decr(e) => vec![e],
+            Exp::Var(_) | Exp::Nat(_) | Exp::True | Exp::False => vec![],
+        }
+    }
+
+    /// the subterm at the given path, if any.
+    pub fn subterm(&self, path: &[usize]) -> Option<&Exp> {
+        let mut e = self;
+        for &i in path {
+            e = *e.children().get(i)?;
+        }
+        Some(e)
+    }
+}
@@ -0,0 +1,231 @@
+//! The evaluator of the first-order programs, i.e., the closure converted
+//! ones of `closure` and the defunctionalized ones of `defunc`.
+//! Both only differ in how a variable is looked up, what a record is,
+//! and how a function is called, see `Target`, while the rest follows
+//! the small-step evaluator, i.e., the same reductions are counted, and
+//! the evaluation gets stuck on the same expression.
+
+use crate::{
+    expr::{add::Add, app::App},
+    limits::{Budget, EvalConfig},
+    stlc_err::StlcError,
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// A piece of code, as far as the evaluator is concerned.
+pub(crate) enum View<'a, C> {
+    /// looked up by `Target::lookup`.
+    Var,
+    /// built by `Target::record` from the values of the fields.
+    Record(&'a [C]),
+    /// call the function with the argument.
+    Apply(&'a C, &'a C),
+    /// evaluate a delayed argument, see `Target::force`.
+    Force(&'a C),
+    Cond(&'a C, &'a C, &'a C),
+    True,
+    False,
+    Nat(u32),
+    IsZero(&'a C),
+    Incr(&'a C),
+    Decr(&'a C),
+    Add(&'a C, &'a C),
+}
+
+/// A first-order program, i.e., its code, its environments & its values.
+pub(crate) trait Target {
+    type Code;
+    type Env;
+    type Value: Clone;
+
+    fn view(code: &Self::Code) -> View<'_, Self::Code>;
+
+    fn lookup(&self, var: &Self::Code, env: &Self::Env) -> Result<Self::Value>;
+
+    fn record(&self, record: &Self::Code, fields: Vec<Self::Value>) -> Self::Value;
+
+    /// whether `f` could be called, checked before evaluating the argument.
+    fn is_function(f: &Self::Value) -> bool;
+
+    /// the body of the function `f` to continue with, and its environment.
+    fn call(&self, f: Self::Value, arg: Self::Value) -> (&Self::Code, Self::Env);
+
+    /// the body of the delayed argument to continue with, and its
+    /// environment, or `value` itself if nothing is delayed.
+    fn force(
+        &self,
+        value: Self::Value,
+    ) -> std::result::Result<(&Self::Code, Self::Env), Self::Value> {
+        Err(value)
+    }
+
+    fn nat(value: &Self::Value) -> Option<u32>;
+
+    fn bool(value: &Self::Value) -> Option<bool>;
+
+    fn from_nat(n: u32) -> Self::Value;
+
+    fn from_bool(b: bool) -> Self::Value;
+
+    fn read_back(&self, value: &Self::Value) -> Exp;
+
+    /// `code` (not evaluated) as an `Exp`, i.e., with `env` substituted.
+    fn read_back_code(&self, code: &Self::Code, env: &Self::Env) -> Exp;
+
+    /// the error of a stuck expression, by the strategy of the program.
+    fn stuck(&self, e: Exp) -> StlcError;
+}
+
+pub(crate) struct Evaluator<'a, P> {
+    program: &'a P,
+    steps: u32,
+    budget: Budget,
+    /// how deep the evaluation of the operands nests.
+    nesting: usize,
+}
+
+impl<'a, P: Target> Evaluator<'a, P> {
+    pub(crate) fn new(program: &'a P, config: &EvalConfig) -> Self {
+        Self {
+            program,
+            steps: 0,
+            budget: Budget::new(config),
+            nesting: 0,
+        }
+    }
+
+    /// evaluate `code` in `env`, returns the value with the number of reductions.
+    pub(crate) fn run(mut self, code: &'a P::Code, env: &P::Env) -> Result<(P::Value, u32)> {
+        let value = self.eval(code, env)?;
+        Ok((value, self.steps))
+    }
+
+    /// count one reduction, `code` being reported.
+    fn tick(&mut self, code: &P::Code, env: &P::Env) -> Result<()> {
+        self.budget
+            .check(self.steps, || self.program.read_back_code(code, env))?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// evaluate an operand, i.e., a nested (non-tail) evaluation.
+    fn nested(&mut self, code: &'a P::Code, env: &P::Env) -> Result<P::Value> {
+        self.budget.check_nesting(self.nesting, self.steps, || {
+            self.program.read_back_code(code, env)
+        })?;
+        self.nesting += 1;
+        let value = self.eval(code, env);
+        self.nesting -= 1;
+        value
+    }
+
+    fn nat(&mut self, code: &'a P::Code, env: &P::Env) -> Result<u32> {
+        let value = self.nested(code, env)?;
+        P::nat(&value).ok_or_else(|| {
+            StlcError::InvalidExpression(format!("{}", self.program.read_back(&value)))
+        })
+    }
+
+    /// note: the calls, the forced arguments and the chosen branches are
+    /// tail calls, which loop instead of recursing, while the operands are
+    /// `nested` within the depth limit.
+    fn eval(&mut self, code: &'a P::Code, env: &P::Env) -> Result<P::Value> {
+        let mut owned: P::Env;
+        let mut env = env;
+        let mut code = code;
+        loop {
+            match P::view(code) {
+                View::Apply(t1, t2) => {
+                    let f = self.nested(t1, env)?;
+                    // the function is checked first, the same as the small-step evaluator
+                    if !P::is_function(&f) {
+                        return Err(self.program.stuck(App::build(
+                            self.program.read_back(&f),
+                            self.program.read_back_code(t2, env),
+                        )));
+                    }
+                    let arg = self.nested(t2, env)?;
+                    self.tick(code, env)?;
+                    (code, owned) = self.program.call(f, arg);
+                    env = &owned;
+                }
+                View::Force(t) => {
+                    let value = self.nested(t, env)?;
+                    match self.program.force(value) {
+                        Ok((body, delayed)) => {
+                            owned = delayed;
+                            env = &owned;
+                            code = body;
+                        }
+                        Err(value) => return Ok(value),
+                    }
+                }
+                View::Cond(t1, t2, t3) => {
+                    let value = self.nested(t1, env)?;
+                    let branch = match P::bool(&value) {
+                        Some(true) => t2,
+                        Some(false) => t3,
+                        None => {
+                            return Err(StlcError::non_boolean_if(self.program.read_back(&value)))
+                        }
+                    };
+                    self.tick(code, env)?;
+                    code = branch;
+                }
+                _ => return self.eval_non_tail(code, env),
+            }
+        }
+    }
+
+    fn eval_non_tail(&mut self, code: &'a P::Code, env: &P::Env) -> Result<P::Value> {
+        match P::view(code) {
+            View::Var => self.program.lookup(code, env),
+            View::Record(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|t| self.nested(t, env))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(self.program.record(code, fields))
+            }
+            View::True => Ok(P::from_bool(true)),
+            View::False => Ok(P::from_bool(false)),
+            View::Nat(n) => Ok(P::from_nat(n)),
+            View::Apply(..) | View::Force(_) | View::Cond(..) => self.eval(code, env),
+            View::IsZero(t) => {
+                let n = self.nat(t, env)?;
+                self.tick(code, env)?;
+                Ok(P::from_bool(n == 0))
+            }
+            View::Incr(t) => {
+                let n = self.nat(t, env)?;
+                self.tick(code, env)?;
+                Ok(P::from_nat(n.saturating_add(1)))
+            }
+            View::Decr(t) => {
+                let n = self.nat(t, env)?;
+                self.tick(code, env)?;
+                Ok(P::from_nat(n.saturating_sub(1)))
+            }
+            View::Add(t1, t2) => {
+                // the first operand is checked before the second one is evaluated
+                let v1 = self.nested(t1, env)?;
+                let Some(n1) = P::nat(&v1) else {
+                    return Err(self.program.stuck(Add::build(
+                        self.program.read_back(&v1),
+                        self.program.read_back_code(t2, env),
+                    )));
+                };
+                let v2 = self.nested(t2, env)?;
+                let Some(n2) = P::nat(&v2) else {
+                    return Err(self
+                        .program
+                        .stuck(Add::build(Exp::Nat(n1), self.program.read_back(&v2))));
+                };
+                self.tick(code, env)?;
+                Ok(P::from_nat(n1.saturating_add(n2)))
+            }
+        }
+    }
+}
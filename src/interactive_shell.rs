@@ -4,6 +4,7 @@
 //! The ultimate goal is to achieve a minimal ghci-like interpreter.
 
 use colored::*;
+use core::fmt;
 use lazy_static::lazy_static;
 use spin::Mutex;
 use std::{
@@ -14,15 +15,44 @@ use std::{
 
 lazy_static! {
     static ref LAMBDA_CONTEXT: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    /// the source text (and spans) of the current expression,
+    /// available only when the *whole* expression is entered via `term`.
+    static ref TERM_SOURCE: Mutex<Option<(String, Spans)>> = Mutex::new(None);
 }
 
 use crate::{
     expr::{
         app::App, cond::Cond, decr::Decr, incr::Incr, is_zero::IsZero, lambda::Lambda, var::Var,
     },
+    limits::EvalConfig,
+    parser::parse_exp_with_spans,
+    pretty::PrettyConfig,
+    span::Spans,
     Exp, Strategy,
 };
 
+/// which implementation evaluates the expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Official,
+    YourOwn,
+    BigStep,
+    Cek,
+    Traced,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Official => write!(f, "official"),
+            Backend::YourOwn => write!(f, "your own"),
+            Backend::BigStep => write!(f, "official big-step"),
+            Backend::Cek => write!(f, "official CEK machine"),
+            Backend::Traced => write!(f, "official small-step"),
+        }
+    }
+}
+
 fn print_list_msg() {
     println!(
         "\ncurrently supported syntax is as below. ({})\n",
@@ -39,6 +69,10 @@ fn print_list_msg() {
     println!("{} e             -- increment", "incr".green());
     println!("{} e             -- decrement", "decr".green());
     println!("{}              -- (λx. x x)", "omega".green());
+    println!(
+        "{} e             -- the whole term as text, e.g., (λx. incr x) 1",
+        "term".green()
+    );
 }
 
 fn read_line() -> String {
@@ -124,6 +158,29 @@ fn parse(mut lhs: String, curr: String, rhs: String) -> Exp {
             parse(lhs, curr, rhs)
         }
         "omega" => Lambda::build("x", App::build(Var::build("x"), Var::build("x"))),
+        "term" => {
+            println!(
+                "\nenter your {} below. (e.g., {} or {})",
+                "term".green().underline(),
+                "(λx. incr x) 1".green().bold(),
+                "λx: int. x + 1".green().bold()
+            );
+            loop {
+                print_prompt();
+                let input = read_line();
+                match parse_exp_with_spans(&input) {
+                    Ok((e, spans)) => {
+                        if curr == "begin" {
+                            *TERM_SOURCE.lock() = Some((input, spans));
+                        }
+                        break e;
+                    }
+                    Err(err) => {
+                        print_out(err.to_string().red(), Color::BrightRed);
+                    }
+                }
+            }
+        }
         "var" => parse("".to_string(), "var".to_string(), "".to_string()),
         "app" => {
             lhs.push_str("(");
@@ -216,6 +273,25 @@ fn parse(mut lhs: String, curr: String, rhs: String) -> Exp {
     }
 }
 
+/// the limits of the official evaluations, so that a runaway term,
+/// e.g., the "grow omega", stops rather than hanging the shell.
+/// note: your own implementation takes no `EvalConfig`, i.e., runs unlimited.
+fn shell_limits() -> EvalConfig {
+    EvalConfig {
+        timeout: Some(Duration::from_secs(10)),
+        max_size: Some(1000000),
+        ..EvalConfig::default()
+    }
+}
+
+/// whether any lambda abstraction in `exp` carries a type annotation.
+fn annotated(exp: &Exp) -> bool {
+    match exp {
+        Exp::Lambda(lambda) if lambda.typed() => true,
+        _ => exp.children().into_iter().any(annotated),
+    }
+}
+
 fn print_statistics(duration: Duration, steps: u32) {
     println!("\n{}", "statistics".bold());
     println!("----");
@@ -232,6 +308,75 @@ fn print_statistics(duration: Duration, steps: u32) {
     println!("----");
 }
 
+/// print the first few states of the CEK machine.
+fn print_cek_trace(exp: &Exp, strategy: Strategy) {
+    const LIMIT: usize = 50;
+    let Ok(trace) = exp.ref_trace_cek(strategy, LIMIT + 1) else {
+        return;
+    };
+    println!("\n{}", "machine states".bold());
+    println!("----");
+    for snapshot in trace.iter().take(LIMIT) {
+        println!("{}", snapshot);
+    }
+    if trace.len() > LIMIT {
+        println!("... (only the first {} states are shown)", LIMIT);
+    }
+    println!("----");
+}
+
+/// print the first few steps with the rules fired, up to where it gets stuck.
+fn print_rule_trace(exp: &Exp, strategy: Strategy) {
+    const LIMIT: usize = 50;
+    println!("\n{}", "steps".bold());
+    println!("----");
+    let mut exp = exp.clone();
+    for _ in 0..LIMIT {
+        if exp.ref_is_value() {
+            break;
+        }
+        match exp.ref_eval_one_step_traced(strategy) {
+            Ok(step) => {
+                println!("{}", step.before);
+                println!("  ⟶ {}", step.derivation().green());
+                exp = step.after;
+            }
+            Err(err) => {
+                println!("{}", exp);
+                println!("  ⟶ {}", err.to_string().red());
+                println!("----");
+                return;
+            }
+        }
+    }
+    println!("{}", exp);
+    if !exp.ref_is_value() {
+        println!("... (only the first {} steps are shown)", LIMIT);
+    }
+    println!("----");
+}
+
+/// compare with call-by-name, i.e., how many steps the sharing saved.
+fn print_need_savings(exp: &Exp, steps: u32) {
+    match exp
+        .clone()
+        .ref_eval_to_normal_form_with(Strategy::CallByName, &shell_limits())
+    {
+        Ok((_, cbn_steps)) => println!(
+            "{}: {} steps compared with call-by-name ({} steps)",
+            "saved".green(),
+            (cbn_steps as i64 - steps as i64).to_string().underline(),
+            cbn_steps
+        ),
+        Err(err) => println!(
+            "{}: call-by-name failed to evaluate it, error: {}",
+            "saved".green(),
+            err
+        ),
+    }
+    println!("----");
+}
+
 pub fn start_interactive_shell() {
     println!("\nCongratulations, the program compiles.");
     println!(
@@ -245,19 +390,37 @@ pub fn start_interactive_shell() {
     loop {
         let lhs = String::from("");
         let rhs = String::from("");
+        *TERM_SOURCE.lock() = None;
         let exp = parse(lhs, "begin".to_string(), rhs);
         let output = format!(
             "your expression {} has been built.",
-            exp.to_string().bold().underline().green()
+            exp.pretty(&PrettyConfig::default())
+                .bold()
+                .underline()
+                .green()
         );
         print_out(output.into(), Color::BrightBlue);
+        if let Some((src, spans)) = TERM_SOURCE.lock().clone() {
+            if annotated(&exp) {
+                match exp.ty_diagnose(None, &spans) {
+                    Ok(ty) => println!(
+                        "\nit has type {}.",
+                        ty.pretty(&PrettyConfig::default()).green().underline()
+                    ),
+                    Err(diagnostic) => println!("\n{}", diagnostic.render(&src).red()),
+                }
+            }
+        }
         println!(
             "\nwhich {} would you select?\n{}",
             "evaluation strategy".bold(),
             format!(
-                "currently available: {} (call-by-value), {} (call-by-name)",
+                "currently available: {} (call-by-value), {} (call-by-name), {} (call-by-need),\n{} (normal-order), {} (applicative-order)",
                 "cbv".underline().green(),
-                "cbn".underline().green()
+                "cbn".underline().green(),
+                "need".underline().green(),
+                "normal".underline().green(),
+                "applicative".underline().green()
             )
         );
         let eval_strategy;
@@ -273,6 +436,18 @@ pub fn start_interactive_shell() {
                     eval_strategy = Strategy::CallByName;
                     break;
                 }
+                "need" => {
+                    eval_strategy = Strategy::CallByNeed;
+                    break;
+                }
+                "normal" => {
+                    eval_strategy = Strategy::NormalOrder;
+                    break;
+                }
+                "applicative" => {
+                    eval_strategy = Strategy::ApplicativeOrder;
+                    break;
+                }
                 _ => {
                     let output = format!(
                         "{} has not been supported, PR(s) welcome.",
@@ -288,55 +463,89 @@ pub fn start_interactive_shell() {
             "(note: please implement `{}` before choosing your own.",
             "eval_to_normal_form".to_string().underline()
         );
-        println!("\n1. {} 2. {}", "official".green(), "your own".green());
-        let mut flag = false;
+        println!(
+            "\n1. {} 2. {} 3. {} 4. {} 5. {}",
+            "official".green(),
+            "your own".green(),
+            "official (big-step with closures)".green(),
+            "official (CEK machine, with its states)".green(),
+            "official (small-step, with the rules)".green()
+        );
+        let backend;
         loop {
             print_prompt();
             let input = read_line();
             match input.as_str() {
-                "1" => break,
-                "2" => {
-                    flag = true;
-                    break;
-                }
+                "1" => backend = Backend::Official,
+                "2" => backend = Backend::YourOwn,
+                "3" => backend = Backend::BigStep,
+                "4" => backend = Backend::Cek,
+                "5" => backend = Backend::Traced,
                 _ => {
                     print_out("please type the correct number.".into(), Color::Red);
                     continue;
                 }
             }
+            break;
         }
         println!(
             "\nstart evaluating {} to normal form by {} using {}.",
-            exp.to_string().underline(),
+            exp.pretty(&PrettyConfig::default()).underline(),
             eval_strategy.to_string().green().underline().bold(),
-            format!(
-                "{} implementation",
-                if flag {
-                    "your own".underline()
-                } else {
-                    "official".underline()
-                }
-            ),
+            format!("{} implementation", backend.to_string().underline()),
         );
         let start = Instant::now();
-        let result = if flag {
-            exp.clone().eval_to_normal_form(eval_strategy)
-        } else {
-            exp.clone().ref_eval_to_normal_form(eval_strategy)
+        let source = TERM_SOURCE.lock().take();
+        let result = match (backend, source) {
+            (Backend::YourOwn, _) => exp
+                .clone()
+                .eval_to_normal_form(eval_strategy)
+                .map_err(|err| err.to_string()),
+            (Backend::BigStep, _) => exp
+                .ref_eval_to_normal_form_big_step_with(eval_strategy, &shell_limits())
+                .map_err(|err| err.to_string()),
+            (Backend::Cek, _) => {
+                print_cek_trace(&exp, eval_strategy);
+                exp.ref_eval_cek_with(eval_strategy, &shell_limits())
+                    .map_err(|err| err.to_string())
+            }
+            (Backend::Traced, _) => {
+                print_rule_trace(&exp, eval_strategy);
+                exp.clone()
+                    .ref_eval_to_normal_form_with(eval_strategy, &shell_limits())
+                    .map_err(|err| err.to_string())
+            }
+            // point to the offending subterm when possible
+            (Backend::Official, Some((src, mut spans))) => exp
+                .clone()
+                .ref_eval_to_normal_form_spanned_with(eval_strategy, &mut spans, &shell_limits())
+                .map_err(|diagnostic| format!("\n{}", diagnostic.render(&src))),
+            (Backend::Official, None) => exp
+                .clone()
+                .ref_eval_to_normal_form_with(eval_strategy, &shell_limits())
+                .map_err(|err| err.to_string()),
         };
         let duration = start.elapsed();
         match result {
             Ok((res, steps)) => {
                 print_out(
-                    res.to_string().underline().bold().green(),
+                    res.pretty(&PrettyConfig::default())
+                        .underline()
+                        .bold()
+                        .green(),
                     Color::BrightBlue,
                 );
                 print_statistics(duration, steps);
+                if eval_strategy == Strategy::CallByNeed
+                    && matches!(backend, Backend::Official | Backend::Traced)
+                {
+                    print_need_savings(&exp, steps);
+                }
             }
             Err(err) => {
                 let output = format!(
                     "failed to evaluate {}, error: {}",
-                    exp.to_string().underline().red(),
+                    exp.pretty(&PrettyConfig::default()).underline().red(),
                     err
                 );
                 print_out(output.into(), Color::BrightRed);
@@ -0,0 +1,317 @@
+//! The Krivine machine, i.e., the abstract machine for call-by-name.
+//! The state consists of the term in focus, its environment, and a stack
+//! of the arguments (each as a *closure*, i.e., the unevaluated argument
+//! with its own environment) not yet consumed. There are only three rules:
+//!
+//! ```text
+//! (t1 t2, e, S)        -> (t1, e, (t2, e) :: S)       push
+//! (λx. t, e, c :: S)   -> (t, e[x := c], S)           grab
+//! (x, e, S)            -> (t, e', S)  if e(x) = (t, e')  access
+//! ```
+//!
+//! The arguments are never evaluated before the beta reduction, which is
+//! exactly call-by-name. To support the rest of `Exp`, the stack may also
+//! hold the pending primitives, e.g., `incr □`, waiting for a number.
+
+use core::fmt;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    bigstep::{Binding, Scope, Thunk},
+    expr::{add::Add, app::App, cond::Cond},
+    limits::{Budget, EvalConfig, REPORTED_FRAMES},
+    stlc_err::StlcError,
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// An entry of the stack.
+#[derive(Debug, Clone)]
+pub enum Item {
+    /// an argument not consumed yet.
+    Arg(Exp, Scope),
+    /// `if □ then t2 else t3`
+    Cond(Exp, Exp, Scope),
+    /// `is_zero □`
+    IsZero,
+    /// `incr □`
+    Incr,
+    /// `decr □`
+    Decr,
+    /// `□ + t2`
+    AddLeft(Exp, Scope),
+    /// `n + □`
+    AddRight(u32),
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Arg(t, scope) => write!(f, "⟨{}, {}⟩", t, scope),
+            Item::Cond(t2, t3, _) => write!(f, "if □ then ({}) else ({})", t2, t3),
+            Item::IsZero => write!(f, "is_zero □"),
+            Item::Incr => write!(f, "incr □"),
+            Item::Decr => write!(f, "decr □"),
+            Item::AddLeft(t2, _) => write!(f, "□ + ({})", t2),
+            Item::AddRight(n) => write!(f, "{} + □", n),
+        }
+    }
+}
+
+impl Item {
+    /// the layer of the evaluation context, with `hole` plugged in.
+    pub fn plug(&self, hole: Exp) -> Exp {
+        match self {
+            Item::Arg(t, scope) => App::build(hole, scope.close(t)),
+            Item::Cond(t2, t3, scope) => Cond::build(hole, scope.close(t2), scope.close(t3)),
+            Item::IsZero => Exp::IsZero(Box::new(hole)),
+            Item::Incr => Exp::Incr(Box::new(hole)),
+            Item::Decr => Exp::Decr(Box::new(hole)),
+            Item::AddLeft(t2, scope) => Add::build(hole, scope.close(t2)),
+            Item::AddRight(n) => Add::build(Exp::Nat(*n), hole),
+        }
+    }
+}
+
+/// The state of the machine.
+/// note: the bindings are never updated under call-by-name,
+/// so a cloned state is an accurate snapshot.
+#[derive(Debug, Clone)]
+pub struct KrivineState {
+    pub term: Exp,
+    pub scope: Scope,
+    /// the top of the stack is the *last* one.
+    pub stack: Vec<Item>,
+}
+
+impl KrivineState {
+    /// whether the machine halts, i.e., a value with nothing to consume it.
+    pub fn is_final(&self) -> bool {
+        self.term.ref_is_value() && self.stack.is_empty()
+    }
+}
+
+impl fmt::Display for KrivineState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, [", self.term, self.scope)?;
+        for (i, item) in self.stack.iter().rev().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        write!(f, "])")
+    }
+}
+
+/// The machine itself.
+#[derive(Debug, Clone)]
+pub struct Krivine {
+    state: KrivineState,
+    /// the number of reductions so far, i.e., the steps
+    /// `ref_eval_one_step_cbn` would have taken.
+    steps: u32,
+    budget: Budget,
+}
+
+impl Krivine {
+    pub fn new(e: &Exp) -> Self {
+        Self::new_with(e, &EvalConfig::default())
+    }
+
+    /// the same as `new` within the steps and the timeout of `config`,
+    /// reporting the term reached, i.e., the redex plugged into (at most)
+    /// `REPORTED_FRAMES` of the stack, see `Item::plug`.
+    /// note: the other limits are not checked, since the term itself
+    /// is never built up.
+    pub fn new_with(e: &Exp, config: &EvalConfig) -> Self {
+        Self {
+            state: KrivineState {
+                term: e.clone(),
+                scope: Scope::new(),
+                stack: vec![],
+            },
+            steps: 0,
+            budget: Budget::new(config),
+        }
+    }
+
+    pub fn state(&self) -> &KrivineState {
+        &self.state
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// count one reduction of `redex`, in the rest of the stack.
+    fn tick(&mut self, redex: impl FnOnce() -> Exp) -> Result<()> {
+        self.budget.check(self.steps, || {
+            let innermost = self.state.stack.iter().rev().take(REPORTED_FRAMES);
+            innermost.fold(redex(), |hole, item| item.plug(hole))
+        })?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn focus(&mut self, term: Exp, scope: Scope) {
+        self.state.term = term;
+        self.state.scope = scope;
+    }
+
+    /// one transition of the machine, returns `false` if already final.
+    pub fn step(&mut self) -> Result<bool> {
+        if self.state.is_final() {
+            return Ok(false);
+        }
+        let scope = self.state.scope.clone();
+        match self.state.term.clone() {
+            // push
+            Exp::App(app) => {
+                self.state.stack.push(Item::Arg(app.t2, scope));
+                self.state.term = app.t1;
+            }
+            // access
+            Exp::Var(v) => match scope.lookup(&v) {
+                Some(Binding::Thunk(thunk)) => {
+                    let (term, scope) = match &*thunk.borrow() {
+                        Thunk::Delayed(term, scope) => (term.clone(), scope.clone()),
+                        Thunk::Forced(value) => (value.read_back(), Scope::new()),
+                    };
+                    self.focus(term, scope);
+                }
+                Some(Binding::Value(value)) => self.focus(value.read_back(), Scope::new()),
+                None => return Err(StlcError::InvalidExpression(v)),
+            },
+            Exp::Cond(cond) => {
+                self.state
+                    .stack
+                    .push(Item::Cond(cond.r#then, cond.r#else, scope));
+                self.state.term = cond.r#if;
+            }
+            Exp::IsZero(t) => {
+                self.state.stack.push(Item::IsZero);
+                self.state.term = *t;
+            }
+            Exp::Incr(t) => {
+                self.state.stack.push(Item::Incr);
+                self.state.term = *t;
+            }
+            Exp::Decr(t) => {
+                self.state.stack.push(Item::Decr);
+                self.state.term = *t;
+            }
+            Exp::Add(add) => {
+                self.state.stack.push(Item::AddLeft(add.t2, scope));
+                self.state.term = add.t1;
+            }
+            // a value, consumed by the top of the stack
+            v => {
+                let item = self.state.stack.pop().unwrap();
+                self.consume(v, scope, item)?;
+            }
+        }
+        Ok(true)
+    }
+
+    fn consume(&mut self, v: Exp, scope: Scope, item: Item) -> Result<()> {
+        match (v, item) {
+            // grab
+            (Exp::Lambda(lambda), Item::Arg(t, arg_scope)) => {
+                self.tick(|| {
+                    let f = scope.close(&Exp::Lambda(lambda.clone()));
+                    App::build(f, arg_scope.close(&t))
+                })?;
+                // a variable is passed by its own binding, rather than by a
+                // thunk of it, so no chain of thunks builds up, e.g., for ω
+                let binding = match &t {
+                    Exp::Var(v) => arg_scope.lookup(v).cloned(),
+                    _ => None,
+                };
+                let binding = binding.unwrap_or_else(|| {
+                    Binding::Thunk(Rc::new(RefCell::new(Thunk::Delayed(t, arg_scope))))
+                });
+                self.focus(lambda.exp, scope.extend(lambda.arg, binding));
+            }
+            (v, Item::Arg(t, arg_scope)) => {
+                return Err(StlcError::StuckExpressionCbn(format!(
+                    "{}",
+                    App::build(v, arg_scope.close(&t))
+                )))
+            }
+            (v @ (Exp::True | Exp::False), Item::Cond(t2, t3, scope)) => {
+                self.tick(|| Cond::build(v.clone(), scope.close(&t2), scope.close(&t3)))?;
+                let branch = if v == Exp::True { t2 } else { t3 };
+                self.focus(branch, scope);
+            }
+            (v, Item::Cond(..)) => return Err(StlcError::non_boolean_if(scope.close(&v))),
+            (Exp::Nat(n), item @ (Item::IsZero | Item::Incr | Item::Decr)) => {
+                self.tick(|| item.plug(Exp::Nat(n)))?;
+                self.state.term = match item {
+                    Item::IsZero if n == 0 => Exp::True,
+                    Item::IsZero => Exp::False,
+                    Item::Incr => Exp::Nat(n.saturating_add(1)),
+                    _ => Exp::Nat(n.saturating_sub(1)),
+                };
+            }
+            (v, Item::IsZero | Item::Incr | Item::Decr) => {
+                return Err(StlcError::InvalidExpression(format!("{}", scope.close(&v))))
+            }
+            (Exp::Nat(n), Item::AddLeft(t2, scope)) => {
+                self.state.stack.push(Item::AddRight(n));
+                self.focus(t2, scope);
+            }
+            (v, Item::AddLeft(t2, t2_scope)) => {
+                return Err(StlcError::StuckExpressionCbn(format!(
+                    "{}",
+                    Add::build(scope.close(&v), t2_scope.close(&t2))
+                )))
+            }
+            (Exp::Nat(n2), Item::AddRight(n1)) => {
+                self.tick(|| Add::build(Exp::Nat(n1), Exp::Nat(n2)))?;
+                self.state.term = Exp::Nat(n1.saturating_add(n2));
+            }
+            (v, Item::AddRight(n1)) => {
+                return Err(StlcError::StuckExpressionCbn(format!(
+                    "{}",
+                    Add::build(Exp::Nat(n1), scope.close(&v))
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// run till the final state, returns the (closed) value
+    /// with the number of reductions.
+    pub fn run(mut self) -> Result<(Exp, u32)> {
+        while self.step()? {}
+        Ok((self.state.scope.close(&self.state.term), self.steps))
+    }
+}
+
+impl Exp {
+    /// evaluate by call-by-name on the Krivine machine, i.e., the same
+    /// as `ref_eval_to_normal_form(Strategy::CallByName)`.
+    pub fn ref_eval_krivine(&self) -> Result<(Exp, u32)> {
+        self.ref_eval_krivine_with(&EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// same as `ref_eval_to_normal_form_with(Strategy::CallByName)`, but by
+    /// the Krivine machine, see `Krivine::new_with`.
+    pub fn ref_eval_krivine_with(&self, config: &EvalConfig) -> Result<(Exp, u32)> {
+        Krivine::new_with(self, config).run()
+    }
+
+    /// every state of the machine from the initial one, at most `limit` of them.
+    /// note: the trace ends early (without an error) when the machine fails.
+    pub fn ref_trace_krivine(&self, limit: usize) -> Vec<KrivineState> {
+        let mut machine = Krivine::new(self);
+        let mut trace = vec![machine.state().clone()];
+        while trace.len() < limit && matches!(machine.step(), Ok(true)) {
+            trace.push(machine.state().clone());
+        }
+        trace
+    }
+}
@@ -2,6 +2,39 @@ use core::fmt;
 
 use expr::{add::Add, app::App, cond::Cond, lambda::Lambda};
 
+/// alpha-equivalence & alpha-invariant hashing.
+pub mod alpha;
+
+/// A-normal form, i.e., every intermediate result named.
+pub mod anf;
+
+/// the environment-based big-step evaluator with closures.
+pub mod bigstep;
+
+/// the CEK abstract machine.
+pub mod cek;
+
+/// closure conversion & lambda lifting.
+pub mod closure;
+
+/// compile well-typed terms to standalone rust source.
+pub mod codegen;
+
+/// the continuation-passing style transformation.
+pub mod cps;
+
+/// defunctionalization into a first-order language.
+pub mod defunc;
+
+/// derivation trees, for typing & big-step evaluation.
+pub mod derivation;
+
+/// graphviz export for syntax trees & reduction graphs.
+pub mod dot;
+
+/// the evaluator of the first-order programs, i.e., closure & defunc.
+pub(crate) mod firstorder;
+
 /// the exercises from day1 to day7.
 pub mod exercises;
 
@@ -11,15 +44,48 @@ pub mod expr;
 /// the frontend.
 pub mod interactive_shell;
 
+/// the textual frontend, i.e., lexer and parser for `Exp`.
+pub mod parser;
+
+/// the locally-nameless (de Bruijn) representation.
+pub mod nameless;
+
+/// the Krivine machine for call-by-name.
+pub mod krivine;
+
+/// evaluation limits, i.e., steps, time & term size.
+pub mod limits;
+
+/// call-by-need evaluation with shared thunks.
+pub mod need;
+
+/// normalization, i.e., reducing under binders.
+pub mod normalize;
+
+/// the precedence-aware pretty printer.
+pub mod pretty;
+
 /// my reference solutions, feel free to check it out.
 pub mod refsols;
 
+/// source spans & caret-style diagnostics.
+pub mod span;
+
+/// capture-avoiding substitution.
+pub mod subst;
+
 /// our custom errors.
 pub mod stlc_err;
 
+/// small-step traces annotated with the evaluation rules.
+pub mod trace;
+
 /// the type for simply-typed lambda calculus.
 pub mod type_;
 
+/// the bytecode compiler & the stack-based virtual machine.
+pub mod vm;
+
 /// The definition for our (currently) untyped lambda calculus
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Exp {
@@ -61,12 +127,20 @@ pub enum Exp {
     // like any other `Exp` we've seen so far.
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Strategy {
     CallByValue,
     CallByName,
+    /// call-by-name, but each argument is evaluated at most once
+    /// and the result is shared, see `need.rs`.
+    CallByNeed,
+    /// always reduce the leftmost-outermost redex, even under binders,
+    /// i.e., it reaches the (full) normal form whenever there is one.
+    NormalOrder,
+    /// always reduce the leftmost-innermost redex, even under binders.
+    ApplicativeOrder,
     // TODO(General): adding other evaluation strategy, PR(s) welcome!
-    // e.g., call by reference, call by need, etc.
+    // e.g., call by reference, etc.
 }
 
 impl fmt::Display for Exp {
@@ -92,6 +166,9 @@ impl fmt::Display for Strategy {
         match self {
             Strategy::CallByValue => write!(f, "call-by-value"),
             Strategy::CallByName => write!(f, "call-by-name"),
+            Strategy::CallByNeed => write!(f, "call-by-need"),
+            Strategy::NormalOrder => write!(f, "normal-order"),
+            Strategy::ApplicativeOrder => write!(f, "applicative-order"),
         }
     }
 }
@@ -0,0 +1,227 @@
+//! The limits of an evaluation, i.e., how many steps, how long, and how
+//! large the term could grow, e.g., the "grow omega" of `ref_grow_omega`
+//! runs out of memory way before it runs out of steps:
+//!
+//! ```text
+//! (λx. x x x) (λx. x x x) ⟶ (λx. x x x) (λx. x x x) (λx. x x x) ⟶ ..
+//! ```
+//!
+//! Each limit fails with its own error, carrying the term reached so far
+//! with the number of steps taken, see `StlcError`.
+//!
+//! Besides, a term reducing back to itself (up to alpha-equivalence),
+//! e.g., ω, never reaches a value, which is detected by remembering
+//! (the alpha-invariant hashes of) the recent terms, so that it fails
+//! with `StlcError::Diverges` right away rather than running out of steps.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::{alpha::AlphaCanonical, nameless::Term, stlc_err::StlcError, Exp};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The knobs for an evaluation, `None` for no limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalConfig {
+    /// the maximum number of steps.
+    pub max_steps: u32,
+    /// the wall-clock time, counted from the start of the evaluation.
+    pub timeout: Option<Duration>,
+    /// the maximum number of nodes of the term, see `Exp::size`.
+    pub max_size: Option<usize>,
+    /// the maximum depth of the term, see `Exp::depth`, or how deep the
+    /// evaluation of the operands nests for the evaluators recursing on them.
+    pub max_depth: Option<usize>,
+    /// how many recent terms are remembered to detect a cycle,
+    /// i.e., the longest cycle detected, 0 to disable the detection.
+    pub cycle_window: usize,
+}
+
+impl Default for EvalConfig {
+    /// one million steps, and the cycles of at most 32 steps.
+    fn default() -> Self {
+        Self {
+            max_steps: 1000000,
+            timeout: None,
+            max_size: None,
+            max_depth: None,
+            cycle_window: 32,
+        }
+    }
+}
+
+/// How deep the evaluators recursing on the operands nest at most, unless
+/// `EvalConfig::max_depth` is lower, e.g., `(λx. incr (x x)) (λx. incr (x x))`
+/// nests one level deeper on every application, and would otherwise
+/// overflow the stack long before running out of steps.
+/// note: a thread (2 MiB by default) holds a few hundred levels in a debug
+/// build, the machines with an explicit continuation (e.g., `cek`) have
+/// no such limit.
+const MAX_NESTING: usize = 256;
+
+/// How many (innermost) frames of the continuation (or the stack) of an
+/// abstract machine are plugged into the term reported at a limit, since
+/// it grows one frame per step at worst, e.g., `(λx. incr (x x)) (λx. incr (x x))`,
+/// while the term is displayed (and dropped) by recursion.
+pub const REPORTED_FRAMES: usize = 100;
+
+/// The steps and the deadline of a running evaluation, i.e., the limits
+/// checked without the term at hand, e.g., by the machines with environments.
+/// Besides, how deep the evaluation of the operands nests, the counterpart
+/// of the depth of the term for the evaluators recursing on the operands.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Budget {
+    max_steps: u32,
+    deadline: Option<Instant>,
+    max_nesting: usize,
+}
+
+impl Budget {
+    /// start the clock.
+    pub(crate) fn new(config: &EvalConfig) -> Self {
+        Self {
+            max_steps: config.max_steps,
+            deadline: config.timeout.map(|timeout| Instant::now() + timeout),
+            max_nesting: config
+                .max_depth
+                .map_or(MAX_NESTING, |depth| depth.min(MAX_NESTING)),
+        }
+    }
+
+    /// check before nesting the evaluation of an operand, `nesting` being
+    /// the levels nested, `partial` gives the operand to report, i.e.,
+    /// without the (deep) context it is nested in.
+    pub(crate) fn check_nesting(
+        &self,
+        nesting: usize,
+        steps: u32,
+        partial: impl FnOnce() -> Exp,
+    ) -> Result<()> {
+        if nesting >= self.max_nesting {
+            return Err(StlcError::ExceedDepthLimit {
+                term: partial(),
+                steps,
+            });
+        }
+        Ok(())
+    }
+
+    /// check before taking another step, `steps` being the ones taken,
+    /// `partial` gives the term to report.
+    pub(crate) fn check(&self, steps: u32, partial: impl FnOnce() -> Exp) -> Result<()> {
+        if steps >= self.max_steps {
+            return Err(StlcError::ExceedStepLimit {
+                term: partial(),
+                steps,
+            });
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(StlcError::Timeout {
+                term: partial(),
+                steps,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The limits of a running evaluation, i.e., with the deadline fixed,
+/// and the recent terms seen.
+pub(crate) struct Limits<'a> {
+    config: &'a EvalConfig,
+    budget: Budget,
+    /// the step at which each recent term is seen.
+    seen: HashMap<Term, u32>,
+    recent: VecDeque<Term>,
+}
+
+impl<'a> Limits<'a> {
+    /// start the clock.
+    pub(crate) fn new(config: &'a EvalConfig) -> Self {
+        Self {
+            config,
+            budget: Budget::new(config),
+            seen: HashMap::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// the length of the cycle `e` closes, if any.
+    /// note: the evaluation is deterministic (up to alpha-equivalence),
+    /// so once a term is seen again, it goes around forever.
+    fn cycle(&mut self, e: &Exp, steps: u32) -> Option<u32> {
+        if self.config.cycle_window == 0 {
+            return None;
+        }
+        let key = e.alpha_key();
+        if let Some(seen) = self.seen.get(&key) {
+            return Some(steps - seen);
+        }
+        if self.recent.len() == self.config.cycle_window {
+            let oldest = self.recent.pop_front()?;
+            self.seen.remove(&oldest);
+        }
+        self.seen.insert(key.clone(), steps);
+        self.recent.push_back(key);
+        None
+    }
+
+    /// check `e` before taking another step, `steps` being the ones taken,
+    /// `partial` gives the term to report, e.g., with the thunks read back.
+    pub(crate) fn check(&mut self, e: &Exp, steps: u32, partial: impl Fn() -> Exp) -> Result<()> {
+        if let Some(cycle) = self.cycle(e, steps) {
+            return Err(StlcError::Diverges {
+                cycle,
+                term: partial(),
+            });
+        }
+        self.budget.check(steps, &partial)?;
+        let config = self.config;
+        let error: fn(Exp, u32) -> StlcError =
+            if config.max_size.is_some_and(|size| e.size() > size) {
+                |term, steps| StlcError::ExceedSizeLimit { term, steps }
+            } else if config.max_depth.is_some_and(|depth| e.depth() > depth) {
+                |term, steps| StlcError::ExceedDepthLimit { term, steps }
+            } else {
+                return Ok(());
+            };
+        Err(error(partial(), steps))
+    }
+}
+
+impl Exp {
+    /// the number of nodes, e.g., 3 for `λx. incr x` (`λx`, `incr` & `x`).
+    pub fn size(&self) -> usize {
+        1 + self.children().into_iter().map(Exp::size).sum::<usize>()
+    }
+
+    /// the number of nodes on the longest path from the root, i.e., 1 for a leaf.
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(Exp::depth)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl StlcError {
+    /// the error of the step limit as `ExceedEvalLimit`, i.e., what the
+    /// evaluators without an `EvalConfig` (always) returned.
+    pub(crate) fn into_eval_limit(self) -> StlcError {
+        match self {
+            StlcError::ExceedStepLimit { term, .. } => StlcError::ExceedEvalLimit(format!(
+                "exceed evaluation limit, current expr: {}",
+                term
+            )),
+            err => err,
+        }
+    }
+}
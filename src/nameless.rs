@@ -0,0 +1,281 @@
+//! The locally-nameless representation of `Exp`.
+//! Bound variables are replaced by their de Bruijn indices (i.e., how many
+//! binders to skip outwards to reach the one it refers to), while free
+//! variables keep their names, e.g.,
+//!
+//! ```text
+//! λx. λy. x y z  ==>  λ. λ. 1 0 z
+//! ```
+//!
+//! So alpha-equivalent terms have exactly the same representation,
+//! and substitution never needs to rename anything.
+
+use core::fmt;
+use std::{
+    cmp::Ordering,
+    collections::BTreeSet,
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    expr::{
+        add::Add, app::App, cond::Cond, decr::Decr, incr::Incr, is_zero::IsZero, lambda::Lambda,
+        var::Var,
+    },
+    subst::FreshNames,
+    type_::Type,
+    Exp,
+};
+
+/// The original binder name, only kept to produce readable names
+/// when converting back to `Exp`; it is ignored by the comparison
+/// and hashing.
+#[derive(Debug, Clone)]
+pub struct Hint(pub String);
+
+impl PartialEq for Hint {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Hint {}
+
+impl Hash for Hint {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl PartialOrd for Hint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hint {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Term {
+    /// bound variable, by its de Bruijn index, i.e., 0 is the innermost binder
+    Bound(usize),
+    /// free variable, by its name
+    Free(String),
+    /// lambda abstraction, the binder itself has no name (only a hint)
+    Lambda(Hint, Option<Type>, Box<Term>),
+    App(Box<Term>, Box<Term>),
+    Cond(Box<Term>, Box<Term>, Box<Term>),
+    True,
+    False,
+    Nat(u32),
+    IsZero(Box<Term>),
+    Incr(Box<Term>),
+    Decr(Box<Term>),
+    Add(Box<Term>, Box<Term>),
+}
+
+impl Term {
+    /// convert a (named) `Exp` into the locally-nameless representation.
+    pub fn from_exp(e: &Exp) -> Term {
+        fn from_exp_inner(e: &Exp, binders: &mut Vec<String>) -> Term {
+            let mut go = |e: &Exp| Box::new(from_exp_inner(e, binders));
+            match e {
+                Exp::Var(v) => match binders.iter().rev().position(|b| b == v) {
+                    Some(i) => Term::Bound(i),
+                    None => Term::Free(v.clone()),
+                },
+                Exp::Lambda(lambda) => {
+                    binders.push(lambda.arg.clone());
+                    let body = from_exp_inner(&lambda.exp, binders);
+                    binders.pop();
+                    Term::Lambda(Hint(lambda.arg.clone()), lambda.ty.clone(), Box::new(body))
+                }
+                Exp::App(app) => Term::App(go(&app.t1), go(&app.t2)),
+                Exp::Cond(cond) => Term::Cond(go(&cond.r#if), go(&cond.r#then), go(&cond.r#else)),
+                Exp::True => Term::True,
+                Exp::False => Term::False,
+                Exp::Nat(n) => Term::Nat(*n),
+                Exp::IsZero(e) => Term::IsZero(go(e)),
+                Exp::Incr(e) => Term::Incr(go(e)),
+                Exp::Decr(e) => Term::Decr(go(e)),
+                Exp::Add(add) => Term::Add(go(&add.t1), go(&add.t2)),
+            }
+        }
+
+        from_exp_inner(e, &mut vec![])
+    }
+
+    /// convert back to a (named) `Exp`, the binders get their original
+    /// names whenever that does not capture anything, otherwise a fresh one.
+    /// note: a dangling index (i.e., not locally closed) is rendered as `#i`.
+    pub fn to_exp(&self) -> Exp {
+        fn to_exp_inner(t: &Term, names: &mut Vec<String>, fresh: &mut FreshNames) -> Exp {
+            let mut go = |t: &Term| to_exp_inner(t, names, fresh);
+            match t {
+                Term::Bound(i) => match names.len().checked_sub(i + 1) {
+                    Some(at) => Var::build(&names[at]),
+                    None => Var::build(&format!("#{}", i)),
+                },
+                Term::Free(v) => Var::build(v),
+                Term::Lambda(hint, ty, body) => {
+                    let arg = if names.contains(&hint.0) || fresh_conflict(t, &hint.0) {
+                        fresh.fresh(&hint.0)
+                    } else {
+                        hint.0.clone()
+                    };
+                    names.push(arg.clone());
+                    let exp = to_exp_inner(body, names, fresh);
+                    names.pop();
+                    Lambda {
+                        arg,
+                        exp,
+                        ty: ty.clone(),
+                    }
+                    .into()
+                }
+                Term::App(t1, t2) => App::build(go(t1), go(t2)),
+                Term::Cond(t1, t2, t3) => Cond::build(go(t1), go(t2), go(t3)),
+                Term::True => Exp::True,
+                Term::False => Exp::False,
+                Term::Nat(n) => Exp::Nat(*n),
+                Term::IsZero(t) => IsZero::build(go(t)),
+                Term::Incr(t) => Incr::build(go(t)),
+                Term::Decr(t) => Decr::build(go(t)),
+                Term::Add(t1, t2) => Add::build(go(t1), go(t2)),
+            }
+        }
+
+        /// whether the binder name would capture a free variable in `t`.
+        fn fresh_conflict(t: &Term, name: &str) -> bool {
+            t.free_names().contains(name)
+        }
+
+        let mut fresh = FreshNames::new();
+        for name in self.free_names().iter().chain(self.hints().iter()) {
+            fresh.reserve(name);
+        }
+        to_exp_inner(self, &mut vec![], &mut fresh)
+    }
+
+    /// all the free (named) variables.
+    pub fn free_names(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        let mut stack = vec![self];
+        while let Some(t) = stack.pop() {
+            if let Term::Free(v) = t {
+                names.insert(v.clone());
+            }
+            stack.extend(t.children());
+        }
+        names
+    }
+
+    fn hints(&self) -> BTreeSet<String> {
+        let mut hints = BTreeSet::new();
+        let mut stack = vec![self];
+        while let Some(t) = stack.pop() {
+            if let Term::Lambda(hint, _, _) = t {
+                hints.insert(hint.0.clone());
+            }
+            stack.extend(t.children());
+        }
+        hints
+    }
+
+    fn children(&self) -> Vec<&Term> {
+        match self {
+            Term::Lambda(_, _, t) | Term::IsZero(t) | Term::Incr(t) | Term::Decr(t) => vec![t],
+            Term::App(t1, t2) | Term::Add(t1, t2) => vec![t1, t2],
+            Term::Cond(t1, t2, t3) => vec![t1, t2, t3],
+            Term::Bound(_) | Term::Free(_) | Term::True | Term::False | Term::Nat(_) => vec![],
+        }
+    }
+
+    /// rebuild the term by applying `f` to every direct subterm,
+    /// `f` also receives how many binders it goes under (i.e., 0 or 1).
+    fn map(&self, mut f: impl FnMut(&Term, usize) -> Term) -> Term {
+        let mut go = |t: &Term| Box::new(f(t, 0));
+        match self {
+            Term::Lambda(hint, ty, t) => Term::Lambda(hint.clone(), ty.clone(), Box::new(f(t, 1))),
+            Term::App(t1, t2) => Term::App(go(t1), go(t2)),
+            Term::Cond(t1, t2, t3) => Term::Cond(go(t1), go(t2), go(t3)),
+            Term::IsZero(t) => Term::IsZero(go(t)),
+            Term::Incr(t) => Term::Incr(go(t)),
+            Term::Decr(t) => Term::Decr(go(t)),
+            Term::Add(t1, t2) => Term::Add(go(t1), go(t2)),
+            t => t.clone(),
+        }
+    }
+
+    /// ↑ᵈ_c, i.e., add `d` to every index at least `cutoff`
+    /// (those not bound inside the current term).
+    pub fn shift(&self, d: isize, cutoff: usize) -> Term {
+        match self {
+            Term::Bound(k) if *k >= cutoff => {
+                let k = *k as isize + d;
+                assert!(k >= 0, "expect shifting to never produce a negative index");
+                Term::Bound(k as usize)
+            }
+            t => t.map(|t, under| t.shift(d, cutoff + under)),
+        }
+    }
+
+    /// [j := s], i.e., replace the index `j` with `s`.
+    pub fn subst(&self, j: usize, s: &Term) -> Term {
+        match self {
+            Term::Bound(k) if *k == j => s.clone(),
+            // going under a binder, every index is now one larger
+            Term::Lambda(..) => {
+                let s = s.shift(1, 0);
+                self.map(|t, _| t.subst(j + 1, &s))
+            }
+            t => t.map(|t, _| t.subst(j, s)),
+        }
+    }
+
+    /// the beta reduction, i.e., `(λ. body) arg -> body[0 := arg]`.
+    pub fn beta(body: &Term, arg: &Term) -> Term {
+        body.subst(0, &arg.shift(1, 0)).shift(-1, 0)
+    }
+
+    /// whether every index refers to a binder inside the term.
+    pub fn is_locally_closed(&self) -> bool {
+        fn closed_under(t: &Term, depth: usize) -> bool {
+            match t {
+                Term::Bound(k) => *k < depth,
+                Term::Lambda(_, _, body) => closed_under(body, depth + 1),
+                t => t.children().into_iter().all(|t| closed_under(t, depth)),
+            }
+        }
+
+        closed_under(self, 0)
+    }
+}
+
+impl From<&Exp> for Term {
+    fn from(value: &Exp) -> Self {
+        Self::from_exp(value)
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Bound(k) => write!(f, "{}", k),
+            Term::Free(v) => write!(f, "{}", v),
+            Term::Lambda(_, None, t) => write!(f, "λ. {}", t),
+            Term::Lambda(_, Some(ty), t) => write!(f, "λ: {}. {}", ty, t),
+            Term::App(t1, t2) => write!(f, "({}) ({})", t1, t2),
+            Term::Cond(t1, t2, t3) => write!(f, "if ({}) then ({}) else ({})", t1, t2, t3),
+            Term::True => write!(f, "true"),
+            Term::False => write!(f, "false"),
+            Term::Nat(n) => write!(f, "{}", n),
+            Term::IsZero(t) => write!(f, "is_zero ({})", t),
+            Term::Incr(t) => write!(f, "incr ({})", t),
+            Term::Decr(t) => write!(f, "decr ({})", t),
+            Term::Add(t1, t2) => write!(f, "({}) + ({})", t1, t2),
+        }
+    }
+}
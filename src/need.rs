@@ -0,0 +1,166 @@
+//! Call-by-need evaluation, i.e., call-by-name with sharing.
+//! Instead of substituting the argument itself, the beta reduction
+//! allocates a *thunk* for it on a heap and substitutes a reference
+//! to that thunk, e.g.,
+//!
+//! ```text
+//! (λx. x + x) (incr 1) -> #0 + #0    where #0 = incr 1
+//!                      -> #0 + #0    where #0 = 2
+//!                      -> 4
+//! ```
+//!
+//! The first time a thunk is needed it gets evaluated (in place), and
+//! every later reference simply reads the value, so each argument is
+//! evaluated at most once; while an argument never needed is never evaluated.
+//! The references are encoded as variables named `#l` (which the parser
+//! never produces), so the usual (capture-avoiding) substitution just works.
+
+use crate::{
+    expr::{add::Add, app::App, cond::Cond},
+    limits::{EvalConfig, Limits},
+    stlc_err::StlcError,
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The heap of thunks, indexed by their locations.
+#[derive(Debug, Clone, Default)]
+struct Heap {
+    thunks: Vec<Exp>,
+}
+
+impl Heap {
+    fn alloc(&mut self, e: Exp) -> Exp {
+        self.thunks.push(e);
+        Exp::Var(format!("#{}", self.thunks.len() - 1))
+    }
+
+    /// the location, if `e` is a reference to a thunk.
+    fn location(e: &Exp) -> Option<usize> {
+        match e {
+            Exp::Var(v) => v.strip_prefix('#')?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// follow the reference(s) as long as the thunk is already a value,
+    /// note that this does not count as an evaluation step.
+    fn deref(&self, mut e: Exp) -> Exp {
+        while let Some(l) = Self::location(&e) {
+            if !self.thunks[l].ref_is_value() {
+                break;
+            }
+            e = self.thunks[l].clone();
+        }
+        e
+    }
+
+    /// replace every reference with (the read back of) its thunk.
+    fn read_back(&self, e: Exp) -> Exp {
+        let locations: Vec<usize> = e
+            .free_vars()
+            .iter()
+            .filter_map(|v| Self::location(&Exp::Var(v.clone())))
+            .collect();
+        locations.into_iter().fold(e, |e, l| {
+            let thunk = self.read_back(self.thunks[l].clone());
+            e.ref_substitute(format!("#{}", l), thunk)
+        })
+    }
+
+    fn stuck(&self, e: Exp) -> StlcError {
+        StlcError::StuckExpressionCbn(format!("{}", self.read_back(e)))
+    }
+
+    fn invalid(&self, e: Exp) -> StlcError {
+        StlcError::InvalidExpression(format!("{}", self.read_back(e)))
+    }
+
+    /// evaluate one step further, the thunk(s) may be updated in place.
+    fn step(&mut self, e: Exp) -> Result<Exp> {
+        if let Some(l) = Self::location(&e) {
+            // force the thunk, the reference stays the same
+            let thunk = self.thunks[l].clone();
+            self.thunks[l] = self.step(thunk)?;
+            return Ok(e);
+        }
+        match e.clone() {
+            Exp::App(app) => match self.deref(app.t1.clone()) {
+                // ----------------------------------- (#l fresh)
+                // (\x. t1) t2 -> [x := #l] t1, #l = t2
+                Exp::Lambda(lambda) => {
+                    // no need to share a value or a reference
+                    let arg = if app.t2.ref_is_value() || Self::location(&app.t2).is_some() {
+                        app.t2
+                    } else {
+                        self.alloc(app.t2)
+                    };
+                    Ok(lambda.exp.ref_substitute(lambda.arg, arg))
+                }
+                t1 if t1.ref_is_value() => Err(self.stuck(e)),
+                t1 => Ok(Exp::App(Box::new(App::new(self.step(t1)?, app.t2)))),
+            },
+            Exp::Cond(cond) => match self.deref(cond.r#if.clone()) {
+                Exp::True => Ok(cond.r#then),
+                Exp::False => Ok(cond.r#else),
+                r#if if r#if.ref_is_value() => Err(self.invalid(r#if)),
+                r#if => Ok(Cond::build(self.step(r#if)?, cond.r#then, cond.r#else)),
+            },
+            Exp::IsZero(t) => match self.deref(*t) {
+                Exp::Nat(n) => Ok(if n == 0 { Exp::True } else { Exp::False }),
+                t => Ok(Exp::IsZero(Box::new(self.step(t)?))),
+            },
+            Exp::Incr(t) => match self.deref(*t) {
+                Exp::Nat(n) => Ok(Exp::Nat(n.saturating_add(1))),
+                t => Ok(Exp::Incr(Box::new(self.step(t)?))),
+            },
+            Exp::Decr(t) => match self.deref(*t) {
+                Exp::Nat(n) => Ok(Exp::Nat(n.saturating_sub(1))),
+                t => Ok(Exp::Decr(Box::new(self.step(t)?))),
+            },
+            Exp::Add(add) => match (self.deref(add.t1), self.deref(add.t2)) {
+                (Exp::Nat(n1), Exp::Nat(n2)) => Ok(Exp::Nat(n1.saturating_add(n2))),
+                (t1, t2) if !t1.ref_is_value() => Ok(Add::build(self.step(t1)?, t2)),
+                (t1 @ Exp::Nat(_), t2) if !t2.ref_is_value() => Ok(Add::build(t1, self.step(t2)?)),
+                _ => Err(self.stuck(e)),
+            },
+            _ => Err(self.invalid(e)),
+        }
+    }
+}
+
+impl Exp {
+    /// evaluate to normal form by call-by-need, returns the
+    /// normal form with the number of steps taken, where following
+    /// a reference to an already evaluated thunk is free.
+    pub fn ref_eval_by_need(self) -> Result<(Exp, u32)> {
+        self.ref_eval_by_need_with(&EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// Same as `ref_eval_by_need`, but within the limits of `config`.
+    /// note: the size (and depth) is the one of the current expression
+    /// with the thunks referenced, rather than read back.
+    /// there is no cycle detection, since the same expression could refer
+    /// to thunks evaluated in the meantime.
+    pub fn ref_eval_by_need_with(self, config: &EvalConfig) -> Result<(Exp, u32)> {
+        let config = EvalConfig {
+            cycle_window: 0,
+            ..config.clone()
+        };
+        let mut limits = Limits::new(&config);
+        let mut heap = Heap::default();
+        let mut e = self;
+        let mut steps = 0;
+        loop {
+            e = heap.deref(e);
+            if e.ref_is_value() {
+                return Ok((heap.read_back(e), steps));
+            }
+            limits.check(&e, steps, || heap.read_back(e.clone()))?;
+            e = heap.step(e)?;
+            steps += 1;
+        }
+    }
+}
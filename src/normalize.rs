@@ -0,0 +1,235 @@
+//! Normalization, i.e., keep reducing (possibly under binders)
+//! until the chosen normal form is reached, e.g.,
+//! `λx. (λy. y) x` is already a value, but its full normal form is `λx. x`.
+//!
+//! Since reducing under binders deals with open terms, a stuck term
+//! headed by a (free) variable, e.g., `x (λy. y)` or `if x then 1 else 2`,
+//! is *neutral*, i.e., normal rather than an error.
+
+use core::fmt;
+
+use crate::{
+    expr::{add::Add, app::App, cond::Cond, lambda::Lambda},
+    limits::{EvalConfig, Limits},
+    stlc_err::StlcError,
+    Exp, Strategy,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// Where the normalization stops.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NormalForm {
+    /// weak head normal form, i.e., never reduce under a lambda
+    /// nor inside the arguments of a neutral term.
+    WeakHead,
+    /// head normal form, i.e., `λx1 .. xn. x t1 .. tm`,
+    /// reduce under lambdas but not inside the arguments.
+    Head,
+    /// (full) beta normal form, i.e., no redex anywhere.
+    Full,
+}
+
+impl fmt::Display for NormalForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NormalForm::WeakHead => write!(f, "weak head normal form"),
+            NormalForm::Head => write!(f, "head normal form"),
+            NormalForm::Full => write!(f, "normal form"),
+        }
+    }
+}
+
+/// Which redex goes first.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Order {
+    /// the leftmost-outermost one, the arguments are substituted as they are.
+    Normal,
+    /// the leftmost-innermost one, the arguments are reduced first.
+    Applicative,
+}
+
+impl From<Strategy> for Order {
+    fn from(value: Strategy) -> Self {
+        match value {
+            Strategy::CallByValue | Strategy::ApplicativeOrder => Order::Applicative,
+            Strategy::CallByName | Strategy::CallByNeed | Strategy::NormalOrder => Order::Normal,
+        }
+    }
+}
+
+impl Exp {
+    /// reduce one step towards `target`, or `None` if already there.
+    fn reduce(&self, order: Order, target: NormalForm) -> Result<Option<Exp>> {
+        let stuck = || match order {
+            Order::Normal => StlcError::StuckExpressionCbn(format!("{}", self)),
+            Order::Applicative => StlcError::StuckExpressionCbv(format!("{}", self)),
+        };
+        match self {
+            Exp::Var(_) | Exp::True | Exp::False | Exp::Nat(_) => Ok(None),
+            Exp::Lambda(lambda) => {
+                if target == NormalForm::WeakHead {
+                    return Ok(None);
+                }
+                Ok(lambda.exp.reduce(order, target)?.map(|exp| {
+                    Lambda {
+                        arg: lambda.arg.clone(),
+                        exp,
+                        ty: lambda.ty.clone(),
+                    }
+                    .into()
+                }))
+            }
+            Exp::App(app) => {
+                let beta = |lambda: &Lambda| {
+                    Some(
+                        lambda
+                            .exp
+                            .clone()
+                            .ref_substitute(lambda.arg.clone(), app.t2.clone()),
+                    )
+                };
+                match order {
+                    Order::Normal => {
+                        if let Exp::Lambda(lambda) = &app.t1 {
+                            return Ok(beta(lambda));
+                        }
+                        if let Some(t1) = app.t1.reduce(order, NormalForm::WeakHead)? {
+                            return Ok(Some(App::build(t1, app.t2.clone())));
+                        }
+                    }
+                    Order::Applicative => {
+                        if let Some(t1) = app.t1.reduce(order, target)? {
+                            return Ok(Some(App::build(t1, app.t2.clone())));
+                        }
+                        if let Some(t2) = app.t2.reduce(order, target)? {
+                            return Ok(Some(App::build(app.t1.clone(), t2)));
+                        }
+                        if let Exp::Lambda(lambda) = &app.t1 {
+                            return Ok(beta(lambda));
+                        }
+                    }
+                }
+                if app.t1.ref_is_value() {
+                    return Err(stuck());
+                }
+                // neutral, only the full normal form looks inside
+                if target != NormalForm::Full {
+                    return Ok(None);
+                }
+                if let Some(t1) = app.t1.reduce(order, target)? {
+                    return Ok(Some(App::build(t1, app.t2.clone())));
+                }
+                Ok(app
+                    .t2
+                    .reduce(order, target)?
+                    .map(|t2| App::build(app.t1.clone(), t2)))
+            }
+            Exp::Cond(cond) => match &cond.r#if {
+                Exp::True => Ok(Some(cond.r#then.clone())),
+                Exp::False => Ok(Some(cond.r#else.clone())),
+                r#if if r#if.ref_is_value() => Err(StlcError::non_boolean_if(r#if)),
+                r#if => {
+                    let rebuild = |r#if, r#then, r#else| Some(Cond::build(r#if, r#then, r#else));
+                    if let Some(r#if) = r#if.reduce_operand(order, target)? {
+                        return Ok(rebuild(r#if, cond.r#then.clone(), cond.r#else.clone()));
+                    }
+                    // neutral, e.g., `if x then t2 else t3`
+                    if target != NormalForm::Full {
+                        return Ok(None);
+                    }
+                    if let Some(r#then) = cond.r#then.reduce(order, target)? {
+                        return Ok(rebuild(r#if.clone(), r#then, cond.r#else.clone()));
+                    }
+                    Ok(cond
+                        .r#else
+                        .reduce(order, target)?
+                        .and_then(|r#else| rebuild(r#if.clone(), cond.r#then.clone(), r#else)))
+                }
+            },
+            Exp::IsZero(t) | Exp::Incr(t) | Exp::Decr(t) => {
+                if let Exp::Nat(n) = **t {
+                    return Ok(Some(match self {
+                        Exp::IsZero(_) if n == 0 => Exp::True,
+                        Exp::IsZero(_) => Exp::False,
+                        Exp::Incr(_) => Exp::Nat(n.saturating_add(1)),
+                        _ => Exp::Nat(n.saturating_sub(1)),
+                    }));
+                }
+                if t.ref_is_value() {
+                    return Err(StlcError::InvalidExpression(format!("{}", t)));
+                }
+                Ok(t.reduce_operand(order, target)?.map(|t| match self {
+                    Exp::IsZero(_) => Exp::IsZero(Box::new(t)),
+                    Exp::Incr(_) => Exp::Incr(Box::new(t)),
+                    _ => Exp::Decr(Box::new(t)),
+                }))
+            }
+            Exp::Add(add) => match (&add.t1, &add.t2) {
+                (Exp::Nat(n1), Exp::Nat(n2)) => Ok(Some(Exp::Nat(n1.saturating_add(*n2)))),
+                (t1, t2)
+                    if (t1.ref_is_value() && !matches!(t1, Exp::Nat(_)))
+                        || (t2.ref_is_value() && !matches!(t2, Exp::Nat(_))) =>
+                {
+                    Err(stuck())
+                }
+                (t1, t2) => {
+                    if let Some(t1) = t1.reduce_operand(order, target)? {
+                        return Ok(Some(Add::build(t1, t2.clone())));
+                    }
+                    Ok(t2
+                        .reduce_operand(order, target)?
+                        .map(|t2| Add::build(t1.clone(), t2)))
+                }
+            },
+        }
+    }
+
+    /// an operand of the primitives (or the if clause) is always reduced
+    /// till it is a value, and then only the full normal form looks inside.
+    fn reduce_operand(&self, order: Order, target: NormalForm) -> Result<Option<Exp>> {
+        match self.reduce(order, NormalForm::WeakHead)? {
+            Some(e) => Ok(Some(e)),
+            None if target == NormalForm::Full => self.reduce(order, target),
+            None => Ok(None),
+        }
+    }
+
+    /// One step towards `target`, or `None` if already there;
+    /// `CallByValue` and `ApplicativeOrder` reduce the leftmost-innermost
+    /// redex first while the others reduce the leftmost-outermost one.
+    pub fn ref_reduce_one_step(
+        &self,
+        strategy: Strategy,
+        target: NormalForm,
+    ) -> Result<Option<Exp>> {
+        self.reduce(strategy.into(), target)
+    }
+
+    /// keep reducing until `target` is reached, returns the
+    /// normal form with the number of steps taken.
+    /// note: call-by-need does not share anything here.
+    pub fn ref_normalize(self, strategy: Strategy, target: NormalForm) -> Result<(Exp, u32)> {
+        self.ref_normalize_with(strategy, target, &EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// Same as `ref_normalize`, but within the limits of `config`.
+    pub fn ref_normalize_with(
+        mut self,
+        strategy: Strategy,
+        target: NormalForm,
+        config: &EvalConfig,
+    ) -> Result<(Exp, u32)> {
+        let mut limits = Limits::new(config);
+        let mut steps = 0;
+        loop {
+            let Some(e) = self.ref_reduce_one_step(strategy, target)? else {
+                return Ok((self, steps));
+            };
+            limits.check(&self, steps, || self.clone())?;
+            self = e;
+            steps += 1;
+        }
+    }
+}
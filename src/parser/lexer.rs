@@ -0,0 +1,173 @@
+use core::fmt;
+
+use crate::stlc_err::StlcError;
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// All the tokens our (tiny) surface syntax could produce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// `λ` or `\`
+    Lambda,
+    Dot,
+    Colon,
+    /// `->` or `→`
+    Arrow,
+    LParen,
+    RParen,
+    Plus,
+    If,
+    Then,
+    Else,
+    True,
+    False,
+    IsZero,
+    Incr,
+    Decr,
+    Ident(String),
+    Nat(u32),
+    /// the end of input, always the last token.
+    Eof,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lambda => write!(f, "`λ`"),
+            Self::Dot => write!(f, "`.`"),
+            Self::Colon => write!(f, "`:`"),
+            Self::Arrow => write!(f, "`->`"),
+            Self::LParen => write!(f, "`(`"),
+            Self::RParen => write!(f, "`)`"),
+            Self::Plus => write!(f, "`+`"),
+            Self::If => write!(f, "`if`"),
+            Self::Then => write!(f, "`then`"),
+            Self::Else => write!(f, "`else`"),
+            Self::True => write!(f, "`true`"),
+            Self::False => write!(f, "`false`"),
+            Self::IsZero => write!(f, "`is_zero`"),
+            Self::Incr => write!(f, "`incr`"),
+            Self::Decr => write!(f, "`decr`"),
+            Self::Ident(v) => write!(f, "variable `{}`", v),
+            Self::Nat(n) => write!(f, "number `{}`", n),
+            Self::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+/// A token together with the byte range `[start, end)`
+/// it occupies in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn keyword_or_ident(word: &str) -> TokenKind {
+    match word {
+        "if" => TokenKind::If,
+        "then" => TokenKind::Then,
+        "else" => TokenKind::Else,
+        "true" => TokenKind::True,
+        "false" => TokenKind::False,
+        "is_zero" => TokenKind::IsZero,
+        "incr" => TokenKind::Incr,
+        "decr" => TokenKind::Decr,
+        _ => TokenKind::Ident(word.into()),
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '\''
+}
+
+/// Split the source into tokens, the returned vector
+/// always ends with a `TokenKind::Eof` token.
+pub fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = src.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            'λ' | '\\' => TokenKind::Lambda,
+            '.' => TokenKind::Dot,
+            ':' => TokenKind::Colon,
+            '→' => TokenKind::Arrow,
+            '-' if chars.peek().map(|&(_, c)| c) == Some('>') => {
+                chars.next();
+                tokens.push(Token {
+                    kind: TokenKind::Arrow,
+                    start,
+                    end: start + 2,
+                });
+                continue;
+            }
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '+' => TokenKind::Plus,
+            c if c.is_ascii_digit() => {
+                let mut end = start + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                let Ok(n) = src[start..end].parse::<u32>() else {
+                    return Err(StlcError::ParseError(format!(
+                        "number `{}` at byte {} does not fit into u32",
+                        &src[start..end],
+                        start
+                    )));
+                };
+                tokens.push(Token {
+                    kind: TokenKind::Nat(n),
+                    start,
+                    end,
+                });
+                continue;
+            }
+            c if is_ident_start(c) => {
+                let mut end = start + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !is_ident_continue(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token {
+                    kind: keyword_or_ident(&src[start..end]),
+                    start,
+                    end,
+                });
+                continue;
+            }
+            c => {
+                return Err(StlcError::ParseError(format!(
+                    "unexpected character `{}` at byte {}",
+                    c, start
+                )))
+            }
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: start + c.len_utf8(),
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        start: src.len(),
+        end: src.len(),
+    });
+    Ok(tokens)
+}
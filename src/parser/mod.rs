@@ -0,0 +1,333 @@
+//! A small recursive descent parser for our stlc.
+//! The accepted syntax is exactly what `Display` prints for `Exp`,
+//! so any printed term could be pasted back in, i.e.,
+//!
+//! ```text
+//! e ::= x | n | true | false
+//!     | λx. e | \x. e           -- lambda abstraction
+//!     | λx: T. e                -- typed lambda abstraction
+//!     | e e                     -- application, left associative
+//!     | if e then e else e      -- condition
+//!     | is_zero e | incr e | decr e
+//!     | e + e                   -- add, left associative
+//!     | (e)
+//!
+//! T ::= int | bool | X          -- X is any type variable
+//!     | T -> T | T → T          -- arrow, right associative
+//!     | (T)
+//! ```
+//!
+//! note: lambda abstractions and conditions extend as far to the right
+//! as possible, i.e., `λx. x y` is `λx. (x y)` rather than `(λx. x) y`.
+
+use std::str::FromStr;
+
+use self::lexer::{tokenize, Token, TokenKind};
+use crate::{
+    expr::{
+        add::Add, app::App, cond::Cond, decr::Decr, incr::Incr, is_zero::IsZero, lambda::Lambda,
+        path::Path, var::Var,
+    },
+    span::{Span, Spans},
+    stlc_err::StlcError,
+    type_::{tarrow::TArrow, Type},
+    Exp,
+};
+
+pub mod lexer;
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The spans of a parsed expression, shaped exactly like the expression.
+struct SpanTree {
+    span: Span,
+    children: Vec<SpanTree>,
+}
+
+impl SpanTree {
+    fn flatten(self, path: &mut Path, spans: &mut Spans) {
+        spans.insert(path.clone(), self.span);
+        for (i, child) in self.children.into_iter().enumerate() {
+            path.push(i);
+            child.flatten(path, spans);
+            path.pop();
+        }
+    }
+}
+
+struct Spanned {
+    exp: Exp,
+    tree: SpanTree,
+}
+
+impl Spanned {
+    fn new(exp: Exp, span: Span, children: Vec<SpanTree>) -> Self {
+        Self {
+            exp,
+            tree: SpanTree { span, children },
+        }
+    }
+
+    /// combine two operands with a binary constructor, e.g., `App::build`.
+    fn join(lhs: Spanned, rhs: Spanned, build: fn(Exp, Exp) -> Exp) -> Self {
+        let span = lhs.tree.span.to(rhs.tree.span);
+        Self::new(build(lhs.exp, rhs.exp), span, vec![lhs.tree, rhs.tree])
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        // never step over the trailing `Eof`
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T> {
+        let token = self.peek();
+        Err(StlcError::ParseError(format!(
+            "expect {}, actual: {} at byte {}",
+            expected, token.kind, token.start
+        )))
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<Token> {
+        if self.peek().kind == kind {
+            Ok(self.advance())
+        } else {
+            self.error(&kind.to_string())
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String> {
+        match self.peek().kind.clone() {
+            TokenKind::Ident(v) => {
+                self.advance();
+                Ok(v)
+            }
+            _ => self.error("a variable"),
+        }
+    }
+
+    /// e ::= e1 + e2 + ... (left associative)
+    fn parse_exp(&mut self) -> Result<Spanned> {
+        let mut lhs = self.parse_app()?;
+        while self.peek().kind == TokenKind::Plus {
+            self.advance();
+            let rhs = self.parse_app()?;
+            lhs = Spanned::join(lhs, rhs, Add::build);
+        }
+        Ok(lhs)
+    }
+
+    /// e ::= e1 e2 ... [λx. e | if e then e else e]
+    /// the trailing lambda abstraction / condition (if any)
+    /// consumes everything to its right.
+    fn parse_app(&mut self) -> Result<Spanned> {
+        let mut result: Option<Spanned> = None;
+        loop {
+            let operand = match self.peek().kind {
+                TokenKind::Lambda => Some(self.parse_lambda()?),
+                TokenKind::If => Some(self.parse_cond()?),
+                _ => None,
+            };
+            if let Some(operand) = operand {
+                result = Some(match result {
+                    Some(f) => Spanned::join(f, operand, App::build),
+                    None => operand,
+                });
+                break;
+            }
+            if !self.starts_unary() {
+                break;
+            }
+            let operand = self.parse_unary()?;
+            result = Some(match result {
+                Some(f) => Spanned::join(f, operand, App::build),
+                None => operand,
+            });
+        }
+        match result {
+            Some(e) => Ok(e),
+            None => self.error("an expression"),
+        }
+    }
+
+    /// λx. e | λx: T. e
+    fn parse_lambda(&mut self) -> Result<Spanned> {
+        let start = self.expect(TokenKind::Lambda)?.start;
+        let arg = self.expect_ident()?;
+        let ty = if self.peek().kind == TokenKind::Colon {
+            self.advance();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Dot)?;
+        let body = self.parse_exp()?;
+        let span = Span::new(start, body.tree.span.end);
+        let exp = match ty {
+            Some(ty) => Lambda::build_with_type(&arg, body.exp, ty),
+            None => Lambda::build(&arg, body.exp),
+        };
+        Ok(Spanned::new(exp, span, vec![body.tree]))
+    }
+
+    fn parse_cond(&mut self) -> Result<Spanned> {
+        let start = self.expect(TokenKind::If)?.start;
+        let r#if = self.parse_exp()?;
+        self.expect(TokenKind::Then)?;
+        let r#then = self.parse_exp()?;
+        self.expect(TokenKind::Else)?;
+        let r#else = self.parse_exp()?;
+        let span = Span::new(start, r#else.tree.span.end);
+        Ok(Spanned::new(
+            Cond::build(r#if.exp, r#then.exp, r#else.exp),
+            span,
+            vec![r#if.tree, r#then.tree, r#else.tree],
+        ))
+    }
+
+    fn starts_unary(&self) -> bool {
+        matches!(
+            self.peek().kind,
+            TokenKind::IsZero
+                | TokenKind::Incr
+                | TokenKind::Decr
+                | TokenKind::Ident(_)
+                | TokenKind::Nat(_)
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::LParen
+        )
+    }
+
+    /// e ::= is_zero a | incr a | decr a | a
+    fn parse_unary(&mut self) -> Result<Spanned> {
+        let build: fn(Exp) -> Exp = match self.peek().kind {
+            TokenKind::IsZero => IsZero::build,
+            TokenKind::Incr => Incr::build,
+            TokenKind::Decr => Decr::build,
+            _ => return self.parse_atom(),
+        };
+        let start = self.advance().start;
+        let operand = self.parse_unary()?;
+        let span = Span::new(start, operand.tree.span.end);
+        Ok(Spanned::new(build(operand.exp), span, vec![operand.tree]))
+    }
+
+    /// a ::= x | n | true | false | (e)
+    fn parse_atom(&mut self) -> Result<Spanned> {
+        let token = self.peek().clone();
+        let span = Span::new(token.start, token.end);
+        let exp = match token.kind {
+            TokenKind::Ident(v) => Var::build(&v),
+            TokenKind::Nat(n) => n.into(),
+            TokenKind::True => Exp::True,
+            TokenKind::False => Exp::False,
+            TokenKind::LParen => {
+                self.advance();
+                // the span includes the parentheses
+                let mut e = self.parse_exp()?;
+                let rparen = self.expect(TokenKind::RParen)?;
+                e.tree.span = Span::new(token.start, rparen.end);
+                return Ok(e);
+            }
+            _ => return self.error("an expression"),
+        };
+        self.advance();
+        Ok(Spanned::new(exp, span, vec![]))
+    }
+
+    /// T ::= T1 -> T2 -> ... (right associative)
+    fn parse_type(&mut self) -> Result<Type> {
+        let ty1 = self.parse_type_atom()?;
+        if self.peek().kind == TokenKind::Arrow {
+            self.advance();
+            let ty2 = self.parse_type()?;
+            return Ok(TArrow::build(ty1, ty2));
+        }
+        Ok(ty1)
+    }
+
+    /// T ::= int | bool | X | (T)
+    fn parse_type_atom(&mut self) -> Result<Type> {
+        match self.peek().kind.clone() {
+            // `int` and `bool` are only keywords inside a type
+            TokenKind::Ident(v) => {
+                self.advance();
+                Ok(match v.as_str() {
+                    "int" => Type::TInt,
+                    "bool" => Type::TBool,
+                    _ => v.into(),
+                })
+            }
+            TokenKind::LParen => {
+                self.advance();
+                let ty = self.parse_type()?;
+                self.expect(TokenKind::RParen)?;
+                Ok(ty)
+            }
+            _ => self.error("a type"),
+        }
+    }
+}
+
+/// Parse the given source into an `Exp`, the entire
+/// input must be consumed.
+/// e.g., `parse_exp("(λx. incr x) 1")` gives the same `Exp` as
+/// `App::build(Lambda::build("x", Incr::build(Var::build("x"))), 1.into())`.
+pub fn parse_exp(src: &str) -> Result<Exp> {
+    Ok(parse_exp_with_spans(src)?.0)
+}
+
+/// Same as `parse_exp`, but also returns the source span of every subterm.
+pub fn parse_exp_with_spans(src: &str) -> Result<(Exp, Spans)> {
+    let mut parser = Parser::new(tokenize(src)?);
+    let e = parser.parse_exp()?;
+    parser.expect(TokenKind::Eof)?;
+    let mut spans = Spans::new();
+    e.tree.flatten(&mut vec![], &mut spans);
+    Ok((e.exp, spans))
+}
+
+/// Parse the given source into a `Type`, the entire
+/// input must be consumed.
+/// e.g., `parse_type("(int -> bool) -> X0")`.
+pub fn parse_type(src: &str) -> Result<Type> {
+    let mut parser = Parser::new(tokenize(src)?);
+    let ty = parser.parse_type()?;
+    parser.expect(TokenKind::Eof)?;
+    Ok(ty)
+}
+
+impl FromStr for Exp {
+    type Err = StlcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_exp(s)
+    }
+}
+
+impl FromStr for Type {
+    type Err = StlcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_type(s)
+    }
+}
@@ -0,0 +1,285 @@
+//! A precedence-aware pretty printer for `Exp` and `Type`.
+//! Unlike `Display` (which happily wraps every operand in parentheses),
+//! the output here only contains the parentheses that are *necessary*
+//! to read the term back with `parser::parse_exp`, i.e.,
+//!
+//! - application is left associative: `f x y` is `(f x) y`
+//! - arrow is right associative: `a -> b -> c` is `a -> (b -> c)`
+//! - lambda abstraction & condition extend as far to the right as possible
+//!
+//! Long terms are broken into multiple lines once they exceed the
+//! configured width, the layout algorithm follows Wadler's
+//! "A prettier printer" (in a strict, simplified fashion).
+
+use crate::{type_::Type, Exp};
+
+/// the knobs for the pretty printer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrettyConfig {
+    /// print `λ` and `→` when true, otherwise `\` and `->`.
+    pub unicode: bool,
+    /// the preferred maximum line width.
+    pub width: usize,
+}
+
+impl PrettyConfig {
+    pub fn new(unicode: bool, width: usize) -> Self {
+        Self { unicode, width }
+    }
+
+    fn lambda(&self) -> &'static str {
+        if self.unicode {
+            "λ"
+        } else {
+            "\\"
+        }
+    }
+
+    fn arrow(&self) -> &'static str {
+        if self.unicode {
+            "→"
+        } else {
+            "->"
+        }
+    }
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        Self::new(true, 80)
+    }
+}
+
+/// The document algebra, a `Line` is rendered as a single space
+/// when the enclosing `Group` fits into the current line,
+/// otherwise a newline followed by the current indentation.
+#[derive(Debug, Clone)]
+enum Doc {
+    Text(String),
+    Line,
+    Concat(Vec<Doc>),
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+}
+
+fn text(s: impl Into<String>) -> Doc {
+    Doc::Text(s.into())
+}
+
+fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+fn nest(indent: usize, doc: Doc) -> Doc {
+    Doc::Nest(indent, Box::new(doc))
+}
+
+fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+fn parens(doc: Doc) -> Doc {
+    concat(vec![text("("), nest(1, doc), text(")")])
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// whether the rest of the current line (i.e., `doc` followed by `rest`
+/// until the next line break in `Break` mode) fits into `remaining` columns.
+fn fits(mut remaining: isize, doc: (usize, Mode, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut docs = vec![doc];
+    let mut rest = rest.iter().rev();
+    loop {
+        if remaining < 0 {
+            return false;
+        }
+        let Some((indent, mode, doc)) = docs.pop().or_else(|| rest.next().copied()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(s) => remaining -= s.chars().count() as isize,
+            Doc::Line => match mode {
+                Mode::Flat => remaining -= 1,
+                Mode::Break => return true,
+            },
+            Doc::Concat(ds) => docs.extend(ds.iter().rev().map(|d| (indent, mode, d))),
+            Doc::Nest(i, d) => docs.push((indent + i, mode, d)),
+            Doc::Group(d) => docs.push((indent, mode, d)),
+        }
+    }
+}
+
+fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    let mut docs = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = docs.pop() {
+        match doc {
+            Doc::Text(s) => {
+                out.push_str(s);
+                column += s.chars().count();
+            }
+            Doc::Line => match mode {
+                Mode::Flat => {
+                    out.push(' ');
+                    column += 1;
+                }
+                Mode::Break => {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                    column = indent;
+                }
+            },
+            Doc::Concat(ds) => docs.extend(ds.iter().rev().map(|d| (indent, mode, d))),
+            Doc::Nest(i, d) => docs.push((indent + i, mode, d)),
+            Doc::Group(d) => {
+                let remaining = width as isize - column as isize;
+                let mode = if fits(remaining, (indent, Mode::Flat, d), &docs) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                docs.push((indent, mode, d));
+            }
+        }
+    }
+    out
+}
+
+/// the precedence levels, a subterm printed at a level
+/// lower than required gets parenthesized.
+const PREC_OPEN: u8 = 0; // λx. e, if e then e else e
+const PREC_ADD: u8 = 1; // e + e
+const PREC_APP: u8 = 2; // e a
+const PREC_UNARY: u8 = 3; // incr a, decr a, is_zero a
+const PREC_ATOM: u8 = 4; // x, n, true, false
+
+fn exp_prec(e: &Exp) -> u8 {
+    match e {
+        Exp::Lambda(_) | Exp::Cond(_) => PREC_OPEN,
+        Exp::Add(_) => PREC_ADD,
+        Exp::App(_) => PREC_APP,
+        Exp::IsZero(_) | Exp::Incr(_) | Exp::Decr(_) => PREC_UNARY,
+        Exp::Var(_) | Exp::Nat(_) | Exp::True | Exp::False => PREC_ATOM,
+    }
+}
+
+fn exp_doc_at(e: &Exp, prec: u8, config: &PrettyConfig) -> Doc {
+    let doc = exp_doc(e, config);
+    if exp_prec(e) < prec {
+        parens(doc)
+    } else {
+        doc
+    }
+}
+
+fn exp_doc(e: &Exp, config: &PrettyConfig) -> Doc {
+    match e {
+        Exp::Var(v) => text(v.clone()),
+        Exp::Nat(n) => text(n.to_string()),
+        Exp::True => text("true"),
+        Exp::False => text("false"),
+        Exp::Lambda(_) => {
+            // keep the binders of nested lambda abstractions
+            // on the same line, i.e., `λx. λy. λz.`
+            let mut binders = vec![];
+            let mut body = e;
+            while let Exp::Lambda(lambda) = body {
+                binders.push(match &lambda.ty {
+                    Some(ty) => format!(
+                        "{}{}: {}.",
+                        config.lambda(),
+                        lambda.arg,
+                        type_doc(ty, config)
+                    ),
+                    None => format!("{}{}.", config.lambda(), lambda.arg),
+                });
+                body = &lambda.exp;
+            }
+            group(concat(vec![
+                text(binders.join(" ")),
+                nest(2, concat(vec![Doc::Line, exp_doc(body, config)])),
+            ]))
+        }
+        Exp::App(_) => {
+            // flatten the (left associative) spine, i.e., `f a1 a2 ... an`
+            let mut args = vec![];
+            let mut head = e;
+            while let Exp::App(app) = head {
+                args.push(&app.t2);
+                head = &app.t1;
+            }
+            let mut docs = vec![];
+            for arg in args.into_iter().rev() {
+                docs.push(Doc::Line);
+                docs.push(exp_doc_at(arg, PREC_ATOM, config));
+            }
+            group(concat(vec![
+                exp_doc_at(head, PREC_UNARY, config),
+                nest(2, concat(docs)),
+            ]))
+        }
+        Exp::Cond(cond) => group(concat(vec![
+            text("if "),
+            nest(3, exp_doc(&cond.r#if, config)),
+            Doc::Line,
+            text("then "),
+            nest(5, exp_doc(&cond.r#then, config)),
+            Doc::Line,
+            text("else "),
+            nest(5, exp_doc(&cond.r#else, config)),
+        ])),
+        Exp::IsZero(t) => unary_doc("is_zero", t, config),
+        Exp::Incr(t) => unary_doc("incr", t, config),
+        Exp::Decr(t) => unary_doc("decr", t, config),
+        Exp::Add(add) => group(concat(vec![
+            exp_doc_at(&add.t1, PREC_ADD, config),
+            Doc::Line,
+            text("+ "),
+            exp_doc_at(&add.t2, PREC_APP, config),
+        ])),
+    }
+}
+
+fn unary_doc(op: &str, t: &Exp, config: &PrettyConfig) -> Doc {
+    concat(vec![
+        text(format!("{} ", op)),
+        exp_doc_at(t, PREC_ATOM, config),
+    ])
+}
+
+/// types never get wrapped, they are usually short enough.
+fn type_doc(ty: &Type, config: &PrettyConfig) -> String {
+    match ty {
+        Type::TArrow(t) => {
+            let ty1 = type_doc(&t.ty1, config);
+            let ty1 = if t.ty1.is_arrow() {
+                format!("({})", ty1)
+            } else {
+                ty1
+            };
+            format!("{} {} {}", ty1, config.arrow(), type_doc(&t.ty2, config))
+        }
+        _ => ty.to_string(),
+    }
+}
+
+impl Exp {
+    /// pretty print the current expression with minimal parentheses,
+    /// see the module level documentation for details.
+    pub fn pretty(&self, config: &PrettyConfig) -> String {
+        render(&exp_doc(self, config), config.width)
+    }
+}
+
+impl Type {
+    /// pretty print the current type with minimal parentheses,
+    /// e.g., `(int -> bool) -> int`.
+    pub fn pretty(&self, config: &PrettyConfig) -> String {
+        type_doc(self, config)
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{
-    expr::{app::App, cond::Cond},
+    expr::{add::Add, app::App, cond::Cond, path::Path},
+    limits::{EvalConfig, Limits},
+    normalize::NormalForm,
+    span::{Diagnostic, Spans},
     stlc_err::StlcError,
     Exp, Strategy,
 };
@@ -116,6 +119,32 @@ impl Exp {
                 // Decr t -> Decr t'
                 _ => Ok(Exp::Decr(Box::new(e.eval(strategy)?))),
             },
+            Exp::Add(add) => match (&add.t1, &add.t2) {
+                // ------------------- (n = n1 + n2)
+                // n1 + n2 -> n
+                (Exp::Nat(n1), Exp::Nat(n2)) => Ok(Exp::Nat(n1.saturating_add(*n2))),
+                // note: the addition is strict under both strategies,
+                // i.e., both operands are evaluated before adding them up.
+                //      t1 -> t1'
+                // -------------------
+                // t1 + t2 -> t1' + t2
+                (t1, _) if !t1.ref_is_value() => Ok(Add::build(add.t1.eval(strategy)?, add.t2)),
+                //      t2 -> t2'
+                // -----------------
+                // n + t2 -> n + t2'
+                (Exp::Nat(_), t2) if !t2.ref_is_value() => {
+                    Ok(Add::build(add.t1, add.t2.eval(strategy)?))
+                }
+                // either operand is a value other than a number, e.g., `(λx. x) + 1`
+                _ => match strategy {
+                    Strategy::CallByValue | Strategy::ApplicativeOrder => {
+                        Err(StlcError::StuckExpressionCbv(format!("{}", self)))
+                    }
+                    Strategy::CallByName | Strategy::CallByNeed | Strategy::NormalOrder => {
+                        Err(StlcError::StuckExpressionCbn(format!("{}", self)))
+                    }
+                },
+            },
             _ => Err(StlcError::InvalidExpression(format!("{}", self))),
         }
     }
@@ -128,33 +157,165 @@ impl Exp {
         self.eval(Strategy::CallByName)
     }
 
+    /// note: a single step has nothing to share,
+    /// so call-by-need steps exactly as call-by-name here.
     pub fn ref_eval_multi_step(mut self, step: u32, strategy: Strategy) -> Result<Exp> {
         for _ in 0..step {
             self = match strategy {
                 Strategy::CallByValue => self.ref_eval_one_step_cbv()?,
-                Strategy::CallByName => self.ref_eval_one_step_cbn()?,
+                Strategy::CallByName | Strategy::CallByNeed => self.ref_eval_one_step_cbn()?,
+                Strategy::NormalOrder | Strategy::ApplicativeOrder => {
+                    match self.ref_reduce_one_step(strategy, NormalForm::Full)? {
+                        Some(e) => e,
+                        None => return Err(StlcError::InvalidExpression(format!("{}", self))),
+                    }
+                }
             }
         }
         Ok(self)
     }
 
-    fn ref_upper_bound(&self) -> u32 {
-        1000000
+    pub fn ref_eval_to_normal_form(self, strategy: Strategy) -> Result<(Exp, u32)> {
+        self.ref_eval_to_normal_form_with(strategy, &EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
     }
 
-    pub fn ref_eval_to_normal_form(mut self, strategy: Strategy) -> Result<(Exp, u32)> {
-        for i in 0..=self.ref_upper_bound() {
+    /// Same as `ref_eval_to_normal_form`, but within the limits of `config`.
+    pub fn ref_eval_to_normal_form_with(
+        mut self,
+        strategy: Strategy,
+        config: &EvalConfig,
+    ) -> Result<(Exp, u32)> {
+        match strategy {
+            Strategy::CallByNeed => return self.ref_eval_by_need_with(config),
+            // reduce under binders as well, see `normalize.rs`
+            Strategy::NormalOrder | Strategy::ApplicativeOrder => {
+                return self.ref_normalize_with(strategy, NormalForm::Full, config)
+            }
+            Strategy::CallByValue | Strategy::CallByName => (),
+        }
+        let mut limits = Limits::new(config);
+        let mut steps = 0;
+        loop {
             if self.ref_is_value() {
-                return Ok((self, i));
+                return Ok((self, steps));
             }
+            limits.check(&self, steps, || self.clone())?;
             self = match strategy {
                 Strategy::CallByValue => self.ref_eval_one_step_cbv()?,
-                Strategy::CallByName => self.ref_eval_one_step_cbn()?,
+                _ => self.ref_eval_one_step_cbn()?,
+            };
+            steps += 1;
+        }
+    }
+
+    /// The path to the subterm `eval` will reduce next under the given strategy,
+    /// i.e., it follows the exact same congruence rules as `eval`.
+    /// when the expression is stuck, this is where it gets stuck instead,
+    /// e.g., `[0]` (the `true`) for `true 1`.
+    pub fn ref_redex_path(&self, strategy: Strategy) -> Path {
+        let mut path = vec![];
+        let mut e = self;
+        loop {
+            let next = match e {
+                Exp::App(app) => match &app.t1 {
+                    // (\x. t) v -> [x := v] t || (\x. t1) t2 -> [x := t2] t1
+                    Exp::Lambda(_) => {
+                        if strategy != Strategy::CallByValue || app.t2.ref_is_value() {
+                            return path;
+                        }
+                        1
+                    }
+                    // v t2 is stuck
+                    t1 if t1.ref_is_value() => {
+                        path.push(0);
+                        return path;
+                    }
+                    _ => 0,
+                },
+                Exp::Cond(cond) => match &cond.r#if {
+                    Exp::True | Exp::False => return path,
+                    // if v then t2 else t3 is stuck
+                    r#if if r#if.ref_is_value() => {
+                        path.push(0);
+                        return path;
+                    }
+                    _ => 0,
+                },
+                Exp::IsZero(t) | Exp::Incr(t) | Exp::Decr(t) => match **t {
+                    Exp::Nat(_) => return path,
+                    _ => 0,
+                },
+                Exp::Add(add) => match (&add.t1, &add.t2) {
+                    (Exp::Nat(_), Exp::Nat(_)) => return path,
+                    (t1, _) if !t1.ref_is_value() => 0,
+                    (Exp::Nat(_), t2) if !t2.ref_is_value() => 1,
+                    // v1 + v2 is stuck, blame the operand that is not a number
+                    (t1, _) => {
+                        path.push(if matches!(t1, Exp::Nat(_)) { 1 } else { 0 });
+                        return path;
+                    }
+                },
+                _ => return path,
+            };
+            path.push(next);
+            e = e.children()[next];
+        }
+    }
+
+    /// Same as `ref_eval_to_normal_form`, but keeps `spans` (of the initial
+    /// expression) in sync after each step, so that when the evaluation
+    /// fails the returned diagnostic could point to the offending subterm.
+    /// note: only call-by-value and call-by-name are tracked, the other
+    /// strategies point to the whole expression instead.
+    pub fn ref_eval_to_normal_form_spanned(
+        self,
+        strategy: Strategy,
+        spans: &mut Spans,
+    ) -> std::result::Result<(Exp, u32), Diagnostic> {
+        self.ref_eval_to_normal_form_spanned_with(strategy, spans, &EvalConfig::default())
+    }
+
+    /// Same as `ref_eval_to_normal_form_spanned`, but within the limits of `config`.
+    pub fn ref_eval_to_normal_form_spanned_with(
+        mut self,
+        strategy: Strategy,
+        spans: &mut Spans,
+        config: &EvalConfig,
+    ) -> std::result::Result<(Exp, u32), Diagnostic> {
+        if !matches!(strategy, Strategy::CallByValue | Strategy::CallByName) {
+            return self
+                .ref_eval_to_normal_form_with(strategy, config)
+                .map_err(|err| Diagnostic::new(err.to_string(), spans.lookup(&[])));
+        }
+        let mut limits = Limits::new(config);
+        let mut steps = 0;
+        loop {
+            if self.ref_is_value() {
+                return Ok((self, steps));
+            }
+            if let Err(err) = limits.check(&self, steps, || self.clone()) {
+                return Err(Diagnostic::new(err.to_string(), spans.lookup(&[])));
             }
+            let path = self.ref_redex_path(strategy);
+            // the part of the redex that survives the step, if any
+            let kept: Option<&[usize]> = match self.subterm(&path) {
+                Some(Exp::App(app)) if matches!(app.t1, Exp::Lambda(_)) => Some(&[0, 0]),
+                Some(Exp::Cond(cond)) if cond.r#if == Exp::True => Some(&[1]),
+                Some(Exp::Cond(cond)) if cond.r#if == Exp::False => Some(&[2]),
+                _ => None,
+            };
+            self = match self.eval(strategy) {
+                Ok(e) => e,
+                Err(err) => {
+                    return Err(Diagnostic::new(
+                        err.to_string(),
+                        spans.lookup_nearest(&path),
+                    ))
+                }
+            };
+            spans.contract(&path, kept);
+            steps += 1;
         }
-        Err(StlcError::ExceedEvalLimit(format!(
-            "exceed evaluation limit, current expr: {}",
-            self
-        )))
     }
 }
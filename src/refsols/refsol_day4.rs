@@ -24,11 +24,11 @@ impl Exp {
 
     pub fn ref_is_stuck(&self, strategy: Strategy) -> bool {
         match strategy {
-            Strategy::CallByValue => {
+            Strategy::CallByValue | Strategy::ApplicativeOrder => {
                 let e = self.clone().eval_one_step_cbv().unwrap();
                 e == self.clone()
             }
-            Strategy::CallByName => {
+            Strategy::CallByName | Strategy::CallByNeed | Strategy::NormalOrder => {
                 let e = self.clone().eval_one_step_cbn().unwrap();
                 e == self.clone()
             }
@@ -143,4 +143,25 @@ impl YCombinator {
         let (result, steps) = e.ref_eval_to_normal_form(strategy)?;
         Ok((result, steps))
     }
+
+    /// same as `ref_eval`, but by the (much faster) big-step evaluator.
+    pub fn ref_eval_big_step(self, inputs: Vec<Exp>, strategy: Strategy) -> Result<(Exp, u32)> {
+        self.ref_build_eval_expr(inputs)
+            .ref_eval_to_normal_form_big_step(strategy)
+    }
+
+    /// same as `ref_eval`, but compiled and run on the virtual machine.
+    pub fn ref_eval_vm(self, inputs: Vec<Exp>, strategy: Strategy) -> Result<(Exp, u32)> {
+        self.ref_build_eval_expr(inputs).ref_eval_vm(strategy)
+    }
+
+    /// the first-order program of `ref_eval`, see `defunc`.
+    pub fn ref_defunctionalize(
+        self,
+        inputs: Vec<Exp>,
+        strategy: Strategy,
+    ) -> Result<crate::defunc::Program> {
+        self.ref_build_eval_expr(inputs)
+            .ref_defunctionalize(strategy)
+    }
 }
@@ -16,7 +16,7 @@ impl Exp {
         self.ref_annotate_term_inner(0)
     }
 
-    fn ref_annotate_term_inner(&mut self, n: u32) -> u32 {
+    pub(crate) fn ref_annotate_term_inner(&mut self, n: u32) -> u32 {
         match self.clone() {
             Self::Lambda(mut lambda) => {
                 let ret = lambda.exp.ref_annotate_term_inner(n + 1);
@@ -135,7 +135,16 @@ impl Exp {
                 ]);
                 Some((n2, Type::TInt, TyConstraints::merge(vec![c1, c2, c])))
             }
-            _ => panic!("not yet supported: {}", self),
+            // ct-iszero
+            Self::IsZero(e) => e.ref_infer_constraints(env, n).map(|(n1, t, c1)| {
+                let c = TyConstraints::build(vec![TyConstraint::build(t, Type::TInt)]);
+                (n1, Type::TBool, TyConstraints::merge(vec![c1, c]))
+            }),
+            // ct-incr & ct-decr
+            Self::Incr(e) | Self::Decr(e) => e.ref_infer_constraints(env, n).map(|(n1, t, c1)| {
+                let c = TyConstraints::build(vec![TyConstraint::build(t, Type::TInt)]);
+                (n1, Type::TInt, TyConstraints::merge(vec![c1, c]))
+            }),
         }
     }
 }
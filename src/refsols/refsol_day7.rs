@@ -71,6 +71,11 @@ impl Type {
                             // unify(ξ' ∪ {S1 = T1, S2 = T2})
                             Self::ref_unify(tc)
                         }
+                        // S1 -> S2 = X, i.e., the same as X = S1 -> S2
+                        Self::TVar(_) => {
+                            tc.insert(0, TyConstraint::build(right, Self::TArrow(a1)));
+                            Self::ref_unify(tc)
+                        }
                         _ => None,
                     }
                 }
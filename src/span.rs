@@ -0,0 +1,137 @@
+//! Source locations for expressions built from text.
+//! The spans live *beside* the `Exp` rather than inside it (so that
+//! every existing pattern match on `Exp` stays untouched), keyed by
+//! the `Path` of the corresponding subterm.
+
+use std::collections::HashMap;
+
+use crate::expr::path::Path;
+
+/// A byte range `[start, end)` in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// the smallest span covering both.
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// The (optional) span of every subterm of an `Exp`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Spans(HashMap<Path, Span>);
+
+impl Spans {
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    pub fn insert(&mut self, path: Path, span: Span) -> Option<Span> {
+        self.0.insert(path, span)
+    }
+
+    pub fn lookup(&self, path: &[usize]) -> Option<Span> {
+        self.0.get(path).copied()
+    }
+
+    /// the span of the subterm at `path`, or of its closest
+    /// ancestor that still has one.
+    pub fn lookup_nearest(&self, path: &[usize]) -> Option<Span> {
+        (0..=path.len())
+            .rev()
+            .find_map(|len| self.lookup(&path[..len]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// keep the spans in sync after the subterm at `redex` has been
+    /// reduced by one step.
+    /// `kept` is the position (relative to `redex`) of the part of
+    /// the redex that becomes the result, e.g., `[1]` for
+    /// `if true then t1 else t2 -> t1`; every other span strictly
+    /// inside the redex is dropped since it no longer exists.
+    pub fn contract(&mut self, redex: &[usize], kept: Option<&[usize]>) {
+        let old = std::mem::take(&mut self.0);
+        for (path, span) in old {
+            if !path.starts_with(redex) {
+                self.0.insert(path, span);
+                continue;
+            }
+            if let Some(kept) = kept {
+                let rest = &path[redex.len()..];
+                if rest.starts_with(kept) {
+                    let mut path = redex.to_vec();
+                    path.extend_from_slice(&rest[kept.len()..]);
+                    self.0.insert(path, span);
+                    continue;
+                }
+            }
+            if path.len() == redex.len() {
+                // the redex itself, unless overwritten by the kept part
+                self.0.entry(path).or_insert(span);
+            }
+        }
+    }
+}
+
+/// An error message optionally pointing to somewhere in the source,
+/// rendered in the same style as rustc does, i.e.,
+///
+/// ```text
+/// error: TypeError(expect int, actual: bool)
+///  --> 1:24
+///   |
+/// 1 | λx: int. λy: bool. x + y
+///   |                        ^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Option<Span>) -> Self {
+        Self { message, span }
+    }
+
+    pub fn render(&self, src: &str) -> String {
+        let mut out = format!("error: {}", self.message);
+        let Some(span) = self.span else {
+            return out;
+        };
+        let start = span.start.min(src.len());
+        let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+        let line_no = src[..start].matches('\n').count() + 1;
+        let column = src[line_start..start].chars().count();
+        // a span across multiple lines is underlined till the end of the first one
+        let end = span.end.clamp(start, line_end);
+        let width = src[start..end].chars().count().max(1);
+        let gutter = " ".repeat(line_no.to_string().len());
+        out.push_str(&format!("\n{}--> {}:{}", gutter, line_no, column + 1));
+        out.push_str(&format!("\n{} |", gutter));
+        out.push_str(&format!("\n{} | {}", line_no, &src[line_start..line_end]));
+        out.push_str(&format!(
+            "\n{} | {}{}",
+            gutter,
+            " ".repeat(column),
+            "^".repeat(width)
+        ));
+        out
+    }
+}
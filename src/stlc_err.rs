@@ -1,5 +1,7 @@
 use core::fmt;
 
+use crate::Exp;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StlcError {
     /// Indicating the current expression is impossible
@@ -25,6 +27,27 @@ pub enum StlcError {
     /// our operational semantics this will take a huge amount
     /// of steps to reduce the input expression to its normal form.
     ExceedEvalLimit(String),
+
+    /// The evaluation takes more steps than `EvalConfig::max_steps`,
+    /// carrying the term reached so far with the number of steps taken.
+    ExceedStepLimit { term: Exp, steps: u32 },
+
+    /// Same as above, but runs out of `EvalConfig::timeout`.
+    Timeout { term: Exp, steps: u32 },
+
+    /// Same as above, but the term grows larger than `EvalConfig::max_size`.
+    ExceedSizeLimit { term: Exp, steps: u32 },
+
+    /// Same as above, but the term grows deeper than `EvalConfig::max_depth`.
+    ExceedDepthLimit { term: Exp, steps: u32 },
+
+    /// The expression reduces back to itself (up to alpha-equivalence)
+    /// every `cycle` steps, e.g., ω, so it never reaches a value.
+    Diverges { cycle: u32, term: Exp },
+
+    /// The given source text is not a valid expression,
+    /// e.g., `λx x` (missing the dot) or `if true then 1`.
+    ParseError(String),
     // TODO: add more custom errors to fit your need(s)!
 }
 
@@ -35,6 +58,49 @@ impl fmt::Display for StlcError {
             StlcError::StuckExpressionCbn(err) => write!(f, "StuckExpressionCbn({})", err),
             StlcError::InvalidExpression(err) => write!(f, "InvalidExpression({})", err),
             StlcError::ExceedEvalLimit(err) => write!(f, "ExceedEvalLimit({})", err),
+            StlcError::ParseError(err) => write!(f, "ParseError({})", err),
+            StlcError::ExceedStepLimit { term, steps } => {
+                write!(
+                    f,
+                    "ExceedStepLimit(after {} steps, current expr: {})",
+                    steps, term
+                )
+            }
+            StlcError::Timeout { term, steps } => {
+                write!(f, "Timeout(after {} steps, current expr: {})", steps, term)
+            }
+            StlcError::ExceedSizeLimit { term, steps } => write!(
+                f,
+                "ExceedSizeLimit(after {} steps, current expr of size {}: {})",
+                steps,
+                term.size(),
+                term
+            ),
+            StlcError::ExceedDepthLimit { term, steps } => write!(
+                f,
+                "ExceedDepthLimit(after {} steps, current expr of depth {}: {})",
+                steps,
+                term.depth(),
+                term
+            ),
+            StlcError::Diverges { cycle, term } => {
+                write!(
+                    f,
+                    "Diverges(every {} steps, repeating expr: {})",
+                    cycle, term
+                )
+            }
         }
     }
 }
+
+impl StlcError {
+    /// the error of an if clause evaluated to `actual`, a value other than
+    /// `true` or `false`, with the same message as `ref_eval_one_step`.
+    pub(crate) fn non_boolean_if(actual: impl fmt::Display) -> Self {
+        StlcError::InvalidExpression(format!(
+            "expect if clause not to be values other than `true` or `false, actual: {}",
+            actual
+        ))
+    }
+}
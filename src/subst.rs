@@ -0,0 +1,247 @@
+//! Capture-avoiding substitution.
+//! The naive `[x := s] (λy. t) = λy. [x := s] t` is only correct when `y`
+//! does not appear free in `s`, otherwise the free `y` of `s` gets
+//! *captured*, e.g., `[x := y] (λy. x)` would become `λy. y`.
+//! The fix is to rename the binder to a fresh name first, i.e.,
+//! `[x := y] (λy. x) = λy1. y`.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    expr::{
+        add::Add, app::App, cond::Cond, decr::Decr, incr::Incr, is_zero::IsZero, lambda::Lambda,
+        var::Var,
+    },
+    Exp,
+};
+
+/// A supply of fresh variable names, i.e., names that
+/// have never been seen (or handed out) before.
+#[derive(Debug, Clone, Default)]
+pub struct FreshNames {
+    used: BTreeSet<String>,
+}
+
+impl FreshNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a supply avoiding every (free or bound) variable of the given expressions.
+    pub fn avoiding(exps: &[&Exp]) -> Self {
+        let mut fresh = Self::new();
+        for e in exps {
+            fresh.used.extend(e.all_vars());
+        }
+        fresh
+    }
+
+    /// mark `name` as used, so that it will never be handed out.
+    pub fn reserve(&mut self, name: &str) {
+        self.used.insert(name.into());
+    }
+
+    /// a fresh name that looks like `base`, e.g., `x1`, `x2`, etc. for `x`.
+    pub fn fresh(&mut self, base: &str) -> String {
+        let stem = base.trim_end_matches(|c: char| c.is_ascii_digit() || c == '\'');
+        let stem = if stem.is_empty() { "x" } else { stem };
+        let name = (1..)
+            .map(|i| format!("{}{}", stem, i))
+            .find(|name| !self.used.contains(name))
+            .unwrap();
+        self.used.insert(name.clone());
+        name
+    }
+}
+
+/// How binders are treated when substituting under them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubstMode {
+    /// only rename a binder when it would capture
+    /// a free variable of the substituted term.
+    CaptureAvoiding,
+    /// rename *every* binder on the way to a fresh name, and give each
+    /// copy of the substituted term fresh binders as well, so that
+    /// all binders in the result are distinct (the Barendregt convention).
+    Barendregt,
+}
+
+impl Exp {
+    /// all the variables appearing free in the current expression.
+    pub fn free_vars(&self) -> BTreeSet<String> {
+        fn free_vars_inner(e: &Exp, bound: &mut Vec<String>, fv: &mut BTreeSet<String>) {
+            match e {
+                Exp::Var(v) => {
+                    if !bound.contains(v) {
+                        fv.insert(v.clone());
+                    }
+                }
+                Exp::Lambda(lambda) => {
+                    bound.push(lambda.arg.clone());
+                    free_vars_inner(&lambda.exp, bound, fv);
+                    bound.pop();
+                }
+                _ => {
+                    for child in e.children() {
+                        free_vars_inner(child, bound, fv);
+                    }
+                }
+            }
+        }
+
+        let mut fv = BTreeSet::new();
+        free_vars_inner(self, &mut vec![], &mut fv);
+        fv
+    }
+
+    /// all the variables appearing in the current expression,
+    /// including the binders.
+    pub fn all_vars(&self) -> BTreeSet<String> {
+        let mut vars = BTreeSet::new();
+        let mut stack = vec![self];
+        while let Some(e) = stack.pop() {
+            match e {
+                Exp::Var(v) => {
+                    vars.insert(v.clone());
+                }
+                Exp::Lambda(lambda) => {
+                    vars.insert(lambda.arg.clone());
+                }
+                _ => (),
+            }
+            stack.extend(e.children());
+        }
+        vars
+    }
+
+    /// `[var := s] self`, renaming binders per `mode` with names from `fresh`.
+    /// note: `fresh` should at least avoid every variable of `self` and `s`,
+    /// e.g., the one returned by `FreshNames::avoiding(&[&self, &s])`.
+    pub fn substitute_with(
+        self,
+        var: &str,
+        s: &Exp,
+        mode: SubstMode,
+        fresh: &mut FreshNames,
+    ) -> Exp {
+        let fv = s.free_vars();
+        self.substitute_inner(var, s, &fv, mode, fresh)
+    }
+
+    fn substitute_inner(
+        self,
+        var: &str,
+        s: &Exp,
+        fv: &BTreeSet<String>,
+        mode: SubstMode,
+        fresh: &mut FreshNames,
+    ) -> Exp {
+        match self {
+            // [x := s] x && [x := s] y
+            Exp::Var(v) => {
+                if v != var {
+                    Exp::Var(v)
+                } else if mode == SubstMode::Barendregt {
+                    s.clone().rename_binders(fresh)
+                } else {
+                    s.clone()
+                }
+            }
+            // [x := s] (λx. t) && [x := s] (λy. t)
+            Exp::Lambda(lambda) => {
+                let shadowed = lambda.arg == var;
+                if shadowed {
+                    return match mode {
+                        SubstMode::CaptureAvoiding => Exp::Lambda(lambda),
+                        SubstMode::Barendregt => Exp::Lambda(lambda).rename_binders(fresh),
+                    };
+                }
+                let Lambda { arg, exp, ty } = *lambda;
+                // rename `y` to a fresh `y'` first if needed,
+                // i.e., λy'. [x := s] ([y := y'] t)
+                let (arg, exp) = if mode == SubstMode::Barendregt || fv.contains(&arg) {
+                    let y = fresh.fresh(&arg);
+                    let exp = exp.substitute_inner(
+                        &arg,
+                        &Var::build(&y),
+                        &BTreeSet::from([y.clone()]),
+                        SubstMode::CaptureAvoiding,
+                        fresh,
+                    );
+                    (y, exp)
+                } else {
+                    (arg, exp)
+                };
+                let exp = exp.substitute_inner(var, s, fv, mode, fresh);
+                Lambda { arg, exp, ty }.into()
+            }
+            // [x := s] (t1 t2)
+            Exp::App(app) => App::build(
+                app.t1.substitute_inner(var, s, fv, mode, fresh),
+                app.t2.substitute_inner(var, s, fv, mode, fresh),
+            ),
+            // [x := s] (if t1 then t2 else t3)
+            Exp::Cond(cond) => Cond::build(
+                cond.r#if.substitute_inner(var, s, fv, mode, fresh),
+                cond.r#then.substitute_inner(var, s, fv, mode, fresh),
+                cond.r#else.substitute_inner(var, s, fv, mode, fresh),
+            ),
+            // [x := s] (t1 + t2)
+            Exp::Add(add) => Add::build(
+                add.t1.substitute_inner(var, s, fv, mode, fresh),
+                add.t2.substitute_inner(var, s, fv, mode, fresh),
+            ),
+            Exp::IsZero(e) => IsZero::build(e.substitute_inner(var, s, fv, mode, fresh)),
+            Exp::Incr(e) => Incr::build(e.substitute_inner(var, s, fv, mode, fresh)),
+            Exp::Decr(e) => Decr::build(e.substitute_inner(var, s, fv, mode, fresh)),
+            // [x := s] true && [x := s] false && [x := s] n
+            e => e,
+        }
+    }
+
+    /// the simultaneous `[x1 := s1, .., xn := sn] self`, i.e., an `xj` free in
+    /// some `si` is left intact, unlike substituting them one after another.
+    pub fn substitute_many(self, substs: &[(String, Exp)]) -> Exp {
+        let mut exps = vec![&self];
+        exps.extend(substs.iter().map(|(_, s)| s));
+        let mut fresh = FreshNames::avoiding(&exps);
+        // first rename each `xi` to a fresh placeholder, then
+        // replace the placeholders, which appear nowhere else.
+        let placeholders: Vec<String> = substs.iter().map(|(x, _)| fresh.fresh(x)).collect();
+        let e = substs
+            .iter()
+            .zip(&placeholders)
+            .fold(self, |e, ((x, _), y)| {
+                e.substitute_with(x, &Var::build(y), SubstMode::CaptureAvoiding, &mut fresh)
+            });
+        substs.iter().zip(&placeholders).fold(e, |e, ((_, s), y)| {
+            e.substitute_with(y, s, SubstMode::CaptureAvoiding, &mut fresh)
+        })
+    }
+
+    /// rename every binder to a fresh name from `fresh`,
+    /// i.e., an alpha-equivalent term satisfying the Barendregt convention.
+    pub fn rename_binders(self, fresh: &mut FreshNames) -> Exp {
+        match self {
+            Exp::Lambda(lambda) => {
+                let Lambda { arg, exp, ty } = *lambda;
+                let y = fresh.fresh(&arg);
+                let exp = exp
+                    .substitute_with(&arg, &Var::build(&y), SubstMode::CaptureAvoiding, fresh)
+                    .rename_binders(fresh);
+                Lambda { arg: y, exp, ty }.into()
+            }
+            Exp::App(app) => App::build(app.t1.rename_binders(fresh), app.t2.rename_binders(fresh)),
+            Exp::Add(add) => Add::build(add.t1.rename_binders(fresh), add.t2.rename_binders(fresh)),
+            Exp::Cond(cond) => Cond::build(
+                cond.r#if.rename_binders(fresh),
+                cond.r#then.rename_binders(fresh),
+                cond.r#else.rename_binders(fresh),
+            ),
+            Exp::IsZero(e) => IsZero::build(e.rename_binders(fresh)),
+            Exp::Incr(e) => Incr::build(e.rename_binders(fresh)),
+            Exp::Decr(e) => Decr::build(e.rename_binders(fresh)),
+            e => e,
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Small-step evaluation traces annotated with the rules, i.e., for each
+//! step, the axiom fired at the redex together with the congruence rules
+//! leading to it, e.g.,
+//!
+//! ```text
+//! incr ((λx. x) 1)  ⟶  incr 1    by E-Incr(E-AppAbs)
+//! incr 1            ⟶  2         by E-IncrNat
+//! ```
+
+use core::fmt;
+
+use crate::{expr::path::Path, stlc_err::StlcError, Exp, Strategy};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// The evaluation rules, the axioms first, then the congruence rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// `(λx. t) v ⟶ [x := v] t`, or any argument under call-by-name.
+    AppAbs,
+    IfTrue,
+    IfFalse,
+    /// `is_zero 0 ⟶ true`
+    IsZeroZero,
+    /// `is_zero n ⟶ false`, for `n` other than 0.
+    IsZeroSucc,
+    IncrNat,
+    DecrNat,
+    /// `n1 + n2 ⟶ n`
+    AddNat,
+    /// `t1 t2 ⟶ t1' t2`
+    App1,
+    /// `v1 t2 ⟶ v1 t2'`, only under call-by-value.
+    App2,
+    If,
+    IsZero,
+    Incr,
+    Decr,
+    /// `t1 + t2 ⟶ t1' + t2`
+    Add1,
+    /// `n1 + t2 ⟶ n1 + t2'`
+    Add2,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rule::AppAbs => "AppAbs",
+            Rule::IfTrue => "IfTrue",
+            Rule::IfFalse => "IfFalse",
+            Rule::IsZeroZero => "IsZeroZero",
+            Rule::IsZeroSucc => "IsZeroSucc",
+            Rule::IncrNat => "IncrNat",
+            Rule::DecrNat => "DecrNat",
+            Rule::AddNat => "AddNat",
+            Rule::App1 => "App1",
+            Rule::App2 => "App2",
+            Rule::If => "If",
+            Rule::IsZero => "IsZero",
+            Rule::Incr => "Incr",
+            Rule::Decr => "Decr",
+            Rule::Add1 => "Add1",
+            Rule::Add2 => "Add2",
+        };
+        write!(f, "E-{}", name)
+    }
+}
+
+impl Rule {
+    /// the axiom reducing `redex` itself, if any.
+    fn axiom(redex: &Exp) -> Option<Rule> {
+        match redex {
+            Exp::App(app) if matches!(app.t1, Exp::Lambda(_)) => Some(Rule::AppAbs),
+            Exp::Cond(cond) => match cond.r#if {
+                Exp::True => Some(Rule::IfTrue),
+                Exp::False => Some(Rule::IfFalse),
+                _ => None,
+            },
+            Exp::IsZero(t) => match **t {
+                Exp::Nat(0) => Some(Rule::IsZeroZero),
+                Exp::Nat(_) => Some(Rule::IsZeroSucc),
+                _ => None,
+            },
+            Exp::Incr(t) if matches!(**t, Exp::Nat(_)) => Some(Rule::IncrNat),
+            Exp::Decr(t) if matches!(**t, Exp::Nat(_)) => Some(Rule::DecrNat),
+            Exp::Add(add) if matches!((&add.t1, &add.t2), (Exp::Nat(_), Exp::Nat(_))) => {
+                Some(Rule::AddNat)
+            }
+            _ => None,
+        }
+    }
+
+    /// the congruence rule descending into the `i`-th child of `e`.
+    fn congruence(e: &Exp, i: usize) -> Option<Rule> {
+        match (e, i) {
+            (Exp::App(_), 0) => Some(Rule::App1),
+            (Exp::App(_), 1) => Some(Rule::App2),
+            (Exp::Cond(_), 0) => Some(Rule::If),
+            (Exp::IsZero(_), 0) => Some(Rule::IsZero),
+            (Exp::Incr(_), 0) => Some(Rule::Incr),
+            (Exp::Decr(_), 0) => Some(Rule::Decr),
+            (Exp::Add(_), 0) => Some(Rule::Add1),
+            (Exp::Add(_), 1) => Some(Rule::Add2),
+            _ => None,
+        }
+    }
+}
+
+/// A single step of the evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub before: Exp,
+    pub after: Exp,
+    /// the axiom fired at the redex.
+    pub rule: Rule,
+    /// the congruence rules from the root down to the redex,
+    /// i.e., one per index of `path`.
+    pub context: Vec<Rule>,
+    /// the path to the redex, see `Path`.
+    pub path: Path,
+}
+
+impl Step {
+    /// the derivation of the step, e.g., `E-App1(E-Incr(E-AppAbs))`.
+    pub fn derivation(&self) -> String {
+        let mut derivation = self.rule.to_string();
+        for rule in self.context.iter().rev() {
+            derivation = format!("{}({})", rule, derivation);
+        }
+        derivation
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  ⟶  {}    by {}",
+            self.before,
+            self.after,
+            self.derivation()
+        )
+    }
+}
+
+impl Exp {
+    /// the step `ref_eval_multi_step(1, strategy)` takes, with its rules.
+    /// note: call-by-need steps exactly as call-by-name, same as `ref_eval_multi_step`.
+    pub fn ref_eval_one_step_traced(&self, strategy: Strategy) -> Result<Step> {
+        if !matches!(
+            strategy,
+            Strategy::CallByValue | Strategy::CallByName | Strategy::CallByNeed
+        ) {
+            return Err(StlcError::InvalidExpression(format!(
+                "{} is not supported by the traced evaluation",
+                strategy
+            )));
+        }
+        let after = self.clone().ref_eval_multi_step(1, strategy)?;
+        let path = self.ref_redex_path(strategy);
+        let mut context = vec![];
+        let mut e = self;
+        for &i in &path {
+            context.extend(Rule::congruence(e, i));
+            e = e.children()[i];
+        }
+        let rule = Rule::axiom(e).ok_or_else(|| StlcError::InvalidExpression(format!("{}", e)))?;
+        Ok(Step {
+            before: self.clone(),
+            after,
+            rule,
+            context,
+            path,
+        })
+    }
+
+    /// the steps of `ref_eval_multi_step(step, strategy)`,
+    /// fewer of them if it reaches a value earlier.
+    pub fn ref_eval_trace(mut self, step: u32, strategy: Strategy) -> Result<Vec<Step>> {
+        let mut steps = vec![];
+        for _ in 0..step {
+            if self.ref_is_value() {
+                break;
+            }
+            let next = self.ref_eval_one_step_traced(strategy)?;
+            self = next.after.clone();
+            steps.push(next);
+        }
+        Ok(steps)
+    }
+}
@@ -0,0 +1,184 @@
+//! A bidirectional type checker that, unlike `ref_ty_check`, explains
+//! *where* and *why* the type check fails instead of returning `false`.
+//! note: the term is expected to be fully annotated, i.e., typed.
+
+use core::fmt;
+
+use super::{tarrow::TArrow, Env, Type};
+use crate::{
+    expr::path::Path,
+    span::{Diagnostic, Spans},
+    Exp,
+};
+
+type Result<T> = std::result::Result<T, TyError>;
+
+/// The type error, together with the offending subterm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TyError {
+    pub path: Path,
+    pub message: String,
+}
+
+impl TyError {
+    fn new(path: &Path, message: String) -> Self {
+        Self {
+            path: path.clone(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for TyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TypeError({})", self.message)
+    }
+}
+
+fn synth(e: &Exp, env: &Env, path: &mut Path) -> Result<Type> {
+    match e {
+        // t-true & t-false
+        Exp::True | Exp::False => Ok(Type::TBool),
+        // t-num
+        Exp::Nat(_) => Ok(Type::TInt),
+        // t-var
+        Exp::Var(v) => env
+            .lookup(v)
+            .ok_or_else(|| TyError::new(path, format!("unbound variable `{}`", v))),
+        // t-abs
+        Exp::Lambda(lambda) => {
+            let Some(t1) = lambda.ty.clone() else {
+                return Err(TyError::new(
+                    path,
+                    format!("missing type annotation for `{}`", lambda.arg),
+                ));
+            };
+            let mut env = env.clone();
+            env.insert(lambda.arg.clone(), t1.clone());
+            path.push(0);
+            let t2 = synth(&lambda.exp, &env, path)?;
+            path.pop();
+            Ok(TArrow::build(t1, t2))
+        }
+        // t-app
+        Exp::App(app) => {
+            path.push(0);
+            let t = synth(&app.t1, env, path)?;
+            let Type::TArrow(t) = t else {
+                return Err(TyError::new(
+                    path,
+                    format!("expect a function, actual: {}", t),
+                ));
+            };
+            path.pop();
+            path.push(1);
+            check(&app.t2, &t.ty1, env, path)?;
+            path.pop();
+            Ok(t.ty2)
+        }
+        // t-if
+        Exp::Cond(cond) => {
+            path.push(0);
+            check(&cond.r#if, &Type::TBool, env, path)?;
+            path.pop();
+            path.push(1);
+            let t = synth(&cond.r#then, env, path)?;
+            path.pop();
+            path.push(2);
+            check(&cond.r#else, &t, env, path)?;
+            path.pop();
+            Ok(t)
+        }
+        // t-incr & t-decr & t-iszero
+        Exp::Incr(t) | Exp::Decr(t) | Exp::IsZero(t) => {
+            path.push(0);
+            check(t, &Type::TInt, env, path)?;
+            path.pop();
+            Ok(if let Exp::IsZero(_) = e {
+                Type::TBool
+            } else {
+                Type::TInt
+            })
+        }
+        // t-add
+        Exp::Add(add) => {
+            path.push(0);
+            check(&add.t1, &Type::TInt, env, path)?;
+            path.pop();
+            path.push(1);
+            check(&add.t2, &Type::TInt, env, path)?;
+            path.pop();
+            Ok(Type::TInt)
+        }
+    }
+}
+
+fn check(e: &Exp, ty: &Type, env: &Env, path: &mut Path) -> Result<()> {
+    match (e, ty) {
+        // the annotation could be omitted when checking against an arrow
+        (Exp::Lambda(lambda), Type::TArrow(t)) => {
+            if let Some(t1) = &lambda.ty {
+                if *t1 != t.ty1 {
+                    return Err(TyError::new(
+                        path,
+                        format!("expect `{}` to be {}, actual: {}", lambda.arg, t.ty1, t1),
+                    ));
+                }
+            }
+            let mut env = env.clone();
+            env.insert(lambda.arg.clone(), t.ty1.clone());
+            path.push(0);
+            check(&lambda.exp, &t.ty2, &env, path)?;
+            path.pop();
+            Ok(())
+        }
+        (Exp::Cond(cond), _) => {
+            path.push(0);
+            check(&cond.r#if, &Type::TBool, env, path)?;
+            path.pop();
+            path.push(1);
+            check(&cond.r#then, ty, env, path)?;
+            path.pop();
+            path.push(2);
+            check(&cond.r#else, ty, env, path)?;
+            path.pop();
+            Ok(())
+        }
+        _ => {
+            let actual = synth(e, env, path)?;
+            if actual != *ty {
+                return Err(TyError::new(
+                    path,
+                    format!("expect {}, actual: {}", ty, actual),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+impl Exp {
+    /// synthesize the type of the current expression under `env`.
+    pub fn ty_synth(&self, env: &Env) -> Result<Type> {
+        synth(self, env, &mut vec![])
+    }
+
+    /// check the current expression against `ty` under `env`.
+    pub fn ty_check_against(&self, ty: &Type, env: &Env) -> Result<()> {
+        check(self, ty, env, &mut vec![])
+    }
+
+    /// Type check (or synthesize, if `ty` is `None`) the current expression,
+    /// and point to the offending subterm via `spans` on failure.
+    pub fn ty_diagnose(
+        &self,
+        ty: Option<&Type>,
+        spans: &Spans,
+    ) -> std::result::Result<Type, Diagnostic> {
+        let result = match ty {
+            Some(ty) => self.ty_check_against(ty, &Env::new()).map(|_| ty.clone()),
+            None => self.ty_synth(&Env::new()),
+        };
+        result.map_err(|err| Diagnostic::new(err.to_string(), spans.lookup_nearest(&err.path)))
+    }
+}
@@ -5,8 +5,10 @@ use self::tarrow::TArrow;
 
 pub mod tarrow;
 
+pub mod check;
+
 /// the simple type(s) for our `Exp`
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum Type {
     /// type variables
     TVar(String),
@@ -155,7 +157,7 @@ impl From<&[TyConstraint]> for TyConstraints {
 /// the context for type check (and infer) - the mapping
 /// from *stlc variable* exp to type.
 /// note: used after day5.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Env(HashMap<String, Type>);
 
 impl Env {
@@ -174,6 +176,13 @@ impl Env {
     pub fn lookup(&self, key: &String) -> Option<Type> {
         self.0.get(key).cloned()
     }
+
+    /// every binding, sorted by the variable.
+    pub fn bindings(&self) -> Vec<(String, Type)> {
+        let mut bindings: Vec<_> = self.0.clone().into_iter().collect();
+        bindings.sort();
+        bindings
+    }
 }
 
 /// definition of type substituion - which is just the mapping
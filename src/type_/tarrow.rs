@@ -2,7 +2,7 @@ use core::fmt;
 
 use super::Type;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct TArrow {
     pub ty1: Type,
     pub ty2: Type,
@@ -26,6 +26,12 @@ impl From<TArrow> for Type {
 
 impl fmt::Display for TArrow {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} -> {}", self.ty1, self.ty2)
+        // arrow is right associative, so only the left
+        // hand side may need the parentheses.
+        if self.ty1.is_arrow() {
+            write!(f, "({}) -> {}", self.ty1, self.ty2)
+        } else {
+            write!(f, "{} -> {}", self.ty1, self.ty2)
+        }
     }
 }
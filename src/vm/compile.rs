@@ -0,0 +1,153 @@
+use super::{Block, BlockKind, Op, Program};
+use crate::{stlc_err::StlcError, Exp, Strategy};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+struct Compiler {
+    strategy: Strategy,
+    blocks: Vec<Block>,
+    free: Vec<String>,
+    operands: Vec<Exp>,
+}
+
+impl Compiler {
+    /// compile `e` into a new block, returns its index.
+    fn block(&mut self, kind: BlockKind, e: &Exp, source: &Exp, scope: Vec<String>) -> usize {
+        let index = self.blocks.len();
+        // reserved first, so that the entry is always the first one
+        self.blocks.push(Block {
+            kind,
+            code: vec![],
+            source: source.clone(),
+            scope: vec![],
+        });
+        let mut code = vec![];
+        self.emit(e, &scope, &mut code);
+        code.push(Op::Return);
+        self.blocks[index].code = code;
+        self.blocks[index].scope = scope;
+        index
+    }
+
+    /// `bound` with the position of `v` in the scope, or `Free` if not bound.
+    fn var(&mut self, v: &str, scope: &[String], bound: fn(usize) -> Op) -> Op {
+        if let Some(i) = scope.iter().position(|name| name == v) {
+            return bound(i);
+        }
+        match self.free.iter().position(|name| name == v) {
+            Some(i) => Op::Free(i),
+            None => {
+                self.free.push(v.to_string());
+                Op::Free(self.free.len() - 1)
+            }
+        }
+    }
+
+    /// check the first operand (on the stack) before `t2` is evaluated,
+    /// unless either one is a value already, i.e., nothing to diverge.
+    fn check(&mut self, t2: &Exp, check: fn(usize) -> Op, code: &mut Vec<Op>) {
+        if !t2.ref_is_value() {
+            self.operands.push(t2.clone());
+            code.push(check(self.operands.len() - 1));
+        }
+    }
+
+    fn emit(&mut self, e: &Exp, scope: &[String], code: &mut Vec<Op>) {
+        match e {
+            Exp::Var(v) => code.push(self.var(v, scope, Op::Access)),
+            Exp::Lambda(lambda) => {
+                let mut inner = vec![lambda.arg.clone()];
+                inner.extend_from_slice(scope);
+                let b = self.block(BlockKind::Lambda, &lambda.exp, e, inner);
+                code.push(Op::Closure(b));
+            }
+            Exp::App(app) => {
+                self.emit(&app.t1, scope, code);
+                // the argument is only evaluated (first) under call-by-value
+                if self.strategy == Strategy::CallByValue && !matches!(app.t1, Exp::Lambda(_)) {
+                    self.check(&app.t2, Op::CheckClosure, code);
+                }
+                match (&app.t2, self.strategy) {
+                    (t2, Strategy::CallByValue) => self.emit(t2, scope, code),
+                    // a bound variable is passed along as it is, i.e., shared
+                    (Exp::Var(v), _) if scope.contains(v) => {
+                        code.push(self.var(v, scope, Op::Share))
+                    }
+                    // nothing to delay for a value
+                    (t2 @ (Exp::Lambda(_) | Exp::True | Exp::False | Exp::Nat(_)), _) => {
+                        self.emit(t2, scope, code)
+                    }
+                    (t2, _) => {
+                        let b = self.block(BlockKind::Thunk, t2, t2, scope.to_vec());
+                        code.push(Op::Thunk(b));
+                    }
+                }
+                code.push(Op::Apply);
+            }
+            Exp::Cond(cond) => {
+                self.emit(&cond.r#if, scope, code);
+                let jump_if_false = code.len();
+                code.push(Op::JumpIfFalse(0));
+                self.emit(&cond.r#then, scope, code);
+                let jump = code.len();
+                code.push(Op::Jump(0));
+                code[jump_if_false] = Op::JumpIfFalse(code.len());
+                self.emit(&cond.r#else, scope, code);
+                code[jump] = Op::Jump(code.len());
+            }
+            Exp::True => code.push(Op::True),
+            Exp::False => code.push(Op::False),
+            Exp::Nat(n) => code.push(Op::Nat(*n)),
+            Exp::IsZero(t) => {
+                self.emit(t, scope, code);
+                code.push(Op::IsZero);
+            }
+            Exp::Incr(t) => {
+                self.emit(t, scope, code);
+                code.push(Op::Incr);
+            }
+            Exp::Decr(t) => {
+                self.emit(t, scope, code);
+                code.push(Op::Decr);
+            }
+            Exp::Add(add) => {
+                self.emit(&add.t1, scope, code);
+                if !matches!(add.t1, Exp::Nat(_)) {
+                    self.check(&add.t2, Op::CheckNat, code);
+                }
+                self.emit(&add.t2, scope, code);
+                code.push(Op::Add);
+            }
+        }
+    }
+}
+
+impl Exp {
+    /// compile to bytecode for `strategy`.
+    /// note: only call-by-value, call-by-name and call-by-need are supported,
+    /// since the others reduce under binders.
+    pub fn ref_compile(&self, strategy: Strategy) -> Result<Program> {
+        if !matches!(
+            strategy,
+            Strategy::CallByValue | Strategy::CallByName | Strategy::CallByNeed
+        ) {
+            return Err(StlcError::InvalidExpression(format!(
+                "{} is not supported by the bytecode compiler",
+                strategy
+            )));
+        }
+        let mut compiler = Compiler {
+            strategy,
+            blocks: vec![],
+            free: vec![],
+            operands: vec![],
+        };
+        compiler.block(BlockKind::Entry, self, self, vec![]);
+        Ok(Program {
+            strategy,
+            blocks: compiler.blocks,
+            free: compiler.free,
+            operands: compiler.operands,
+        })
+    }
+}
@@ -0,0 +1,366 @@
+use core::fmt;
+use std::{cell::RefCell, rc::Rc};
+
+use super::{BlockKind, Op, Program};
+use crate::{
+    expr::{add::Add, app::App},
+    limits::{Budget, EvalConfig},
+    stlc_err::StlcError,
+    Exp, Strategy,
+};
+
+type Result<T> = std::result::Result<T, StlcError>;
+
+/// A value on the stack (or bound in the environment).
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nat(u32),
+    Bool(bool),
+    /// the block of the lambda with the environment it was defined in.
+    Closure(usize, Env),
+    /// only bound in the environment under call-by-name or call-by-need.
+    Thunk(Rc<RefCell<Thunk>>),
+}
+
+/// A delayed argument.
+#[derive(Debug)]
+pub enum Thunk {
+    Delayed(usize, Env),
+    /// only under call-by-need, i.e., the thunk is evaluated at most once.
+    Forced(Value),
+}
+
+/// The environment, i.e., a persistent linked list with the innermost
+/// binding at the front, indexed by the position computed by the compiler.
+#[derive(Debug, Clone, Default)]
+pub struct Env(Option<Rc<Node>>);
+
+#[derive(Debug)]
+struct Node {
+    value: Value,
+    next: Env,
+}
+
+impl Env {
+    fn push(&self, value: Value) -> Self {
+        Self(Some(Rc::new(Node {
+            value,
+            next: self.clone(),
+        })))
+    }
+
+    fn get(&self, i: usize) -> &Value {
+        let mut env = self;
+        for _ in 0..i {
+            env = &env.0.as_ref().unwrap().next;
+        }
+        &env.0.as_ref().unwrap().value
+    }
+}
+
+/// An activation of a block.
+#[derive(Debug)]
+struct Frame {
+    block: usize,
+    pc: usize,
+    env: Env,
+    /// the thunk to update with the result, only under call-by-need.
+    update: Option<Rc<RefCell<Thunk>>>,
+}
+
+/// The virtual machine, i.e., an operand stack with a call stack.
+pub struct Vm<'a> {
+    program: &'a Program,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    /// the number of reductions so far, i.e., the steps
+    /// the small-step evaluator would have taken.
+    steps: u32,
+    budget: Budget,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Self::new_with(program, &EvalConfig::default())
+    }
+
+    /// the same as `new` within the steps and the timeout of `config`,
+    /// reporting the block being run, closed in its environment.
+    pub fn new_with(program: &'a Program, config: &EvalConfig) -> Self {
+        Self {
+            program,
+            stack: vec![],
+            frames: vec![Frame {
+                block: 0,
+                pc: 0,
+                env: Env::default(),
+                update: None,
+            }],
+            steps: 0,
+            budget: Budget::new(config),
+        }
+    }
+
+    /// substitute the bindings of `env` into `source`,
+    /// `names` being the scope `env` is indexed by.
+    fn close(&self, source: &Exp, names: &[String], env: &Env) -> Exp {
+        let substs: Vec<(String, Exp)> = source
+            .free_vars()
+            .into_iter()
+            .filter_map(|v| {
+                let i = names.iter().position(|name| *name == v)?;
+                Some((v, self.read_back(env.get(i))))
+            })
+            .collect();
+        source.clone().substitute_many(&substs)
+    }
+
+    /// convert back to an `Exp`, i.e., close the source of a closure (or thunk).
+    pub fn read_back(&self, value: &Value) -> Exp {
+        let close = |block: usize, env: &Env, skip: usize| {
+            let block = &self.program.blocks[block];
+            self.close(&block.source, &block.scope[skip..], env)
+        };
+        match value {
+            Value::Nat(n) => Exp::Nat(*n),
+            Value::Bool(true) => Exp::True,
+            Value::Bool(false) => Exp::False,
+            // the scope of the block includes the argument itself
+            Value::Closure(block, env) => close(*block, env, 1),
+            Value::Thunk(thunk) => match &*thunk.borrow() {
+                Thunk::Delayed(block, env) => close(*block, env, 0),
+                Thunk::Forced(value) => self.read_back(value),
+            },
+        }
+    }
+
+    /// the stuck term of `build` from the first operand (on the stack)
+    /// and the `i`-th operand (unevaluated) of the program.
+    fn stuck_operand(&self, i: usize, build: fn(Exp, Exp) -> Exp) -> StlcError {
+        let frame = self.frames.last().unwrap();
+        let scope = &self.program.blocks[frame.block].scope;
+        let t1 = self.read_back(self.stack.last().unwrap());
+        let t2 = self.close(&self.program.operands[i], scope, &frame.env);
+        self.stuck(build(t1, t2))
+    }
+
+    /// the block being run closed in its environment,
+    /// i.e., the body with the argument for a lambda.
+    fn current(&self) -> Exp {
+        let frame = self.frames.last().unwrap();
+        let block = &self.program.blocks[frame.block];
+        match (&block.kind, &block.source) {
+            (BlockKind::Lambda, Exp::Lambda(lambda)) => {
+                self.close(&lambda.exp, &block.scope, &frame.env)
+            }
+            (_, source) => self.close(source, &block.scope, &frame.env),
+        }
+    }
+
+    /// count one reduction.
+    fn tick(&mut self) -> Result<()> {
+        self.budget.check(self.steps, || self.current())?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap()
+    }
+
+    fn nat(&mut self) -> Result<u32> {
+        match self.pop() {
+            Value::Nat(n) => Ok(n),
+            v => Err(StlcError::InvalidExpression(format!(
+                "{}",
+                self.read_back(&v)
+            ))),
+        }
+    }
+
+    fn stuck(&self, e: Exp) -> StlcError {
+        match self.program.strategy {
+            Strategy::CallByValue => StlcError::StuckExpressionCbv(format!("{}", e)),
+            _ => StlcError::StuckExpressionCbn(format!("{}", e)),
+        }
+    }
+
+    /// push a binding, forcing it if it is a thunk.
+    fn force(&mut self, value: Value) {
+        let Value::Thunk(thunk) = value else {
+            return self.stack.push(value);
+        };
+        let (block, env) = match &*thunk.borrow() {
+            Thunk::Forced(value) => return self.stack.push(value.clone()),
+            Thunk::Delayed(block, env) => (*block, env.clone()),
+        };
+        let update = (self.program.strategy == Strategy::CallByNeed).then_some(thunk);
+        self.frames.push(Frame {
+            block,
+            pc: 0,
+            env,
+            update,
+        });
+    }
+
+    /// run till the entry returns, returns the value
+    /// with the number of reductions.
+    pub fn run(mut self) -> Result<(Exp, u32)> {
+        let program = self.program;
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let code = &program.blocks[frame.block].code;
+            let op = code[frame.pc];
+            frame.pc += 1;
+            match op {
+                Op::Access(i) => {
+                    let value = frame.env.get(i).clone();
+                    self.force(value);
+                }
+                Op::Share(i) => {
+                    let value = frame.env.get(i).clone();
+                    self.stack.push(value);
+                }
+                Op::Free(i) => return Err(StlcError::InvalidExpression(program.free[i].clone())),
+                Op::Closure(block) => {
+                    let env = frame.env.clone();
+                    self.stack.push(Value::Closure(block, env));
+                }
+                Op::Thunk(block) => {
+                    let env = frame.env.clone();
+                    self.stack
+                        .push(Value::Thunk(Rc::new(RefCell::new(Thunk::Delayed(
+                            block, env,
+                        )))));
+                }
+                Op::CheckClosure(i) => {
+                    if !matches!(self.stack.last(), Some(Value::Closure(..))) {
+                        return Err(self.stuck_operand(i, App::build));
+                    }
+                }
+                Op::CheckNat(i) => {
+                    if !matches!(self.stack.last(), Some(Value::Nat(_))) {
+                        return Err(self.stuck_operand(i, Add::build));
+                    }
+                }
+                Op::Apply => {
+                    // a tail call replaces the current frame, unless it updates a thunk
+                    let tail = code[frame.pc] == Op::Return && frame.update.is_none();
+                    let arg = self.pop();
+                    let (block, env) = match self.pop() {
+                        Value::Closure(block, env) => (block, env.push(arg)),
+                        f => {
+                            return Err(
+                                self.stuck(App::build(self.read_back(&f), self.read_back(&arg)))
+                            )
+                        }
+                    };
+                    self.tick()?;
+                    let frame = Frame {
+                        block,
+                        pc: 0,
+                        env,
+                        update: None,
+                    };
+                    if tail {
+                        *self.frames.last_mut().unwrap() = frame;
+                    } else {
+                        self.frames.push(frame);
+                    }
+                }
+                Op::Return => {
+                    let frame = self.frames.pop().unwrap();
+                    if let Some(thunk) = frame.update {
+                        *thunk.borrow_mut() = Thunk::Forced(self.stack.last().unwrap().clone());
+                    }
+                    if self.frames.is_empty() {
+                        let value = self.pop();
+                        return Ok((self.read_back(&value), self.steps));
+                    }
+                }
+                Op::Nat(n) => self.stack.push(Value::Nat(n)),
+                Op::True => self.stack.push(Value::Bool(true)),
+                Op::False => self.stack.push(Value::Bool(false)),
+                Op::JumpIfFalse(target) => {
+                    let b = match self.pop() {
+                        Value::Bool(b) => b,
+                        v => return Err(StlcError::non_boolean_if(self.read_back(&v))),
+                    };
+                    self.tick()?;
+                    if !b {
+                        self.frames.last_mut().unwrap().pc = target;
+                    }
+                }
+                Op::Jump(target) => frame.pc = target,
+                Op::IsZero => {
+                    let n = self.nat()?;
+                    self.tick()?;
+                    self.stack.push(Value::Bool(n == 0));
+                }
+                Op::Incr => {
+                    let n = self.nat()?;
+                    self.tick()?;
+                    self.stack.push(Value::Nat(n.saturating_add(1)));
+                }
+                Op::Decr => {
+                    let n = self.nat()?;
+                    self.tick()?;
+                    self.stack.push(Value::Nat(n.saturating_sub(1)));
+                }
+                Op::Add => {
+                    let (v2, v1) = (self.pop(), self.pop());
+                    let (Value::Nat(n1), Value::Nat(n2)) = (&v1, &v2) else {
+                        return Err(
+                            self.stuck(Add::build(self.read_back(&v1), self.read_back(&v2)))
+                        );
+                    };
+                    let n = n1.saturating_add(*n2);
+                    self.tick()?;
+                    self.stack.push(Value::Nat(n));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nat(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Closure(block, _) => write!(f, "<closure {}>", block),
+            Value::Thunk(thunk) => match &*thunk.borrow() {
+                Thunk::Delayed(block, _) => write!(f, "<thunk {}>", block),
+                Thunk::Forced(value) => write!(f, "{}", value),
+            },
+        }
+    }
+}
+
+impl Program {
+    /// run on a fresh machine, returns the (closed) value
+    /// with the number of reductions.
+    pub fn run(&self) -> Result<(Exp, u32)> {
+        self.run_with(&EvalConfig::default())
+            .map_err(StlcError::into_eval_limit)
+    }
+
+    /// the same as `run`, see `Vm::new_with`.
+    pub fn run_with(&self, config: &EvalConfig) -> Result<(Exp, u32)> {
+        Vm::new_with(self, config).run()
+    }
+}
+
+impl Exp {
+    /// compile and run on the virtual machine, i.e., the same
+    /// as `ref_eval_to_normal_form(strategy)`, but much faster.
+    pub fn ref_eval_vm(&self, strategy: Strategy) -> Result<(Exp, u32)> {
+        self.ref_compile(strategy)?.run()
+    }
+
+    /// same as `ref_eval_to_normal_form_with(strategy)`, but by the
+    /// virtual machine, see `Vm::new_with`.
+    pub fn ref_eval_vm_with(&self, strategy: Strategy, config: &EvalConfig) -> Result<(Exp, u32)> {
+        self.ref_compile(strategy)?.run_with(config)
+    }
+}
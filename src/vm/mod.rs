@@ -0,0 +1,169 @@
+//! A bytecode compiler and a stack-based virtual machine for closed `Exp`.
+//! Every lambda (and, under call-by-name or call-by-need, every argument
+//! that is not already a value) becomes a *block* of instructions, and
+//! a variable is compiled to its position in the environment, e.g.,
+//!
+//! ```text
+//! (λx. incr x) 1   ==>   block 0 (entry):       block 1 (λx. incr (x)):
+//!                          0  closure  1          0  access   0   ; x
+//!                          1  nat      1          1  incr
+//!                          2  apply               2  return
+//!                          3  return
+//! ```
+//!
+//! The machine never touches the term itself (until `read_back`), which
+//! makes the larger recursive programs, e.g., the ones built with
+//! `YCombinator`, orders of magnitude faster than substitution.
+
+use core::fmt;
+
+use crate::{Exp, Strategy};
+
+/// the compiler, i.e., from `Exp` to `Program`.
+pub mod compile;
+
+/// the virtual machine running a `Program`.
+pub mod machine;
+
+/// A single instruction.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Op {
+    /// push the `i`-th binding of the environment (innermost first),
+    /// forcing it if it is a thunk.
+    Access(usize),
+    /// push the `i`-th binding as it is, i.e., pass a thunk along unforced.
+    Share(usize),
+    /// fail on the `i`-th free variable of the program.
+    Free(usize),
+    /// push a closure of the block with the current environment.
+    Closure(usize),
+    /// push a thunk of the block with the current environment.
+    Thunk(usize),
+    /// fail unless the top of the stack is a closure, i.e., the function
+    /// is checked before its `i`-th operand (see `Program`) is evaluated.
+    CheckClosure(usize),
+    /// fail unless the top of the stack is a number, the same for `Add`.
+    CheckNat(usize),
+    /// pop the argument and the function, and call it.
+    Apply,
+    /// leave the current block, the result is on the top of the stack.
+    Return,
+    Nat(u32),
+    True,
+    False,
+    /// pop the condition, and jump to the target (of the same block) if `false`.
+    JumpIfFalse(usize),
+    Jump(usize),
+    IsZero,
+    Incr,
+    Decr,
+    Add,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Access(i) => write!(f, "{:<8} {}", "access", i),
+            Op::Share(i) => write!(f, "{:<8} {}", "share", i),
+            Op::Free(i) => write!(f, "{:<8} {}", "free", i),
+            Op::Closure(b) => write!(f, "{:<8} {}", "closure", b),
+            Op::Thunk(b) => write!(f, "{:<8} {}", "thunk", b),
+            Op::CheckClosure(i) => write!(f, "{:<8} {}", "chkfun", i),
+            Op::CheckNat(i) => write!(f, "{:<8} {}", "chknat", i),
+            Op::Apply => write!(f, "apply"),
+            Op::Return => write!(f, "return"),
+            Op::Nat(n) => write!(f, "{:<8} {}", "nat", n),
+            Op::True => write!(f, "true"),
+            Op::False => write!(f, "false"),
+            Op::JumpIfFalse(t) => write!(f, "{:<8} {}", "jmpf", t),
+            Op::Jump(t) => write!(f, "{:<8} {}", "jmp", t),
+            Op::IsZero => write!(f, "is_zero"),
+            Op::Incr => write!(f, "incr"),
+            Op::Decr => write!(f, "decr"),
+            Op::Add => write!(f, "add"),
+        }
+    }
+}
+
+/// What a block is compiled from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BlockKind {
+    /// the whole program.
+    Entry,
+    /// the body of a lambda abstraction.
+    Lambda,
+    /// an argument delayed as a thunk.
+    Thunk,
+}
+
+/// A sequence of instructions, always ending with `Return`.
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub code: Vec<Op>,
+    /// the term it is compiled from, i.e., the whole lambda for `Lambda`.
+    pub source: Exp,
+    /// the names of the environment inside the block, innermost first.
+    pub scope: Vec<String>,
+}
+
+/// The compiled program, the entry is always the first block.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub strategy: Strategy,
+    pub blocks: Vec<Block>,
+    /// the free variables, i.e., the operands of `Free`.
+    pub free: Vec<String>,
+    /// the second operands, reported by `CheckClosure` and `CheckNat`.
+    pub operands: Vec<Exp>,
+}
+
+impl Program {
+    /// the number of instructions in all blocks.
+    pub fn len(&self) -> usize {
+        self.blocks.iter().map(|b| b.code.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The disassembler, one block after another, e.g.,
+///
+/// ```text
+/// block 1 (λx. incr (x)):
+///   0  access   0       ; x
+///   1  incr
+/// ```
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "; compiled by {}", self.strategy)?;
+        for (b, block) in self.blocks.iter().enumerate() {
+            match block.kind {
+                BlockKind::Entry => writeln!(f, "block {} (entry):", b)?,
+                BlockKind::Lambda => writeln!(f, "block {} ({}):", b, block.source)?,
+                BlockKind::Thunk => writeln!(f, "block {} (thunk {}):", b, block.source)?,
+            }
+            let width = block.code.len().to_string().len();
+            for (pc, op) in block.code.iter().enumerate() {
+                let op_str = format!("{}", op);
+                match op {
+                    Op::Access(i) | Op::Share(i) => {
+                        writeln!(f, "  {:>width$}  {:<16} ; {}", pc, op_str, block.scope[*i])?
+                    }
+                    Op::Free(i) => {
+                        writeln!(f, "  {:>width$}  {:<16} ; {}", pc, op_str, self.free[*i])?
+                    }
+                    Op::CheckClosure(i) | Op::CheckNat(i) => writeln!(
+                        f,
+                        "  {:>width$}  {:<16} ; {}",
+                        pc, op_str, self.operands[*i]
+                    )?,
+                    _ => writeln!(f, "  {:>width$}  {}", pc, op_str)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use common::p;
+use stlc::{alpha::Alpha, parser::parse_type, type_::Type, Strategy};
+
+fn t(src: &str) -> Type {
+    parse_type(src).unwrap()
+}
+
+#[test]
+fn test_exp_alpha_eq() {
+    assert!(p("λx. x").alpha_eq(&p("λy. y")));
+    assert!(p("λx. λy. x y").alpha_eq(&p("λy. λx. y x")));
+    assert!(!p("λx. λy. x").alpha_eq(&p("λx. λy. y")));
+    // free variables must match exactly
+    assert!(!p("λx. y").alpha_eq(&p("λx. z")));
+    assert!(p("λx: int. x + 1").alpha_eq(&p("λn: int. n + 1")));
+    assert!(!p("λx: int. x").alpha_eq(&p("λx: bool. x")));
+    assert_ne!(p("λx. x"), p("λy. y"));
+}
+
+#[test]
+fn test_type_alpha_eq() {
+    assert!(t("X3 -> X3").alpha_eq(&t("a -> a")));
+    assert!(t("(a -> b) -> a").alpha_eq(&t("(X1 -> X0) -> X1")));
+    assert!(!t("a -> b").alpha_eq(&t("a -> a")));
+    assert!(!t("a -> int").alpha_eq(&t("a -> bool")));
+    assert_eq!(t("X3 -> (X7 -> X3)").canonical(), t("t0 -> t1 -> t0"));
+}
+
+#[test]
+fn test_alpha_hash_ord() {
+    let terms = ["λx. x", "λy. y", "λa. λb. a", "λx. λy. x", "λx. λy. y"];
+    let set: HashSet<_> = terms.iter().map(|s| Alpha::new(p(s))).collect();
+    assert_eq!(set.len(), 3);
+    let set: BTreeSet<_> = terms.iter().map(|s| Alpha::new(p(s))).collect();
+    assert_eq!(set.len(), 3);
+
+    // cache the evaluation result by alpha-equivalence
+    let mut cache = HashMap::new();
+    let e = p("(λx. incr x) 1");
+    let result = e.clone().ref_eval_to_normal_form(Strategy::CallByValue);
+    cache.insert(Alpha::new(e), result);
+    assert!(cache.contains_key(&Alpha::new(p("(λy. incr y) 1"))));
+
+    let types: HashSet<_> = ["X3 -> X3", "a -> a", "a -> b"]
+        .iter()
+        .map(|s| Alpha::new(t(s)))
+        .collect();
+    assert_eq!(types.len(), 2);
+}
+
+#[test]
+fn test_alpha_assertion() {
+    let (result, _) = p("(λx. λy. x) y")
+        .ref_eval_to_normal_form(Strategy::CallByName)
+        .unwrap();
+    // no need to hard-code the fresh name
+    assert_eq!(Alpha::new(result), Alpha::new(p("λz. y")));
+}
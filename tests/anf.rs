@@ -0,0 +1,85 @@
+mod common;
+
+use common::{assert_same_values, p, TERMS};
+use stlc::{Exp, Strategy};
+
+const EXTRA_TERMS: [&str; 3] = [
+    "(λx. λy. x) (λz. z) false 7",
+    "incr ((λx. x) 1) + 2",
+    "(λx. incr ((λx. x + 1) (incr 2)) + x) 5",
+];
+
+#[test]
+fn test_anf_agrees() {
+    assert_same_values(
+        &[Strategy::CallByValue, Strategy::CallByName],
+        &EXTRA_TERMS,
+        |e, strategy| Ok(e.ref_to_anf().ref_eval_to_normal_form(strategy)?.0),
+    );
+    for src in TERMS.iter().chain(&EXTRA_TERMS) {
+        let anf = p(src).ref_to_anf();
+        assert!(anf.ref_is_anf(), "{}", anf);
+        // idempotent
+        assert_eq!(anf.ref_to_anf(), anf);
+    }
+}
+
+#[test]
+fn test_is_anf() {
+    for src in [
+        "1",
+        "λx. x",
+        "f x",
+        "(λt. incr t) (incr 1)",
+        "(λt1. (λt2. t2 + 2) (incr t1)) ((λx. x) 1)",
+        "if b then incr 1 else (λt. t) (decr 1)",
+        "λf. (λt. f t) (f 1)",
+    ] {
+        assert!(p(src).ref_is_anf(), "{}", src);
+    }
+    for src in [
+        "incr (incr 1)",
+        "f (g x)",
+        "(λx. x) (incr 1) 2",
+        "if is_zero x then 1 else 2",
+        "λf. f (f 1)",
+        "(λt. t) ((λs. s) (incr 1))",
+    ] {
+        assert!(!p(src).ref_is_anf(), "{}", src);
+    }
+}
+
+#[test]
+fn test_anf_names_in_order() {
+    let anf = p("incr ((λx. x) 1) + 2").ref_to_anf();
+    assert_eq!(anf, p("(λt1. (λt2. t2 + 2) (incr t1)) ((λx. x) 1)"));
+    assert_eq!(
+        anf.ref_display_anf(),
+        "let t1 = (λx. x) (1) in\nlet t2 = incr (t1) in\n(t2) + (2)"
+    );
+    // the names never clash with the ones of the term
+    let anf = p("λt1. incr (incr t1)").ref_to_anf();
+    assert_eq!(anf, p("λt1. (λt2. incr t2) (incr t1)"));
+}
+
+#[test]
+fn test_anf_trace() {
+    // every step of the call-by-value evaluation stays in ANF,
+    // reducing the first let (or its right hand side) each time
+    let mut e = p("(λf. f (incr (f 1))) (λx. x + 1)").ref_to_anf();
+    let mut steps = vec![];
+    while !e.ref_is_value() {
+        assert!(e.ref_is_anf(), "{}", e);
+        steps.push(e.ref_display_anf());
+        e = e.ref_eval_multi_step(1, Strategy::CallByValue).unwrap();
+    }
+    assert_eq!(e, Exp::Nat(4));
+    assert_eq!(
+        steps[1],
+        "let t1 = (λx. (x) + (1)) (1) in\nlet t2 = incr (t1) in\n(λx. (x) + (1)) (t2)"
+    );
+    assert_eq!(
+        steps[2],
+        "let t1 = (1) + (1) in\nlet t2 = incr (t1) in\n(λx. (x) + (1)) (t2)"
+    );
+}
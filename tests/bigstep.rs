@@ -0,0 +1,113 @@
+mod common;
+
+use common::{assert_agrees, p, TERMS};
+use stlc::{
+    limits::EvalConfig, refsols::refsol_day4::YCombinator, stlc_err::StlcError, Exp, Strategy,
+};
+
+#[test]
+fn test_big_step_agrees() {
+    assert_agrees(
+        &[
+            Strategy::CallByValue,
+            Strategy::CallByName,
+            Strategy::CallByNeed,
+        ],
+        &[
+            "(λx. λy. x) (λz. y)",
+            "(λx. λy. λz. x y) (λa. a) w",
+            "λx. (λy. y) x",
+        ],
+        Exp::ref_eval_to_normal_form_big_step,
+    );
+    // error(s) included
+    for src in TERMS {
+        let e = p(&format!("({}) true", src));
+        for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+            assert_eq!(
+                e.ref_eval_to_normal_form_big_step(strategy).is_ok(),
+                e.clone().ref_eval_to_normal_form(strategy).is_ok(),
+                "{} by {}",
+                src,
+                strategy
+            );
+        }
+    }
+}
+
+#[test]
+fn test_big_step_errors() {
+    assert!(p("(λx. x 1) true")
+        .ref_eval_to_normal_form_big_step(Strategy::CallByValue)
+        .is_err());
+    assert!(p("1 + (λx. x)")
+        .ref_eval_to_normal_form_big_step(Strategy::CallByName)
+        .is_err());
+    assert!(p("(λx. x) y")
+        .ref_eval_to_normal_form_big_step(Strategy::CallByName)
+        .is_err());
+    assert!(p("λx. x")
+        .ref_eval_to_normal_form_big_step(Strategy::NormalOrder)
+        .is_err());
+}
+
+#[test]
+fn test_big_step_diverges() {
+    // runs into the limit rather than overflowing the stack
+    let e = p("(λx. λy. incr y) ((λx. x x) (λx. x x)) 1");
+    assert!(matches!(
+        e.ref_eval_to_normal_form_big_step(Strategy::CallByValue),
+        Err(StlcError::ExceedEvalLimit(_))
+    ));
+    assert_eq!(
+        e.ref_eval_to_normal_form_big_step(Strategy::CallByName),
+        Ok((Exp::Nat(2), 3))
+    );
+    // while the operands nest into the depth limit, one level per step
+    let e = p("(λx. incr (x x)) (λx. incr (x x))");
+    for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+        assert!(matches!(
+            e.ref_eval_to_normal_form_big_step(strategy),
+            Err(StlcError::ExceedDepthLimit { .. })
+        ));
+    }
+    let config = EvalConfig {
+        max_depth: Some(10),
+        ..EvalConfig::default()
+    };
+    assert_eq!(
+        e.ref_eval_big_step_with(Strategy::CallByValue, &config)
+            .map(|_| ()),
+        Err(StlcError::ExceedDepthLimit {
+            term: p("λx. incr (x x)"),
+            steps: 10
+        })
+    );
+}
+
+#[test]
+fn test_read_back() {
+    // the closure captures `x`, which gets substituted back
+    let (v, _) = p("(λx. λy. x y) (λz. z)")
+        .ref_eval_big_step(Strategy::CallByValue)
+        .unwrap();
+    assert_eq!(v.read_back(), p("λy. (λz. z) y"));
+    // simultaneously, and without capture
+    let (v, _) = p("(λy. λz. λw. y z) z 5")
+        .ref_eval_big_step(Strategy::CallByName)
+        .unwrap();
+    assert_eq!(v.read_back(), p("λw. z 5"));
+}
+
+#[test]
+fn test_y_combinator_times() {
+    let times = YCombinator::ref_new(YCombinator::ref_gen_built_in_times());
+    let inputs = vec![Exp::Nat(3), Exp::Nat(3), Exp::Nat(4)];
+    // the same value, in the same number of steps
+    let big = times
+        .clone()
+        .ref_eval_big_step(inputs.clone(), Strategy::CallByName);
+    let small = times.ref_eval(inputs, Strategy::CallByName);
+    assert_eq!(big, small);
+    assert_eq!(big.unwrap().0, Exp::Nat(12));
+}
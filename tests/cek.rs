@@ -0,0 +1,108 @@
+mod common;
+
+use common::{assert_agrees, p};
+use stlc::{
+    cek::Cek,
+    expr::{app::App, incr::Incr},
+    refsols::refsol_day4::YCombinator,
+    stlc_err::StlcError,
+    Exp, Strategy,
+};
+
+#[test]
+fn test_cek_agrees() {
+    assert_agrees(
+        &[
+            Strategy::CallByValue,
+            Strategy::CallByName,
+            Strategy::CallByNeed,
+        ],
+        &["(λx. λy. x) (λz. y)", "λx. (λy. y) x"],
+        Exp::ref_eval_cek,
+    );
+    // the Y combinator only works under call-by-name
+    let y = p("λF. (λx. F (x x)) (λx. F (x x))");
+    let e = [Exp::Nat(3), Exp::Nat(3), Exp::Nat(4)].into_iter().fold(
+        App::build(y, YCombinator::ref_gen_built_in_times()),
+        App::build,
+    );
+    let times = YCombinator::ref_new(YCombinator::ref_gen_built_in_times());
+    assert_eq!(
+        e.ref_eval_cek(Strategy::CallByName),
+        times.ref_eval(
+            vec![Exp::Nat(3), Exp::Nat(3), Exp::Nat(4)],
+            Strategy::CallByName
+        )
+    );
+}
+
+#[test]
+fn test_cek_trace() {
+    let trace = p("(λx. incr x) 1")
+        .ref_trace_cek(Strategy::CallByValue, 100)
+        .unwrap();
+    let trace: Vec<String> = trace.iter().map(|s| s.to_string()).collect();
+    assert_eq!(
+        trace,
+        [
+            "▸ (λx. incr (x)) (1) | ∅ | halt",
+            "▸ λx. incr (x) | ∅ | (□) (1) · halt",
+            "◂ λx. incr (x) | (□) (1) · halt",
+            "▸ 1 | ∅ | (λx. incr (x)) (□) · halt",
+            "◂ 1 | (λx. incr (x)) (□) · halt",
+            "▸ incr (x) | {x ↦ 1} | halt",
+            "▸ x | {x ↦ 1} | incr (□) · halt",
+            "◂ 1 | incr (□) · halt",
+            "◂ 2 | halt",
+        ]
+    );
+
+    // under call-by-name the argument is bound as a thunk, and
+    // evaluated (twice) only when needed
+    let trace = p("(λx. x + x) (incr 1)")
+        .ref_trace_cek(Strategy::CallByName, 100)
+        .unwrap();
+    assert_eq!(trace[3].scope, "{x ↦ ⟨incr (1)⟩}");
+    assert_eq!(trace.iter().filter(|s| s.focus == "incr (1)").count(), 2);
+    // while call-by-need evaluates it once, the earlier snapshot is intact
+    let trace = p("(λx. x + x) (incr 1)")
+        .ref_trace_cek(Strategy::CallByNeed, 100)
+        .unwrap();
+    assert_eq!(trace[3].scope, "{x ↦ ⟨incr (1)⟩}");
+    assert_eq!(trace.iter().filter(|s| s.focus == "incr (1)").count(), 1);
+    assert_eq!(trace.last().unwrap().to_string(), "◂ 4 | halt");
+
+    // the evaluation context
+    let trace = p("incr ((λx. x) 1 + 2)")
+        .ref_trace_cek(Strategy::CallByValue, 4)
+        .unwrap();
+    assert_eq!(trace.len(), 4);
+    assert_eq!(trace[3].context, "incr (((□) (1)) + (2))");
+}
+
+#[test]
+fn test_cek_step_by_step() {
+    let mut machine = Cek::new(&p("(λx. x) true"), Strategy::CallByName).unwrap();
+    let mut transitions = 0;
+    while machine.step().unwrap() {
+        transitions += 1;
+    }
+    assert!(machine.state().is_final());
+    assert_eq!(transitions, 5);
+    assert_eq!(machine.steps(), 1);
+
+    assert_eq!(
+        p("(λx. x 1) true").ref_eval_cek(Strategy::CallByValue),
+        Err(StlcError::StuckExpressionCbv("(true) (1)".into()))
+    );
+    assert!(Cek::new(&p("1"), Strategy::NormalOrder).is_err());
+}
+
+#[test]
+fn test_cek_deep_term() {
+    // no stack overflow, no matter how deep the evaluation context is
+    let e = (0..5000).fold(Exp::Nat(0), |e, _| Incr::build(e));
+    let (value, steps) = Cek::new(&e, Strategy::CallByValue).unwrap().run().unwrap();
+    assert_eq!(value.read_back(), Exp::Nat(5000));
+    assert_eq!(steps, 5000);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{assert_agrees, p, TERMS};
+use stlc::{closure::Code, stlc_err::StlcError, Strategy};
+
+const EXTRA_TERMS: [&str; 5] = [
+    "(λx. λy. x) (λz. z)",
+    "(λx. λy. λz. x + y + z) 1 2",
+    "(λx. λx. x + 1) true 41",
+    "(λa. λb. λc. λd. a + d) 1 2 3",
+    "λx. (λy. y) x",
+];
+
+#[test]
+fn test_closure_conversion_agrees() {
+    assert_agrees(&[Strategy::CallByValue], &EXTRA_TERMS, |e, _| {
+        e.ref_closure_convert().run()
+    });
+    for src in TERMS.iter().chain(&EXTRA_TERMS) {
+        let program = p(src).ref_closure_convert();
+        assert!(
+            program.defs.iter().all(|def| def.is_closed()),
+            "{}",
+            program
+        );
+    }
+}
+
+#[test]
+fn test_lifted_definitions() {
+    let program = p("(λx. λy. x + y) 1 2").ref_closure_convert();
+    assert_eq!(program.defs.len(), 2);
+    assert!(program.defs[0].captured.is_empty());
+    assert_eq!(program.defs[1].captured, vec!["x".to_string()]);
+    assert_eq!(
+        program.to_string(),
+        "f0(env, x) = ⟨f1, [x]⟩\nf1(env, y) = (env[0]) + (y)\nmain = ((⟨f0, []⟩) (1)) (2)"
+    );
+    // only what is free is captured, through the environment of the enclosing one
+    let program = p("λa. λb. λc. λd. a + d").ref_closure_convert();
+    let captured: Vec<_> = program
+        .defs
+        .iter()
+        .map(|def| def.captured.clone())
+        .collect();
+    assert_eq!(
+        captured,
+        vec![
+            vec![],
+            vec!["a".to_string()],
+            vec!["a".to_string()],
+            vec!["a".to_string()]
+        ]
+    );
+    assert_eq!(
+        program.defs[2].body,
+        Code::MakeClosure {
+            fun: 3,
+            env: vec![Code::Env(0)]
+        }
+    );
+    // a shadowed variable is not captured
+    let program = p("λx. λx. x").ref_closure_convert();
+    assert!(program.defs[1].captured.is_empty());
+}
+
+#[test]
+fn test_closure_conversion_errors() {
+    let run = |src: &str| p(src).ref_closure_convert().run();
+    assert!(matches!(
+        run("(λx. x 1) true"),
+        Err(StlcError::StuckExpressionCbv(_))
+    ));
+    assert!(matches!(
+        run("1 + (λx. x)"),
+        Err(StlcError::StuckExpressionCbv(_))
+    ));
+    // stuck before the (diverging) second operand is evaluated
+    for src in [
+        "true ((λx. x x) (λx. x x))",
+        "(λx. x) + ((λx. x x) (λx. x x))",
+        "(λy. y ((λx. x x) (λx. x x))) 1",
+    ] {
+        assert_eq!(
+            run(src),
+            p(src).ref_eval_to_normal_form(Strategy::CallByValue),
+            "{}",
+            src
+        );
+        assert!(matches!(run(src), Err(StlcError::StuckExpressionCbv(_))));
+    }
+    assert_eq!(
+        run("(λx. y) 1"),
+        Err(StlcError::InvalidExpression("y".to_string()))
+    );
+    // runs into the limit rather than overflowing the stack, in tail position
+    assert!(matches!(
+        run("(λx. x x) (λx. x x)"),
+        Err(StlcError::ExceedEvalLimit(_))
+    ));
+    // or nesting one level deeper on every call
+    assert!(matches!(
+        run("(λx. incr (x x)) (λx. incr (x x))"),
+        Err(StlcError::ExceedDepthLimit { .. })
+    ));
+}
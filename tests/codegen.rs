@@ -0,0 +1,92 @@
+mod common;
+
+use std::{fs, path::PathBuf, process::Command};
+
+use common::{p, TERMS};
+use stlc::{
+    type_::{tarrow::TArrow, Type},
+    Strategy,
+};
+
+/// compile `main_src` with rustc, and run it.
+fn compile_and_run(name: &str, main_src: &str) -> String {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let src = dir.join(format!("{}.rs", name));
+    let bin = dir.join(name);
+    fs::write(&src, main_src).unwrap();
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc)
+        .args(["--edition", "2021", "-o"])
+        .arg(&bin)
+        .arg(&src)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        main_src,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = Command::new(&bin).output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_compiled_agrees() {
+    let mut main_src = String::new();
+    let mut calls = String::new();
+    let mut expected = String::new();
+    let extra = [
+        "(λx. λx. x + 1) true 41",
+        "(λf. λg. λx. f (g x)) (λb. if b then 1 else 0) (λn. is_zero n) 0",
+        "(λx. λy. x) (λz. z) false 7",
+        "is_zero (decr 1)",
+    ];
+    for (i, src) in TERMS.iter().chain(&extra).enumerate() {
+        let e = p(src);
+        main_src.push_str(&e.ref_to_rust(&format!("term{}", i)).unwrap());
+        calls.push_str(&format!("    println!(\"{{}}\", term{}());\n", i));
+        let (v, _) = e.ref_eval_to_normal_form(Strategy::CallByValue).unwrap();
+        expected.push_str(&format!("{}\n", v));
+    }
+    main_src.push_str(&format!("\nfn main() {{\n{}}}\n", calls));
+    assert_eq!(compile_and_run("codegen_agrees", &main_src), expected);
+}
+
+#[test]
+fn test_compiled_function() {
+    // a function is returned as is, i.e., `Rc<dyn Fn(u32) -> u32>`
+    let e = p("(λf. λx. f (f x)) (λn. n + n)");
+    let src = e.ref_to_rust("quadruple").unwrap();
+    assert!(src.contains("pub fn quadruple() -> std::rc::Rc<dyn Fn(u32) -> u32>"));
+    let main_src = format!(
+        "{}\nfn main() {{ println!(\"{{}}\", quadruple()(5)); }}\n",
+        src
+    );
+    assert_eq!(compile_and_run("codegen_function", &main_src), "20\n");
+}
+
+#[test]
+fn test_elaborate() {
+    let (e, ty) = p("λf. λx. f (incr x)").ref_elaborate().unwrap();
+    assert_eq!(ty.to_string(), "(int -> int) -> int -> int");
+    assert!(e.ref_typed());
+    // the unconstrained type variable defaults to `int`
+    let (_, ty) = p("λx. x").ref_elaborate().unwrap();
+    assert_eq!(ty.to_string(), "int -> int");
+    // shadowing
+    let (_, ty) = p("λx. (λx. is_zero x) x").ref_elaborate().unwrap();
+    assert_eq!(ty.to_string(), "int -> bool");
+    let (_, ty) = p("λx: bool. if x then 1 else 2").ref_elaborate().unwrap();
+    assert_eq!(ty, TArrow::build(Type::TBool, Type::TInt));
+}
+
+#[test]
+fn test_elaborate_errors() {
+    assert!(p("(λx. x) true 1").ref_elaborate().is_err());
+    assert!(p("λx. x x").ref_elaborate().is_err());
+    assert!(p("λx. y").ref_elaborate().is_err());
+    assert!(p("(λx: int. x) (λy. y)").ref_elaborate().is_err());
+    assert!(p("λx: int. x true").ref_to_rust("f").is_err());
+}
@@ -0,0 +1,63 @@
+#![allow(dead_code)]
+
+use stlc::{parser::parse_exp, stlc_err::StlcError, Exp, Strategy};
+
+pub fn p(src: &str) -> Exp {
+    parse_exp(src).unwrap()
+}
+
+/// closed, well-typed terms, which terminate whatever the strategy.
+pub const TERMS: [&str; 6] = [
+    "(λx. incr x) 1",
+    "(λx. λy. incr y) (incr 2) 1",
+    "(λx. x + x) (incr 1)",
+    "(λf. λx. f (f x)) (λx. if is_zero x then 10 else decr x) 1",
+    "(λx: int. (λy: int. x + y) 2) 3",
+    "if is_zero (decr 1) then 2 + 3 else 4",
+];
+
+/// `eval` gives the same result as the small-step evaluation, i.e., the same
+/// normal form in the same number of steps (or the same error), on `TERMS`
+/// and `extra`, by each of `strategies`.
+pub fn assert_agrees(
+    strategies: &[Strategy],
+    extra: &[&str],
+    eval: impl Fn(&Exp, Strategy) -> Result<(Exp, u32), StlcError>,
+) {
+    for src in TERMS.iter().chain(extra) {
+        let e = p(src);
+        for &strategy in strategies {
+            assert_eq!(
+                eval(&e, strategy),
+                e.clone().ref_eval_to_normal_form(strategy),
+                "{} by {}",
+                src,
+                strategy
+            );
+        }
+    }
+}
+
+/// `eval` gives the same normal form as the small-step evaluation, though
+/// (possibly) in a different number of steps, on `TERMS` and `extra`, by
+/// each of `strategies`.
+pub fn assert_same_values(
+    strategies: &[Strategy],
+    extra: &[&str],
+    eval: impl Fn(&Exp, Strategy) -> Result<Exp, StlcError>,
+) {
+    for src in TERMS.iter().chain(extra) {
+        let e = p(src);
+        for &strategy in strategies {
+            assert_eq!(
+                eval(&e, strategy),
+                e.clone()
+                    .ref_eval_to_normal_form(strategy)
+                    .map(|(value, _)| value),
+                "{} by {}",
+                src,
+                strategy
+            );
+        }
+    }
+}
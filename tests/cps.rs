@@ -0,0 +1,102 @@
+mod common;
+
+use common::{assert_same_values, p, TERMS};
+use stlc::{
+    expr::app::App,
+    stlc_err::StlcError,
+    type_::{tarrow::TArrow, Env, Type},
+    Exp, Strategy,
+};
+
+const EXTRA_TERMS: [&str; 2] = [
+    "(λf. λg. λx. f (g x)) (λb. if b then 1 else 0) (λn. is_zero n) 0",
+    "(λx. λy. x) (λz. z) false 7",
+];
+
+const STRATEGIES: [Strategy; 2] = [Strategy::CallByValue, Strategy::CallByName];
+
+/// `[e] (λx. x)`, i.e., run with the identity continuation.
+fn run(cps: Exp, strategy: Strategy) -> Result<Exp, StlcError> {
+    let e = Exp::App(Box::new(App::new(cps, p("λx. x"))));
+    Ok(e.ref_eval_to_normal_form(strategy)?.0)
+}
+
+#[test]
+fn test_cps_agrees() {
+    // either translation, evaluated by either strategy
+    for translation in STRATEGIES {
+        assert_same_values(&STRATEGIES, &EXTRA_TERMS, |e, strategy| {
+            run(e.ref_cps(translation), strategy)
+        });
+    }
+}
+
+#[test]
+fn test_evaluation_order_independence() {
+    // the argument diverges, which only matters under call-by-value
+    // note: on the virtual machine, since it takes a while to run into the limit
+    let e = p("(λx. λy. incr y) ((λx. x x) (λx. x x)) 1");
+    assert!(e.ref_eval_vm(Strategy::CallByValue).is_err());
+    // the call-by-name translation never evaluates it, whatever the strategy
+    let cps = e.ref_cps(Strategy::CallByName);
+    for strategy in STRATEGIES {
+        assert_eq!(run(cps.clone(), strategy), Ok(Exp::Nat(2)));
+    }
+    // the call-by-value translation always does, whatever the strategy
+    let cps = e.ref_cps(Strategy::CallByValue);
+    let e = Exp::App(Box::new(App::new(cps, p("λx. x"))));
+    for strategy in STRATEGIES {
+        assert!(matches!(
+            e.ref_eval_vm(strategy),
+            Err(StlcError::ExceedEvalLimit(_))
+        ));
+    }
+}
+
+#[test]
+fn test_cps_hygiene() {
+    // the continuations never capture the variables of the term
+    let e = p("(λk. λm. k + m) 1 2");
+    for translation in STRATEGIES {
+        let cps = e.ref_cps(translation);
+        assert_eq!(run(cps, Strategy::CallByValue), Ok(Exp::Nat(3)));
+    }
+    assert_eq!(p("x").ref_cps(Strategy::CallByName), p("x"));
+    assert_eq!(p("1").ref_cps(Strategy::CallByValue), p("λk1. k1 1"));
+}
+
+#[test]
+fn test_cps_types() {
+    let r = Type::from("R");
+    let arrow = TArrow::build(Type::TInt, Type::TBool);
+    let c = |ty: Type| TArrow::build(TArrow::build(ty, r.clone()), r.clone());
+    assert_eq!(
+        arrow.ref_cps(Strategy::CallByValue, &r),
+        TArrow::build(Type::TInt, c(Type::TBool))
+    );
+    assert_eq!(
+        arrow.ref_cps(Strategy::CallByName, &r),
+        TArrow::build(c(Type::TInt), c(Type::TBool))
+    );
+    assert_eq!(
+        Type::TInt.ref_cps_computation(Strategy::CallByValue, &r),
+        c(Type::TInt)
+    );
+
+    // the typed translation is well-typed, at the translated type
+    for src in TERMS
+        .iter()
+        .chain(&EXTRA_TERMS)
+        .chain(&["λf. λx. f (f x)", "λx. x"])
+    {
+        for translation in STRATEGIES {
+            let (cps, ty) = p(src).ref_cps_typed(translation, &r).unwrap();
+            assert_eq!(cps.ty_synth(&Env::new()), Ok(ty.clone()), "{}", src);
+            let (_, source_ty) = p(src).ref_elaborate().unwrap();
+            assert_eq!(ty, source_ty.ref_cps_computation(translation, &r));
+        }
+    }
+    assert!(p("(λx. x) true 1")
+        .ref_cps_typed(Strategy::CallByValue, &r)
+        .is_err());
+}
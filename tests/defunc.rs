@@ -0,0 +1,145 @@
+mod common;
+
+use common::{assert_agrees, p};
+use stlc::{defunc::Term, refsols::refsol_day4::YCombinator, stlc_err::StlcError, Exp, Strategy};
+
+/// the number of lambdas in `e`.
+fn lambdas(e: &Exp) -> usize {
+    let own = usize::from(matches!(e, Exp::Lambda(_)));
+    own + e.children().into_iter().map(lambdas).sum::<usize>()
+}
+
+#[test]
+fn test_defunctionalization_agrees() {
+    assert_agrees(
+        &[Strategy::CallByValue, Strategy::CallByName],
+        &[
+            "(λx. λy. x) (λz. z)",
+            "(λx. λy. λz. x + y + z) 1 2",
+            "(λx. λx. x + 1) true 41",
+            "(λx. λy. x) 7 ((λx. x) (λy. y))",
+            "λx. (λy. y) x",
+        ],
+        |e, strategy| e.ref_defunctionalize(strategy)?.run(),
+    );
+}
+
+#[test]
+fn test_dispatchers() {
+    let program = p("(λf. f 1) (λx. incr x)")
+        .ref_defunctionalize(Strategy::CallByValue)
+        .unwrap();
+    assert_eq!(
+        program.to_string(),
+        "apply(Lam0, f) = apply(f, 1)\napply(Lam1, x) = incr (x)\nmain = apply(Lam0, Lam1)"
+    );
+    // the record holds the free variables, in scope order
+    let program = p("λa. λb. λc. a + c")
+        .ref_defunctionalize(Strategy::CallByValue)
+        .unwrap();
+    assert_eq!(
+        program.apply[2].body,
+        Term::Add(
+            Box::new(Term::Var("a".to_string())),
+            Box::new(Term::Var("c".to_string()))
+        )
+    );
+    assert_eq!(program.apply[2].fields, vec!["a".to_string()]);
+    assert!(program.force.is_empty());
+    // under call-by-name, only what is neither a value nor a variable is delayed
+    let program = p("λf. λx. f x (incr x) 1")
+        .ref_defunctionalize(Strategy::CallByName)
+        .unwrap();
+    assert_eq!(
+        program.to_string(),
+        "apply(Lam0, f) = Lam1(f)\n\
+         apply(Lam1(f), x) = apply(apply(apply(force(f), x), Delay0(x)), 1)\n\
+         force(Delay0(x)) = incr (force(x))\n\
+         main = Lam0"
+    );
+    assert!(matches!(
+        p("λx. x").ref_defunctionalize(Strategy::CallByNeed),
+        Err(StlcError::InvalidExpression(_))
+    ));
+}
+
+#[test]
+fn test_defunctionalization_errors() {
+    let run = |src: &str, strategy| p(src).ref_defunctionalize(strategy).unwrap().run();
+    assert!(matches!(
+        run("(λx. x 1) true", Strategy::CallByValue),
+        Err(StlcError::StuckExpressionCbv(_))
+    ));
+    assert!(matches!(
+        run("1 + (λx. x)", Strategy::CallByName),
+        Err(StlcError::StuckExpressionCbn(_))
+    ));
+    // stuck before the (diverging) second operand is evaluated
+    for src in [
+        "true ((λx. x x) (λx. x x))",
+        "(λx. x) + ((λx. x x) (λx. x x))",
+    ] {
+        for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+            let result = run(src, strategy);
+            assert!(
+                matches!(
+                    result,
+                    Err(StlcError::StuckExpressionCbv(_) | StlcError::StuckExpressionCbn(_))
+                ),
+                "{} by {}",
+                src,
+                strategy
+            );
+            assert_eq!(result, p(src).ref_eval_to_normal_form(strategy));
+        }
+    }
+    // under call-by-name, the (delayed) argument is forced by `+` only
+    let src = "(λx. λy. x + y) true ((λx. x x) (λx. x x))";
+    assert!(matches!(
+        run(src, Strategy::CallByName),
+        Err(StlcError::StuckExpressionCbn(_))
+    ));
+    assert_eq!(
+        run(src, Strategy::CallByName),
+        p(src).ref_eval_to_normal_form(Strategy::CallByName)
+    );
+    assert_eq!(
+        run("(λx. y) 1", Strategy::CallByName),
+        Err(StlcError::InvalidExpression("y".to_string()))
+    );
+    assert!(matches!(
+        run("(λx. x x) (λx. x x)", Strategy::CallByValue),
+        Err(StlcError::ExceedEvalLimit(_))
+    ));
+    // nesting one level deeper on every call runs into the limit as well
+    for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+        assert!(matches!(
+            run("(λx. incr (x x)) (λx. incr (x x))", strategy),
+            Err(StlcError::ExceedDepthLimit { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_y_combinator() {
+    let times = YCombinator::ref_new(YCombinator::ref_gen_built_in_times());
+    let inputs = vec![Exp::Nat(3), Exp::Nat(3), Exp::Nat(4)];
+    let program = times
+        .clone()
+        .ref_defunctionalize(inputs.clone(), Strategy::CallByName)
+        .unwrap();
+    // the recursion goes through `apply` only, i.e., one case per lambda
+    assert_eq!(program.apply.len(), lambdas(&program.source));
+    assert!(program
+        .apply
+        .iter()
+        .all(|case| matches!(case.source, Exp::Lambda(_))));
+    assert_eq!(program.run(), times.ref_eval(inputs, Strategy::CallByName));
+    assert_eq!(program.run().unwrap().0, Exp::Nat(12));
+
+    let equal = YCombinator::ref_new(YCombinator::ref_gen_built_in_equal());
+    let program = equal
+        .ref_defunctionalize(vec![Exp::Nat(30), Exp::Nat(29)], Strategy::CallByName)
+        .unwrap();
+    assert_eq!(program.run().unwrap().0, Exp::False);
+}
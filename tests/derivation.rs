@@ -0,0 +1,226 @@
+mod common;
+
+use common::{assert_agrees, p, TERMS};
+use stlc::{
+    derivation::{
+        eval::{EvalDerivation, EvalRule},
+        typing::TyRule,
+    },
+    limits::EvalConfig,
+    stlc_err::StlcError,
+    type_::{tarrow::TArrow, Env, Type},
+    Exp, Strategy,
+};
+
+#[test]
+fn test_typing_derivation() {
+    let tree = p("(λx: int. incr x) 1")
+        .ref_ty_derivation(None, &Env::new())
+        .unwrap();
+    assert_eq!(tree.conclusion.ty, Type::TInt);
+    assert_eq!(
+        tree.rules(),
+        vec![
+            &TyRule::App,
+            &TyRule::Abs,
+            &TyRule::Incr,
+            &TyRule::Var,
+            &TyRule::Num
+        ]
+    );
+    assert_eq!((tree.size(), tree.height()), (5, 4));
+    assert_eq!(
+        tree.to_ascii(),
+        "⊢ (λx: int. incr x) 1 : int    [T-App]\n  \
+         ⊢ λx: int. incr x : int → int    [T-Abs]\n    \
+         x: int ⊢ incr x : int    [T-Incr]\n      \
+         x: int ⊢ x : int    [T-Var]\n  \
+         ⊢ 1 : int    [T-Num]\n"
+    );
+    // every premise holds on its own
+    fn sound(tree: &stlc::derivation::typing::TyDerivation) {
+        let typing = &tree.conclusion;
+        assert_eq!(
+            typing.term.ty_check_against(&typing.ty, &typing.context),
+            Ok(())
+        );
+        tree.premises.iter().for_each(sound);
+    }
+    for src in [
+        "λf: int -> bool. λx: int. if f x then x + 1 else decr x",
+        "(λx: bool. if x then 1 else 2) (is_zero 0)",
+        "λx: int. λx: bool. x",
+    ] {
+        sound(&p(src).ref_ty_derivation(None, &Env::new()).unwrap());
+    }
+}
+
+#[test]
+fn test_typing_derivation_checked() {
+    // checking against an arrow, the annotation could be omitted
+    let ty = TArrow::build(Type::TInt, Type::TInt);
+    let tree = p("λx. x + 1")
+        .ref_ty_derivation(Some(&ty), &Env::new())
+        .unwrap();
+    assert_eq!(
+        tree.premises[0].conclusion.to_string(),
+        "x: int ⊢ x + 1 : int"
+    );
+    // under the given context
+    let mut env = Env::new();
+    env.insert("y".to_string(), Type::TBool);
+    let tree = p("if y then 1 else 2")
+        .ref_ty_derivation(None, &env)
+        .unwrap();
+    assert_eq!(tree.rule, TyRule::If);
+    assert_eq!(
+        tree.premises[0].conclusion.to_string(),
+        "y: bool ⊢ y : bool"
+    );
+    // the errors are the ones of the checker
+    let err = p("(λx: int. x) true")
+        .ref_ty_derivation(None, &Env::new())
+        .unwrap_err();
+    assert_eq!(err.path, vec![1]);
+    assert!(p("1")
+        .ref_ty_derivation(Some(&Type::TBool), &Env::new())
+        .is_err());
+}
+
+#[test]
+fn test_typing_derivation_latex() {
+    let tree = p("λx: int. is_zero x")
+        .ref_ty_derivation(None, &Env::new())
+        .unwrap();
+    assert_eq!(
+        tree.to_bussproofs(),
+        "\\begin{prooftree}\n\
+         \\AxiomC{}\n\
+         \\RightLabel{\\scriptsize T-Var}\n\
+         \\UnaryInfC{$\\mathtt{x} : \\mathtt{int} \\vdash \\mathtt{x} : \\mathtt{int}$}\n\
+         \\RightLabel{\\scriptsize T-IsZero}\n\
+         \\UnaryInfC{$\\mathtt{x} : \\mathtt{int} \\vdash \\mathtt{is\\_zero\\ x} : \\mathtt{bool}$}\n\
+         \\RightLabel{\\scriptsize T-Abs}\n\
+         \\UnaryInfC{$\\vdash \\mathtt{\\lambda x:\\ int.\\ is\\_zero\\ x} : \\mathtt{int\\ \\to \\ bool}$}\n\
+         \\end{prooftree}\n"
+    );
+    let tree = p("1 + 2").ref_ty_derivation(None, &Env::new()).unwrap();
+    assert_eq!(
+        tree.to_mathpartir(),
+        "\\begin{mathpar}\n\
+         \\inferrule*[right=T-Add]\n{\n  \
+         \\inferrule*[right=T-Num]\n  {}\n  {\\vdash \\mathtt{1} : \\mathtt{int}}\n  \
+         \\\\\n  \
+         \\inferrule*[right=T-Num]\n  {}\n  {\\vdash \\mathtt{2} : \\mathtt{int}}\n\
+         }\n{\\vdash \\mathtt{1\\ +\\ 2} : \\mathtt{int}}\n\
+         \\end{mathpar}\n"
+    );
+}
+
+#[test]
+fn test_eval_derivation_agrees() {
+    let extra = ["(λx. λx. x + 1) true 41", "λx. (λy. y) x"];
+    assert_agrees(&[Strategy::CallByValue], &extra, |e, _| {
+        let tree = e.ref_eval_derivation()?;
+        Ok((tree.value().clone(), tree.steps()))
+    });
+    // every judgement holds by small steps as well
+    fn agrees(tree: &EvalDerivation) {
+        assert!(tree.agrees_with_small_step(), "{}", tree.conclusion);
+        tree.premises.iter().for_each(agrees);
+    }
+    for src in TERMS.iter().chain(&extra) {
+        agrees(&p(src).ref_eval_derivation().unwrap());
+    }
+}
+
+#[test]
+fn test_eval_derivation() {
+    let tree = p("(λx. incr x) 1").ref_eval_derivation().unwrap();
+    assert_eq!(
+        tree.to_ascii(),
+        "(λx. incr x) 1 ⇓ 2    [B-App]\n  \
+         λx. incr x ⇓ λx. incr x    [B-Value]\n  \
+         1 ⇓ 1    [B-Value]\n  \
+         incr 1 ⇓ 2    [B-Incr]\n    \
+         1 ⇓ 1    [B-Value]\n"
+    );
+    let tree = p("if is_zero 0 then 1 else 2")
+        .ref_eval_derivation()
+        .unwrap();
+    assert_eq!(
+        tree.rules(),
+        vec![
+            &EvalRule::IfTrue,
+            &EvalRule::IsZeroZero,
+            &EvalRule::Value,
+            &EvalRule::Value
+        ]
+    );
+    assert_eq!(
+        tree.to_bussproofs(),
+        "\\begin{prooftree}\n\
+         \\AxiomC{}\n\
+         \\RightLabel{\\scriptsize B-Value}\n\
+         \\UnaryInfC{$\\mathtt{0} \\Downarrow \\mathtt{0}$}\n\
+         \\RightLabel{\\scriptsize B-IsZeroZero}\n\
+         \\UnaryInfC{$\\mathtt{is\\_zero\\ 0} \\Downarrow \\mathtt{true}$}\n\
+         \\AxiomC{}\n\
+         \\RightLabel{\\scriptsize B-Value}\n\
+         \\UnaryInfC{$\\mathtt{1} \\Downarrow \\mathtt{1}$}\n\
+         \\RightLabel{\\scriptsize B-IfTrue}\n\
+         \\BinaryInfC{$\\mathtt{if\\ is\\_zero\\ 0\\ then\\ 1\\ else\\ 2} \\Downarrow \\mathtt{1}$}\n\
+         \\end{prooftree}\n"
+    );
+}
+
+#[test]
+fn test_eval_derivation_errors() {
+    let derive = |src: &str| {
+        p(src)
+            .ref_eval_derivation()
+            .map(|tree| tree.value().clone())
+    };
+    // the same errors as the small-step evaluator, i.e., the first operand
+    // is checked before the (diverging) second one is evaluated
+    for src in [
+        "(λx. x 1) true",
+        "1 + (λx. x)",
+        "incr true",
+        "(λx. y) 1",
+        "true ((λx. x x) (λx. x x))",
+        "(λx. x) + ((λx. x x) (λx. x x))",
+    ] {
+        assert_eq!(
+            derive(src),
+            p(src)
+                .ref_eval_to_normal_form(Strategy::CallByValue)
+                .map(|(v, _)| v),
+            "{}",
+            src
+        );
+    }
+    // ω ⇓ v needs ω ⇓ v itself
+    let omega = p("(λx. x x) (λx. x x)");
+    assert_eq!(
+        omega.ref_eval_derivation().map(|tree| tree.value().clone()),
+        Err(StlcError::Diverges {
+            cycle: 1,
+            term: omega.clone()
+        })
+    );
+    let config = EvalConfig {
+        max_steps: 100,
+        cycle_window: 0,
+        ..EvalConfig::default()
+    };
+    assert!(matches!(
+        omega.ref_eval_derivation_with(&config),
+        Err(StlcError::ExceedStepLimit { steps: 100, .. })
+    ));
+    // deep, but within the steps
+    let deep = (0..250).fold(Exp::Nat(1), |e, _| Exp::Incr(Box::new(e)));
+    let tree = deep.ref_eval_derivation().unwrap();
+    assert_eq!((tree.value(), tree.steps()), (&Exp::Nat(251), 250));
+    assert_eq!(tree.height(), 251);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::p;
+use stlc::{Exp, Strategy};
+
+#[test]
+fn test_ast_to_dot() {
+    assert_eq!(
+        p("(λx: int. incr x) 1").to_dot(),
+        "digraph ast {\n  node [shape=box];\n  n0 [label=\"app\"];\n  n1 [label=\"λx: int\"];\n  \
+         n2 [label=\"incr\"];\n  n3 [label=\"x\"];\n  n2 -> n3;\n  n1 -> n2;\n  n0 -> n1;\n  \
+         n4 [label=\"1\"];\n  n0 -> n4;\n}\n"
+    );
+    let dot = p("if true then 1 else 2").to_dot();
+    assert!(dot.contains("n0 -> n1 [label=\"if\"];"));
+    assert!(dot.contains("n0 -> n3 [label=\"else\"];"));
+}
+
+#[test]
+fn test_reducts() {
+    let reducts = p("(λx. x + x) ((λy. y) 1)").ref_reducts();
+    assert_eq!(
+        reducts,
+        vec![
+            (vec![], p("(λy. y) 1 + (λy. y) 1")),
+            (vec![1], p("(λx. x + x) 1")),
+        ]
+    );
+    // under lambdas as well
+    assert_eq!(p("λx. incr 1").ref_reducts(), vec![(vec![0], p("λx. 2"))]);
+    assert!(p("x (λy. y)").ref_reducts().is_empty());
+}
+
+#[test]
+fn test_confluence() {
+    let graph = p("(λx. x + x) ((λy. y) (incr 1))").ref_reduction_graph(100);
+    assert!(!graph.truncated);
+    assert_eq!(graph.normal_forms(), vec![&Exp::Nat(4)]);
+    // both strategies reach it, each through its own edges
+    for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+        let mut at = 0;
+        while let Some(edge) = graph
+            .edges
+            .iter()
+            .find(|edge| edge.from == at && edge.strategies.contains(&strategy))
+        {
+            at = edge.to;
+        }
+        assert_eq!(graph.nodes[at], Exp::Nat(4), "{}", strategy);
+    }
+}
+
+#[test]
+fn test_omega() {
+    // ω reduces to itself, whatever the strategy
+    let graph = p("(λx. x x) (λx. x x)").ref_reduction_graph(100);
+    assert_eq!(graph.nodes.len(), 1);
+    assert_eq!(graph.edges.len(), 1);
+    assert_eq!(
+        graph.edges[0].strategies,
+        vec![Strategy::CallByValue, Strategy::CallByName]
+    );
+    assert!(graph.normal_forms().is_empty());
+
+    // call-by-value loops on the argument, call-by-name drops it
+    let graph = p("(λx. λy. incr y) ((λx. x x) (λx. x x)) 1").ref_reduction_graph(100);
+    let from_start: Vec<_> = graph
+        .edges
+        .iter()
+        .filter(|edge| edge.from == 0)
+        .map(|edge| (edge.to, edge.path.clone(), edge.strategies.clone()))
+        .collect();
+    assert_eq!(
+        from_start,
+        vec![
+            (1, vec![0], vec![Strategy::CallByName]),
+            (0, vec![0, 1], vec![Strategy::CallByValue]),
+        ]
+    );
+    assert_eq!(graph.normal_forms(), vec![&Exp::Nat(2)]);
+    let dot = graph.to_dot();
+    assert!(dot.contains("n0 -> n0 [label=\"cbv\", color=blue];"));
+    assert!(dot.contains("n0 -> n1 [label=\"cbn\", color=red];"));
+    assert!(dot.contains("[label=\"2\", peripheries=2];"));
+
+    // a growing term never ends, only the first few are explored
+    let graph = p("(λx. x x x) (λx. x x x)").ref_reduction_graph(5);
+    assert!(graph.truncated);
+    assert_eq!(graph.nodes.len(), 5);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::p;
+use stlc::{
+    expr::lambda::Lambda,
+    parser::{parse_exp_with_spans, parse_type},
+    stlc_err::StlcError,
+    Exp, Strategy,
+};
+
+#[test]
+fn test_eval_add() {
+    for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+        assert_eq!(
+            p("1 + 2").ref_eval_to_normal_form(strategy),
+            Ok((Exp::Nat(3), 1))
+        );
+        // left to right, both operands first
+        assert_eq!(
+            p("incr 1 + decr 3").ref_eval_multi_step(1, strategy),
+            Ok(p("2 + decr 3"))
+        );
+        assert_eq!(
+            p("incr 1 + decr 3").ref_eval_multi_step(2, strategy),
+            Ok(p("2 + 2"))
+        );
+        assert_eq!(
+            p("(λx. λy. x + y) 1 2").ref_eval_to_normal_form(strategy),
+            Ok((Exp::Nat(3), 3))
+        );
+        // saturating, just like `incr`
+        assert_eq!(
+            Exp::Nat(u32::MAX).ref_eval_to_normal_form(strategy),
+            Ok((Exp::Nat(u32::MAX), 0))
+        );
+        assert_eq!(
+            p(&format!("{} + 1", u32::MAX)).ref_eval_to_normal_form(strategy),
+            Ok((Exp::Nat(u32::MAX), 1))
+        );
+    }
+    assert_eq!(
+        p("(λx. x) + 1").ref_eval_one_step_cbv(),
+        Err(StlcError::StuckExpressionCbv("(λx. x) + (1)".into()))
+    );
+    assert_eq!(
+        p("1 + true").ref_eval_one_step_cbn(),
+        Err(StlcError::StuckExpressionCbn("(1) + (true)".into()))
+    );
+}
+
+#[test]
+fn test_add_redex_path() {
+    assert_eq!(p("1 + 2").ref_redex_path(Strategy::CallByValue), vec![]);
+    assert_eq!(
+        p("1 + (λx. x) 2").ref_redex_path(Strategy::CallByValue),
+        vec![1]
+    );
+    // the `true` is to blame
+    let src = "1 + true";
+    let (e, mut spans) = parse_exp_with_spans(src).unwrap();
+    let err = e
+        .ref_eval_to_normal_form_spanned(Strategy::CallByValue, &mut spans)
+        .unwrap_err();
+    assert_eq!(err.span.map(|s| &src[s.start..s.end]), Some("true"));
+}
+
+#[test]
+fn test_typed_lambda_substitution() {
+    // the annotation of the inner lambda survives the substitution
+    let e = p("(λx: int. λy: int. x + y) 1");
+    let result = e.ref_eval_one_step_cbv().unwrap();
+    assert_eq!(result, p("λy: int. 1 + y"));
+    match result {
+        Exp::Lambda(lambda) => assert_eq!(lambda.ty, Some(parse_type("int").unwrap())),
+        _ => unreachable!(),
+    }
+    // as well as when renamed to avoid capture
+    let e = p("λy: bool. x").ref_substitute("x".into(), p("y"));
+    assert_eq!(
+        e,
+        Lambda::build_with_type("y1", p("y"), parse_type("bool").unwrap())
+    );
+}
+
+#[test]
+fn test_typed_term_runs() {
+    // type checks on day5, then runs to the expected value
+    let e = p("(λx: int. (λy: int. x + y) 2) 3");
+    assert!(e.ref_ty_check(parse_type("int").unwrap()));
+    for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+        let (result, _) = e.clone().ref_eval_to_normal_form(strategy).unwrap();
+        assert_eq!(result, Exp::Nat(5));
+    }
+}
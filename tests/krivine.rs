@@ -0,0 +1,89 @@
+mod common;
+
+use common::{assert_agrees, p};
+use stlc::{krivine::Krivine, stlc_err::StlcError, Exp, Strategy};
+
+#[test]
+fn test_krivine_agrees() {
+    assert_agrees(
+        &[Strategy::CallByName],
+        &[
+            "(λx. λy. incr y) ((λx. x x) (λx. x x)) 1",
+            "(λx. λy. x) (λz. y)",
+            "(λx. λy. λz. x y) (λa. a) w",
+            "λx. (λy. y) x",
+            "(λx. x 1) true",
+            "(λx. x + 1) (λy. y)",
+            "incr (if 1 then 2 else 3)",
+            "(λx. y) 1 + 2",
+        ],
+        |e, _| e.ref_eval_krivine(),
+    );
+    // and every intermediate step of the terms from `tests/day3.rs`, i.e.,
+    // with the substitutions already (partially) done
+    for src in ["(λx. incr x) 1", "(λx. λy. incr y) ((λx. x x) (λx. x x)) 1"] {
+        let mut e = p(src);
+        while let Ok(next) = e.clone().ref_eval_one_step_cbn() {
+            assert_eq!(
+                next.ref_eval_krivine(),
+                next.clone().ref_eval_to_normal_form(Strategy::CallByName),
+                "{}",
+                next
+            );
+            e = next;
+        }
+    }
+}
+
+#[test]
+fn test_krivine_trace() {
+    let trace: Vec<String> = p("(λx. incr x) 1")
+        .ref_trace_krivine(100)
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(
+        trace,
+        [
+            "((λx. incr (x)) (1), ∅, [])",
+            "(λx. incr (x), ∅, [⟨1, ∅⟩])",
+            "(incr (x), {x ↦ ⟨1⟩}, [])",
+            "(x, {x ↦ ⟨1⟩}, [incr □])",
+            "(1, ∅, [incr □])",
+            "(2, ∅, [])",
+        ]
+    );
+
+    // the diverging argument is pushed, but never accessed
+    let trace = p("(λx. λy. incr y) ((λx. x x) (λx. x x)) 1").ref_trace_krivine(100);
+    assert!(trace.last().unwrap().is_final());
+    assert_eq!(trace.last().unwrap().term, Exp::Nat(2));
+}
+
+#[test]
+fn test_krivine_step_by_step() {
+    let mut machine = Krivine::new(&p("(λx. λy. x) true false"));
+    let mut transitions = 0;
+    while machine.step().unwrap() {
+        transitions += 1;
+        assert!(machine.state().stack.len() <= 2);
+    }
+    assert_eq!(transitions, 5);
+    assert_eq!(machine.steps(), 2);
+    assert_eq!(machine.run(), Ok((Exp::True, 2)));
+}
+
+#[test]
+fn test_krivine_omega() {
+    // `x` is passed by its own binding, rather than by a thunk of it, so
+    // every access takes a single transition, i.e., push, access & grab
+    let mut machine = Krivine::new(&p("(λx. x x) (λx. x x)"));
+    for _ in 0..2 + 3 * 1000 {
+        assert!(machine.step().unwrap());
+    }
+    assert_eq!(machine.steps(), 1001);
+    assert!(matches!(
+        p("(λx. x x) (λx. x x)").ref_eval_krivine(),
+        Err(StlcError::ExceedEvalLimit(_))
+    ));
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use std::time::Duration;
+
+use common::p;
+use stlc::{
+    expr::incr::Incr,
+    limits::{EvalConfig, REPORTED_FRAMES},
+    stlc_err::StlcError,
+    Exp, Strategy,
+};
+
+const OMEGA: &str = "(λx. x x) (λx. x x)";
+const GROW_OMEGA: &str = "(λx. x x x) (λx. x x x)";
+
+#[test]
+fn test_size_and_depth() {
+    assert_eq!(p("λx. incr x").size(), 3);
+    assert_eq!(p("λx. incr x").depth(), 3);
+    assert_eq!(p(OMEGA).size(), 9);
+    assert_eq!(p("if true then 1 else 2 + 3").depth(), 3);
+}
+
+#[test]
+fn test_step_limit() {
+    // ω would be detected right away otherwise
+    let config = EvalConfig {
+        max_steps: 10,
+        cycle_window: 0,
+        ..EvalConfig::default()
+    };
+    for strategy in [
+        Strategy::CallByValue,
+        Strategy::CallByName,
+        Strategy::CallByNeed,
+        Strategy::NormalOrder,
+    ] {
+        assert_eq!(
+            p(OMEGA).ref_eval_to_normal_form_with(strategy, &config),
+            Err(StlcError::ExceedStepLimit {
+                term: p(OMEGA),
+                steps: 10
+            }),
+            "{}",
+            strategy
+        );
+    }
+    // exactly as many steps as allowed
+    let config = EvalConfig {
+        max_steps: 2,
+        ..EvalConfig::default()
+    };
+    assert_eq!(
+        p("(λx. incr x) 1").ref_eval_to_normal_form_with(Strategy::CallByValue, &config),
+        Ok((Exp::Nat(2), 2))
+    );
+    assert!(matches!(
+        p("(λx. incr (incr x)) 1").ref_eval_to_normal_form_with(Strategy::CallByValue, &config),
+        Err(StlcError::ExceedStepLimit { steps: 2, .. })
+    ));
+}
+
+#[test]
+fn test_term_limits() {
+    // it keeps growing, way before running out of steps
+    let config = EvalConfig {
+        max_size: Some(100),
+        ..EvalConfig::default()
+    };
+    let Err(StlcError::ExceedSizeLimit { term, steps }) =
+        p(GROW_OMEGA).ref_eval_to_normal_form_with(Strategy::CallByValue, &config)
+    else {
+        panic!("expect to exceed the size limit");
+    };
+    assert!(term.size() > 100 && steps < 20);
+    // the partial result is where the evaluation stops
+    assert_eq!(
+        p(GROW_OMEGA).ref_eval_multi_step(steps, Strategy::CallByValue),
+        Ok(term)
+    );
+
+    let config = EvalConfig {
+        max_depth: Some(10),
+        ..EvalConfig::default()
+    };
+    for strategy in [Strategy::CallByName, Strategy::CallByNeed] {
+        let result = p(GROW_OMEGA).ref_eval_to_normal_form_with(strategy, &config);
+        assert!(
+            matches!(&result, Err(StlcError::ExceedDepthLimit { term, .. }) if term.depth() > 10),
+            "{:?}",
+            result
+        );
+    }
+}
+
+#[test]
+fn test_timeout() {
+    let config = EvalConfig {
+        timeout: Some(Duration::ZERO),
+        ..EvalConfig::default()
+    };
+    assert_eq!(
+        p(OMEGA).ref_eval_to_normal_form_with(Strategy::CallByValue, &config),
+        Err(StlcError::Timeout {
+            term: p(OMEGA),
+            steps: 0
+        })
+    );
+    let config = EvalConfig {
+        max_steps: u32::MAX,
+        timeout: Some(Duration::from_millis(50)),
+        cycle_window: 0,
+        ..EvalConfig::default()
+    };
+    let result = p(OMEGA).ref_eval_to_normal_form_with(Strategy::CallByName, &config);
+    assert!(
+        matches!(result, Err(StlcError::Timeout { steps, .. }) if steps > 0),
+        "{:?}",
+        result
+    );
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .starts_with("Timeout(after "));
+}
+
+#[test]
+fn test_divergence() {
+    // ω reduces to itself in a single step, whatever the strategy
+    for strategy in [
+        Strategy::CallByValue,
+        Strategy::CallByName,
+        Strategy::NormalOrder,
+        Strategy::ApplicativeOrder,
+    ] {
+        assert_eq!(
+            p(OMEGA).ref_eval_to_normal_form(strategy),
+            Err(StlcError::Diverges {
+                cycle: 1,
+                term: p(OMEGA)
+            }),
+            "{}",
+            strategy
+        );
+    }
+    // up to alpha-equivalence, reported at the first repetition
+    let e = p("(λy. (λx. x x) (λz. z z)) 1");
+    assert_eq!(
+        e.ref_eval_to_normal_form(Strategy::CallByValue),
+        Err(StlcError::Diverges {
+            cycle: 1,
+            term: p("(λz. z z) (λz. z z)")
+        })
+    );
+    // a longer cycle, i.e., ω with a detour through a condition
+    let e = p("(λx. if true then x x else 0) (λx. if true then x x else 0)");
+    assert!(matches!(
+        e.clone().ref_eval_to_normal_form(Strategy::CallByValue),
+        Err(StlcError::Diverges { cycle: 2, .. })
+    ));
+    // too long for the window
+    let config = EvalConfig {
+        max_steps: 100,
+        cycle_window: 1,
+        ..EvalConfig::default()
+    };
+    assert!(matches!(
+        e.ref_eval_to_normal_form_with(Strategy::CallByValue, &config),
+        Err(StlcError::ExceedStepLimit { steps: 100, .. })
+    ));
+    // growing is not a cycle
+    let config = EvalConfig {
+        max_steps: 100,
+        ..EvalConfig::default()
+    };
+    assert!(matches!(
+        p(GROW_OMEGA).ref_eval_to_normal_form_with(Strategy::CallByValue, &config),
+        Err(StlcError::ExceedStepLimit { .. })
+    ));
+    // the argument of K is never evaluated by name
+    assert_eq!(
+        p("(λx. λy. incr y) ((λx. x x) (λx. x x)) 1").ref_eval_to_normal_form(Strategy::CallByName),
+        Ok((Exp::Nat(2), 3))
+    );
+}
+
+#[test]
+fn test_machine_limits() {
+    let config = EvalConfig {
+        max_steps: 10,
+        cycle_window: 0,
+        ..EvalConfig::default()
+    };
+    let omega = Err(StlcError::ExceedStepLimit {
+        term: p(OMEGA),
+        steps: 10,
+    });
+    for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+        assert_eq!(
+            p(OMEGA).ref_eval_to_normal_form_big_step_with(strategy, &config),
+            omega
+        );
+        assert_eq!(p(OMEGA).ref_eval_cek_with(strategy, &config), omega);
+        assert_eq!(p(OMEGA).ref_eval_vm_with(strategy, &config), omega);
+        let program = p(OMEGA).ref_defunctionalize(strategy).unwrap();
+        assert_eq!(program.run_with(&config), omega);
+    }
+    assert_eq!(p(OMEGA).ref_eval_krivine_with(&config), omega);
+    assert_eq!(p(OMEGA).ref_closure_convert().run_with(&config), omega);
+    // the legacy entry points still report the legacy error
+    let e = p(OMEGA);
+    for result in [
+        e.ref_eval_krivine(),
+        e.ref_eval_vm(Strategy::CallByValue),
+        e.ref_closure_convert().run(),
+        e.ref_defunctionalize(Strategy::CallByName).unwrap().run(),
+    ] {
+        assert!(matches!(result, Err(StlcError::ExceedEvalLimit(_))));
+    }
+    // the CEK machine reports the whole term, the same as the small steps
+    let e = p("incr ((λx. x x) (λx. x x))");
+    assert_eq!(
+        e.ref_eval_cek_with(Strategy::CallByValue, &config),
+        e.clone()
+            .ref_eval_to_normal_form_with(Strategy::CallByValue, &config)
+    );
+    // up to the innermost frames, however deep the continuation (or the
+    // stack of the Krivine machine) grows
+    let e = p("(λx. incr (x x)) (λx. incr (x x))");
+    let deep = EvalConfig {
+        max_steps: 100000,
+        ..EvalConfig::default()
+    };
+    let innermost = Err(StlcError::ExceedStepLimit {
+        term: (0..REPORTED_FRAMES).fold(e.clone(), |e, _| Incr::build(e)),
+        steps: 100000,
+    });
+    assert_eq!(e.ref_eval_cek_with(Strategy::CallByValue, &deep), innermost);
+    assert_eq!(e.ref_eval_krivine_with(&deep), innermost);
+    assert!(matches!(
+        e.ref_eval_cek(Strategy::CallByValue),
+        Err(StlcError::ExceedEvalLimit(_))
+    ));
+    let config = EvalConfig {
+        timeout: Some(Duration::ZERO),
+        ..EvalConfig::default()
+    };
+    assert!(matches!(
+        p(OMEGA).ref_eval_to_normal_form_big_step_with(Strategy::CallByValue, &config),
+        Err(StlcError::Timeout { steps: 0, .. })
+    ));
+    let e = p(OMEGA);
+    for result in [
+        e.ref_eval_cek_with(Strategy::CallByValue, &config),
+        e.ref_eval_krivine_with(&config),
+        e.ref_eval_vm_with(Strategy::CallByValue, &config),
+        e.ref_closure_convert().run_with(&config),
+        e.ref_defunctionalize(Strategy::CallByName)
+            .unwrap()
+            .run_with(&config),
+    ] {
+        assert!(matches!(result, Err(StlcError::Timeout { steps: 0, .. })));
+    }
+}
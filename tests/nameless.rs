@@ -0,0 +1,96 @@
+mod common;
+
+use common::p;
+use stlc::{
+    expr::lambda::Lambda,
+    nameless::{Hint, Term},
+};
+
+fn lam(body: Term) -> Term {
+    Term::Lambda(Hint("_".into()), None, Box::new(body))
+}
+
+fn app(t1: Term, t2: Term) -> Term {
+    Term::App(Box::new(t1), Box::new(t2))
+}
+
+#[test]
+fn test_from_exp() {
+    // λx. λy. x y z ==> λ. λ. 1 0 z
+    let t = Term::from_exp(&p("λx. λy. x y z"));
+    let expected = lam(lam(app(
+        app(Term::Bound(1), Term::Bound(0)),
+        Term::Free("z".into()),
+    )));
+    assert_eq!(t, expected);
+    assert_eq!(t.to_string(), "λ. λ. ((1) (0)) (z)");
+    // shadowing refers to the innermost binder
+    assert_eq!(Term::from_exp(&p("λx. λx. x")), lam(lam(Term::Bound(0))));
+    // the binder names do not matter
+    assert_eq!(
+        Term::from_exp(&p("λx. λy. x")),
+        Term::from_exp(&p("λa. λb. a"))
+    );
+    assert_ne!(
+        Term::from_exp(&p("λx. λy. x")),
+        Term::from_exp(&p("λx. λy. y"))
+    );
+}
+
+#[test]
+fn test_to_exp() {
+    // the original names come back whenever possible
+    for src in [
+        "λx. λy. x y z",
+        "λx: int. λy: bool. if y then x + 1 else decr x",
+        "(λf. λx. f (f x)) (λx. incr x) 0",
+    ] {
+        assert_eq!(Term::from_exp(&p(src)).to_exp(), p(src));
+    }
+    // otherwise a fresh one, i.e., never capture the free `y`
+    let t = lam(app(Term::Bound(0), Term::Free("x".into())));
+    let t = match t {
+        Term::Lambda(_, ty, body) => Term::Lambda(Hint("x".into()), ty, body),
+        _ => unreachable!(),
+    };
+    assert_eq!(t.to_exp(), p("λx1. x1 x"));
+}
+
+#[test]
+fn test_shift_subst() {
+    // ↑¹₀ (λ. 0 1) = λ. 0 2
+    assert_eq!(
+        lam(app(Term::Bound(0), Term::Bound(1))).shift(1, 0),
+        lam(app(Term::Bound(0), Term::Bound(2)))
+    );
+    // [0 := z] (λ. 0 1) = λ. 0 z
+    assert_eq!(
+        lam(app(Term::Bound(0), Term::Bound(1))).subst(0, &Term::Free("z".into())),
+        lam(app(Term::Bound(0), Term::Free("z".into())))
+    );
+    // (λx. λy. x) y -> λy1. y, no capture
+    let Term::Lambda(_, _, body) = Term::from_exp(&p("λx. λy. x")) else {
+        unreachable!()
+    };
+    let result = Term::beta(&body, &Term::Free("y".into()));
+    assert!(result.is_locally_closed());
+    assert_eq!(result.to_exp(), p("λy1. y"));
+    // the result agrees with `ref_substitute`
+    let e = p("λy. λz. x y (λx. x z)");
+    let s = p("λw. y z");
+    let Term::Lambda(_, _, body) = Term::from_exp(&Lambda::build("x", e.clone())) else {
+        unreachable!()
+    };
+    assert_eq!(
+        Term::beta(&body, &Term::from_exp(&s)),
+        Term::from_exp(&e.ref_substitute("x".into(), s))
+    );
+}
+
+#[test]
+fn test_appears_free_in() {
+    let e = p("λx. (λx. x) x");
+    assert!(!e.ref_appears_free_in("x"));
+    assert!(p("λy. x + y").ref_appears_free_in("x"));
+    assert!(!p("λy. x + y").ref_appears_free_in("y"));
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::p;
+use stlc::{stlc_err::StlcError, Exp, Strategy};
+
+#[test]
+fn test_need_shares_arguments() {
+    let e = p("(λx. x + x) (incr 1)");
+    assert_eq!(
+        e.clone().ref_eval_to_normal_form(Strategy::CallByName),
+        Ok((Exp::Nat(4), 4))
+    );
+    // `incr 1` is only evaluated once
+    assert_eq!(
+        e.ref_eval_to_normal_form(Strategy::CallByNeed),
+        Ok((Exp::Nat(4), 3))
+    );
+
+    // the more it gets used, the more steps saved
+    let expensive = "(λf. f (f (f 0))) (λn. incr n)";
+    let e = p(&format!("(λx. x + x + x + x) ({})", expensive));
+    let (v1, cbn) = e
+        .clone()
+        .ref_eval_to_normal_form(Strategy::CallByName)
+        .unwrap();
+    let (v2, need) = e.ref_eval_by_need().unwrap();
+    assert_eq!(v1, Exp::Nat(12));
+    assert_eq!(v1, v2);
+    assert!(need < cbn, "expect {} < {}", need, cbn);
+}
+
+#[test]
+fn test_need_is_lazy() {
+    // the diverging argument is never needed
+    let e = p("(λx. λy. incr y) ((λx. x x) (λx. x x)) 1");
+    assert_eq!(e.ref_eval_by_need(), Ok((Exp::Nat(2), 3)));
+    assert!(matches!(
+        p("(λx. x x) (λx. x x)").ref_eval_by_need(),
+        Err(StlcError::ExceedEvalLimit(_))
+    ));
+}
+
+#[test]
+fn test_need_read_back() {
+    // the references are replaced by their thunks, without capture
+    assert_eq!(
+        p("(λx. λy. x) (incr y)").ref_eval_by_need(),
+        Ok((p("λy1. incr y"), 1))
+    );
+    assert_eq!(
+        p("(λx. if x then 1 else 2) (is_zero (decr 1))").ref_eval_by_need(),
+        Ok((Exp::Nat(1), 4))
+    );
+    assert_eq!(
+        p("(λx. x 1) (incr true)").ref_eval_by_need(),
+        Err(StlcError::InvalidExpression("true".into()))
+    );
+    assert_eq!(
+        p("(λx. x + 1) (λy. y)").ref_eval_by_need(),
+        Err(StlcError::StuckExpressionCbn("(λy. y) + (1)".into()))
+    );
+}
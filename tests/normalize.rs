@@ -0,0 +1,106 @@
+mod common;
+
+use common::p;
+use stlc::{alpha::Alpha, normalize::NormalForm, stlc_err::StlcError, Exp, Strategy};
+
+/// the church numeral, i.e., λf. λx. f (f .. (f x))
+fn church(n: u32) -> Exp {
+    let body = (0..n).fold("x".to_string(), |acc, _| format!("f ({})", acc));
+    p(&format!("λf. λx. {}", body))
+}
+
+#[test]
+fn test_reduce_under_binders() {
+    let e = p("λx. (λy. y) x");
+    assert_eq!(
+        e.clone().ref_eval_to_normal_form(Strategy::CallByName),
+        Ok((e.clone(), 0))
+    );
+    for strategy in [Strategy::NormalOrder, Strategy::ApplicativeOrder] {
+        assert_eq!(
+            e.clone().ref_eval_to_normal_form(strategy),
+            Ok((p("λx. x"), 1))
+        );
+    }
+}
+
+#[test]
+fn test_normal_form_targets() {
+    // head normal form leaves the arguments alone
+    let e = p("λx. x ((λy. y) z)");
+    assert_eq!(
+        e.clone()
+            .ref_normalize(Strategy::NormalOrder, NormalForm::WeakHead),
+        Ok((e.clone(), 0))
+    );
+    assert_eq!(
+        e.clone()
+            .ref_normalize(Strategy::NormalOrder, NormalForm::Head),
+        Ok((e.clone(), 0))
+    );
+    assert_eq!(
+        e.ref_normalize(Strategy::NormalOrder, NormalForm::Full),
+        Ok((p("λx. x z"), 1))
+    );
+
+    let e = p("(λx. λy. x y) (λz. z)");
+    assert_eq!(
+        e.clone()
+            .ref_normalize(Strategy::NormalOrder, NormalForm::WeakHead),
+        Ok((p("λy. (λz. z) y"), 1))
+    );
+    assert_eq!(
+        e.ref_normalize(Strategy::NormalOrder, NormalForm::Head),
+        Ok((p("λy. y"), 2))
+    );
+
+    // neutral terms are normal rather than stuck
+    let e = p("λx. if x then (λy. y) 1 else incr x");
+    assert_eq!(
+        e.ref_normalize(Strategy::NormalOrder, NormalForm::Full),
+        Ok((p("λx. if x then 1 else incr x"), 1))
+    );
+    assert!(p("1 2")
+        .ref_normalize(Strategy::NormalOrder, NormalForm::Full)
+        .is_err());
+}
+
+#[test]
+fn test_normal_vs_applicative() {
+    // the unused argument only differs in the number of steps
+    let e = p("(λx. λy. y) ((λz. z) 1)");
+    assert_eq!(
+        e.clone().ref_eval_to_normal_form(Strategy::NormalOrder),
+        Ok((p("λy. y"), 1))
+    );
+    assert_eq!(
+        e.ref_eval_to_normal_form(Strategy::ApplicativeOrder),
+        Ok((p("λy. y"), 2))
+    );
+    // while a diverging one is only discarded in normal order
+    let e = p("(λx. λy. y) ((λx. x x) (λx. x x))");
+    assert_eq!(
+        e.clone().ref_eval_to_normal_form(Strategy::NormalOrder),
+        Ok((p("λy. y"), 1))
+    );
+    assert_eq!(
+        e.clone()
+            .ref_eval_to_normal_form(Strategy::ApplicativeOrder),
+        Err(StlcError::Diverges { cycle: 1, term: e })
+    );
+}
+
+#[test]
+fn test_church_numerals() {
+    let plus = p("λm. λn. λf. λx. m f (n f x)");
+    let times = p("λm. λn. λf. m (n f)");
+    for strategy in [Strategy::NormalOrder, Strategy::ApplicativeOrder] {
+        let e = p(&format!("({}) ({}) ({})", plus, church(2), church(3)));
+        let (result, _) = e.ref_eval_to_normal_form(strategy).unwrap();
+        assert_eq!(Alpha::new(result), Alpha::new(church(5)));
+
+        let e = p(&format!("({}) ({}) ({})", times, church(2), church(3)));
+        let (result, _) = e.ref_eval_to_normal_form(strategy).unwrap();
+        assert_eq!(Alpha::new(result), Alpha::new(church(6)));
+    }
+}
@@ -0,0 +1,138 @@
+use stlc::{
+    expr::{
+        add::Add, app::App, cond::Cond, decr::Decr, incr::Incr, is_zero::IsZero, lambda::Lambda,
+        var::Var,
+    },
+    parser::{parse_exp, parse_type},
+    refsols::{
+        refsol_day1::{Exp1, Exp2, Exp3, Exp4, Exp5},
+        refsol_day4::YCombinator,
+    },
+    stlc_err::StlcError,
+    type_::{tarrow::TArrow, Type},
+    Exp,
+};
+
+#[test]
+fn test_parse_basic() {
+    // (λx. incr x) 1
+    let e = App::build(Lambda::build("x", Incr::build(Var::build("x"))), 1.into());
+    assert_eq!(parse_exp("(λx. incr x) 1").unwrap(), e);
+    assert_eq!(parse_exp(r"(\x. incr x) 1").unwrap(), e);
+
+    // λx. λy. x y z, the body extends as far as possible
+    let e = Lambda::build(
+        "x",
+        Lambda::build(
+            "y",
+            App::build(
+                App::build(Var::build("x"), Var::build("y")),
+                Var::build("z"),
+            ),
+        ),
+    );
+    assert_eq!("λx. λy. x y z".parse::<Exp>().unwrap(), e);
+
+    // if is_zero (decr 1) then f 1 + 2 else λx. x
+    let e = Cond::build(
+        IsZero::build(Decr::build(1.into())),
+        Add::build(App::build(Var::build("f"), 1.into()), 2.into()),
+        Lambda::build("x", Var::build("x")),
+    );
+    assert_eq!(
+        parse_exp("if is_zero (decr 1) then f 1 + 2 else λx. x").unwrap(),
+        e
+    );
+}
+
+#[test]
+fn test_parse_round_trip() {
+    let exps = vec![
+        Exp1::new(),
+        Exp2::new(),
+        Exp3::new(),
+        Exp4::new(),
+        Exp5::new(),
+        YCombinator::ref_gen_built_in_times(),
+        YCombinator::ref_gen_built_in_equal(),
+        // ((λx. x + 1) 1) + (incr (decr true))
+        Add::build(
+            App::build(
+                Lambda::build("x", Add::build(Var::build("x"), 1.into())),
+                1.into(),
+            ),
+            Incr::build(Decr::build(Exp::True)),
+        ),
+    ];
+    for e in exps {
+        assert_eq!(parse_exp(&e.to_string()).unwrap(), e, "round trip {e}");
+    }
+}
+
+#[test]
+fn test_parse_error() {
+    for src in [
+        "λx x",
+        "if true then 1",
+        "(λx. x",
+        "x )",
+        "",
+        "4294967296",
+        "x # y",
+    ] {
+        let Err(StlcError::ParseError(_)) = parse_exp(src) else {
+            panic!("expect `{src}` to be rejected");
+        };
+    }
+}
+
+#[test]
+fn test_parse_type() {
+    assert_eq!(parse_type("int").unwrap(), Type::TInt);
+    // right associative
+    assert_eq!(
+        parse_type("int -> bool -> X0").unwrap(),
+        TArrow::build(Type::TInt, TArrow::build(Type::TBool, "X0".into()))
+    );
+    assert_eq!(
+        "(int -> bool) -> X0".parse::<Type>().unwrap(),
+        TArrow::build(TArrow::build(Type::TInt, Type::TBool), "X0".into())
+    );
+    for src in ["int ->", "(int", "-> bool", "int - bool"] {
+        let Err(StlcError::ParseError(_)) = parse_type(src) else {
+            panic!("expect `{src}` to be rejected");
+        };
+    }
+}
+
+#[test]
+fn test_parse_typed_term() {
+    // λx: (TInt -> (TInt -> TInt)). λy: (TInt -> TInt). λz: TInt. (x z) (y z)
+    let e = parse_exp("λx: int -> int -> int. λy: int -> int. λz: int. x z (y z)").unwrap();
+    let res = Lambda::build_with_type(
+        "x",
+        Lambda::build_with_type(
+            "y",
+            Lambda::build_with_type(
+                "z",
+                App::build(
+                    App::build(Var::build("x"), Var::build("z")),
+                    App::build(Var::build("y"), Var::build("z")),
+                ),
+                Type::TInt,
+            ),
+            TArrow::build(Type::TInt, Type::TInt),
+        ),
+        TArrow::build(Type::TInt, TArrow::build(Type::TInt, Type::TInt)),
+    );
+    assert_eq!(e, res);
+
+    // the day5 & day7 test cases, as one-line strings
+    let e = parse_exp("λx: bool -> int. λy: bool. λz: int. x y + z").unwrap();
+    let t = parse_type("(bool -> int) -> bool -> int -> int").unwrap();
+    assert!(e.ref_ty_check(t));
+
+    let e = parse_exp("λx. λy. if y then x + 1 else x").unwrap();
+    let t = parse_type("int -> bool -> int").unwrap();
+    assert_eq!(e.ref_ty_infer_c(), Some(t));
+}
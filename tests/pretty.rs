@@ -0,0 +1,107 @@
+use stlc::{
+    expr::{add::Add, app::App, incr::Incr, lambda::Lambda, var::Var},
+    parser::{parse_exp, parse_type},
+    pretty::PrettyConfig,
+    refsols::{
+        refsol_day1::{Exp3, Exp4, Exp5},
+        refsol_day4::YCombinator,
+    },
+    type_::{tarrow::TArrow, Type},
+};
+
+#[test]
+fn test_pretty_minimal_parentheses() {
+    let config = PrettyConfig::default();
+
+    // ((f x) y) (g z)
+    let e = App::build(
+        App::build(
+            App::build(Var::build("f"), Var::build("x")),
+            Var::build("y"),
+        ),
+        App::build(Var::build("g"), Var::build("z")),
+    );
+    assert_eq!(e.pretty(&config), "f x y (g z)");
+
+    // (λx. x) ((λy. y) (λz. z))
+    assert_eq!(Exp3::new().pretty(&config), "(λx. x) ((λy. y) (λz. z))");
+
+    // ((λx. x) (λy. if y then false else true)) true
+    assert_eq!(
+        Exp4::new().pretty(&config),
+        "(λx. x) (λy. if y then false else true) true"
+    );
+
+    // (1 + 2) + (3 + incr 4)
+    let e = Add::build(
+        Add::build(1.into(), 2.into()),
+        Add::build(3.into(), Incr::build(4.into())),
+    );
+    assert_eq!(e.pretty(&config), "1 + 2 + (3 + incr 4)");
+
+    // λx: int -> int. λy. x y, the annotation is kept
+    let e = Lambda::build_with_type(
+        "x",
+        Lambda::build("y", App::build(Var::build("x"), Var::build("y"))),
+        TArrow::build(Type::TInt, Type::TInt),
+    );
+    assert_eq!(e.pretty(&config), "λx: int → int. λy. x y");
+    assert_eq!(
+        e.pretty(&PrettyConfig::new(false, 80)),
+        r"\x: int -> int. \y. x y"
+    );
+}
+
+#[test]
+fn test_pretty_type() {
+    let config = PrettyConfig::new(false, 80);
+    // (a -> b) -> c
+    let t1 = TArrow::build(TArrow::build("a".into(), "b".into()), "c".into());
+    // a -> (b -> c)
+    let t2 = TArrow::build("a".into(), TArrow::build("b".into(), "c".into()));
+    assert_eq!(t1.pretty(&config), "(a -> b) -> c");
+    assert_eq!(t2.pretty(&config), "a -> b -> c");
+    assert_eq!(t1.to_string(), "(a -> b) -> c");
+    assert_eq!(t2.to_string(), "a -> b -> c");
+    assert_eq!(
+        parse_type(&t1.pretty(&PrettyConfig::default())).unwrap(),
+        t1
+    );
+}
+
+#[test]
+fn test_pretty_width() {
+    let times = YCombinator::ref_gen_built_in_times();
+    let narrow = times.pretty(&PrettyConfig::new(true, 30));
+    assert!(narrow.lines().count() > 1);
+    for line in narrow.lines() {
+        assert!(line.chars().count() <= 30, "`{line}` is too long");
+    }
+    let wide = times.pretty(&PrettyConfig::new(true, 1000));
+    assert_eq!(wide.lines().count(), 1);
+}
+
+#[test]
+fn test_pretty_round_trip() {
+    let exps = vec![
+        Exp3::new(),
+        Exp4::new(),
+        Exp5::new(),
+        YCombinator::ref_gen_built_in_times(),
+        YCombinator::ref_gen_built_in_equal(),
+        parse_exp(
+            "incr (incr x) (decr (f y)) + (λx: (int -> int) -> bool. x) + (if a then b else c) d",
+        )
+        .unwrap(),
+    ];
+    for e in exps {
+        for config in [
+            PrettyConfig::default(),
+            PrettyConfig::new(false, 20),
+            PrettyConfig::new(true, 10),
+        ] {
+            let s = e.pretty(&config);
+            assert_eq!(parse_exp(&s).unwrap(), e, "round trip `{s}`");
+        }
+    }
+}
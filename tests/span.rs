@@ -0,0 +1,98 @@
+use stlc::{
+    parser::{parse_exp, parse_exp_with_spans, parse_type},
+    span::{Diagnostic, Span},
+    Strategy,
+};
+
+#[test]
+fn test_parse_spans() {
+    let src = "(λx. incr x) 1";
+    let (e, spans) = parse_exp_with_spans(src).unwrap();
+    assert_eq!(e.children().len(), 2);
+    // the whole application
+    assert_eq!(spans.lookup(&[]), Some(Span::new(0, src.len())));
+    // `(λx. incr x)` with the parentheses, note that `λ` takes two bytes
+    let span = spans.lookup(&[0]).unwrap();
+    assert_eq!(&src[span.start..span.end], "(λx. incr x)");
+    // the body `incr x`
+    assert_eq!(spans.lookup(&[0, 0]), Some(Span::new(6, 12)));
+    // `x` in `incr x`
+    assert_eq!(spans.lookup(&[0, 0, 0]), Some(Span::new(11, 12)));
+    // `1`
+    assert_eq!(spans.lookup(&[1]), Some(Span::new(14, 15)));
+    assert_eq!(spans.len(), 5);
+}
+
+#[test]
+fn test_eval_diagnostic() {
+    // `true` is not a function
+    let src = "(λx. x 1) true";
+    let (e, mut spans) = parse_exp_with_spans(src).unwrap();
+    let err = e
+        .ref_eval_to_normal_form_spanned(Strategy::CallByValue, &mut spans)
+        .unwrap_err();
+    // after one step `true 1` gets stuck, the `true` comes from the argument,
+    // and the nearest known location is the `x` it replaced.
+    let expected = "\
+error: StuckExpressionCbv((true) (1))
+ --> 1:6
+  |
+1 | (λx. x 1) true
+  |      ^";
+    assert_eq!(err.render(src), expected);
+
+    // stuck at the very first step
+    let src = "if is_zero 1\nthen 2\nelse incr true";
+    let (e, mut spans) = parse_exp_with_spans(src).unwrap();
+    let err = e
+        .ref_eval_to_normal_form_spanned(Strategy::CallByName, &mut spans)
+        .unwrap_err();
+    let expected = "\
+error: InvalidExpression(true)
+ --> 3:11
+  |
+3 | else incr true
+  |           ^^^^";
+    assert_eq!(err.render(src), expected);
+
+    // the happy path is the same as `ref_eval_to_normal_form`
+    let src = "(λx. λy. incr y) ((λx. x x) (λx. x x)) 1";
+    let (e, mut spans) = parse_exp_with_spans(src).unwrap();
+    assert_eq!(
+        e.ref_eval_to_normal_form_spanned(Strategy::CallByName, &mut spans),
+        Ok((parse_exp("2").unwrap(), 3))
+    );
+}
+
+#[test]
+fn test_type_diagnostic() {
+    let src = "λx: int. λy: bool. x + y";
+    let (e, spans) = parse_exp_with_spans(src).unwrap();
+    let err = e.ty_diagnose(None, &spans).unwrap_err();
+    let expected = "\
+error: TypeError(expect int, actual: bool)
+ --> 1:24
+  |
+1 | λx: int. λy: bool. x + y
+  |                        ^";
+    assert_eq!(err.render(src), expected);
+
+    let src = "λf: int -> int. f true";
+    let (e, spans) = parse_exp_with_spans(src).unwrap();
+    let ty = parse_type("(int -> int) -> int").unwrap();
+    let err = e.ty_diagnose(Some(&ty), &spans).unwrap_err();
+    assert_eq!(err.span, Some(Span::new(19, 23)));
+
+    let src = "λx: int. λy: int. x + y";
+    let (e, spans) = parse_exp_with_spans(src).unwrap();
+    assert_eq!(
+        e.ty_diagnose(None, &spans),
+        Ok(parse_type("int -> int -> int").unwrap())
+    );
+}
+
+#[test]
+fn test_diagnostic_without_span() {
+    let d = Diagnostic::new("InvalidExpression(x)".into(), None);
+    assert_eq!(d.render("x"), "error: InvalidExpression(x)");
+}
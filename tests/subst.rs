@@ -0,0 +1,84 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::p;
+use stlc::{
+    subst::{FreshNames, SubstMode},
+    Exp, Strategy,
+};
+
+/// collect all the binders, in order.
+fn binders(e: &Exp) -> Vec<String> {
+    let mut result = vec![];
+    if let Exp::Lambda(lambda) = e {
+        result.push(lambda.arg.clone());
+    }
+    for child in e.children() {
+        result.extend(binders(child));
+    }
+    result
+}
+
+#[test]
+fn test_free_vars() {
+    let e = p("λx. λy. x y z (λz. w)");
+    assert_eq!(e.free_vars(), BTreeSet::from(["z".into(), "w".into()]));
+    assert_eq!(p("λx. x").free_vars(), BTreeSet::new());
+}
+
+#[test]
+fn test_substitute_capture_avoiding() {
+    // [x := y] (λy. x) = λy1. y
+    assert_eq!(p("λy. x").ref_substitute("x".into(), p("y")), p("λy1. y"));
+    // the shadowed variable is left intact
+    assert_eq!(p("λx. x").ref_substitute("x".into(), p("y")), p("λx. x"));
+    // the fresh name avoids the other variables as well
+    assert_eq!(
+        p("λy. λy1. x y y1").ref_substitute("x".into(), p("y y1")),
+        p("λy2. λy3. y y1 y2 y3")
+    );
+    // no capture, no renaming
+    assert_eq!(
+        p("λz. x + z").ref_substitute("x".into(), p("incr y")),
+        p("λz. incr y + z")
+    );
+    // type annotations are kept
+    assert_eq!(
+        p("λy: int. x").ref_substitute("x".into(), p("y")),
+        p("λy1: int. y")
+    );
+}
+
+#[test]
+fn test_substitute_barendregt() {
+    let e = p("(λy. x) (λy. λz. x z)");
+    let s = p("λz. y z");
+    let mut fresh = FreshNames::avoiding(&[&e, &s]);
+    let result = e.substitute_with("x", &s, SubstMode::Barendregt, &mut fresh);
+    let bs = binders(&result);
+    let unique: BTreeSet<_> = bs.iter().cloned().collect();
+    assert_eq!(bs.len(), 5);
+    assert_eq!(
+        bs.len(),
+        unique.len(),
+        "binders should be distinct: {result}"
+    );
+    for b in &bs {
+        assert!(!["x", "y", "z"].contains(&b.as_str()));
+    }
+    // the free `y` from `s` is not captured
+    assert!(result.free_vars().contains("y"));
+}
+
+#[test]
+fn test_eval_open_terms() {
+    // (λx. λy. x) y should *not* reduce to λy. y
+    let e = p("(λx. λy. x) y");
+    let (result, _) = e.ref_eval_to_normal_form(Strategy::CallByName).unwrap();
+    assert_eq!(result, p("λy1. y"));
+
+    let e = p("(λx. λy. x) (λz. y)");
+    let (result, _) = e.ref_eval_to_normal_form(Strategy::CallByValue).unwrap();
+    assert_eq!(result, p("λy1. λz. y"));
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::{p, TERMS};
+use stlc::{
+    stlc_err::StlcError,
+    trace::{Rule, Step},
+    Strategy,
+};
+
+fn rules(steps: &[Step]) -> Vec<String> {
+    steps.iter().map(Step::derivation).collect()
+}
+
+#[test]
+fn test_trace_agrees() {
+    let extra = ["incr ((λx. x) 1) + 2", "(λx. λy. x) 7 ((λx. x) (λy. y))"];
+    for src in TERMS.iter().chain(&extra) {
+        let e = p(src);
+        for strategy in [Strategy::CallByValue, Strategy::CallByName] {
+            let (value, n) = e.clone().ref_eval_to_normal_form(strategy).unwrap();
+            let steps = e.clone().ref_eval_trace(n + 10, strategy).unwrap();
+            assert_eq!(steps.len() as u32, n, "{} by {}", src, strategy);
+            assert_eq!(steps.last().unwrap().after, value);
+            let mut before = e.clone();
+            for step in &steps {
+                assert_eq!(step.before, before);
+                assert_eq!(
+                    step.after,
+                    before.clone().ref_eval_multi_step(1, strategy).unwrap()
+                );
+                assert_eq!(step.context.len(), step.path.len());
+                assert!(step.before.subterm(&step.path).is_some());
+                before = step.after.clone();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_trace_rules() {
+    let steps = p("incr ((λx. x) 1) + 2")
+        .ref_eval_trace(10, Strategy::CallByValue)
+        .unwrap();
+    assert_eq!(
+        rules(&steps),
+        vec!["E-Add1(E-Incr(E-AppAbs))", "E-Add1(E-IncrNat)", "E-AddNat"]
+    );
+    assert_eq!(steps[0].rule, Rule::AppAbs);
+    assert_eq!(steps[0].context, vec![Rule::Add1, Rule::Incr]);
+    assert_eq!(steps[0].path, vec![0, 0]);
+    assert_eq!(
+        steps[0].to_string(),
+        "(incr ((λx. x) (1))) + (2)  ⟶  (incr (1)) + (2)    by E-Add1(E-Incr(E-AppAbs))"
+    );
+
+    let steps = p("if is_zero (decr 1) then 2 + 3 else 4")
+        .ref_eval_trace(10, Strategy::CallByValue)
+        .unwrap();
+    assert_eq!(
+        rules(&steps),
+        vec![
+            "E-If(E-IsZero(E-DecrNat))",
+            "E-If(E-IsZeroZero)",
+            "E-IfTrue",
+            "E-AddNat"
+        ]
+    );
+}
+
+#[test]
+fn test_trace_strategies() {
+    // the argument first under call-by-value, never under call-by-name
+    let e = p("(λx. x + 1) (incr 1)");
+    let cbv = e.clone().ref_eval_trace(10, Strategy::CallByValue).unwrap();
+    assert_eq!(
+        rules(&cbv),
+        vec!["E-App2(E-IncrNat)", "E-AppAbs", "E-AddNat"]
+    );
+    let cbn = e.clone().ref_eval_trace(10, Strategy::CallByName).unwrap();
+    assert_eq!(
+        rules(&cbn),
+        vec!["E-AppAbs", "E-Add1(E-IncrNat)", "E-AddNat"]
+    );
+    // the same as call-by-name step by step
+    assert_eq!(
+        e.clone().ref_eval_trace(10, Strategy::CallByNeed).unwrap(),
+        cbn
+    );
+    // fewer steps than asked for
+    assert_eq!(e.ref_eval_trace(1, Strategy::CallByValue).unwrap().len(), 1);
+}
+
+#[test]
+fn test_trace_errors() {
+    assert!(matches!(
+        p("(λx. x 1) true").ref_eval_trace(10, Strategy::CallByValue),
+        Err(StlcError::StuckExpressionCbv(_))
+    ));
+    assert!(matches!(
+        p("1 + (λx. x)").ref_eval_one_step_traced(Strategy::CallByName),
+        Err(StlcError::StuckExpressionCbn(_))
+    ));
+    assert!(matches!(
+        p("(λx. x) 1").ref_eval_one_step_traced(Strategy::NormalOrder),
+        Err(StlcError::InvalidExpression(_))
+    ));
+    assert_eq!(
+        p("λx. x").ref_eval_trace(10, Strategy::CallByValue),
+        Ok(vec![])
+    );
+}
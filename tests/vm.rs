@@ -0,0 +1,113 @@
+mod common;
+
+use common::{assert_agrees, p};
+use stlc::{
+    refsols::refsol_day4::YCombinator,
+    stlc_err::StlcError,
+    vm::{BlockKind, Op},
+    Exp, Strategy,
+};
+
+#[test]
+fn test_vm_agrees() {
+    assert_agrees(
+        &[
+            Strategy::CallByValue,
+            Strategy::CallByName,
+            Strategy::CallByNeed,
+        ],
+        &[
+            "(λx. λy. x) (λz. y)",
+            "(λx. λy. λz. x y) (λa. a) w",
+            "λx. (λy. y) x",
+            "(λx. λy. if y then x else decr x) (3 + 4) (is_zero 0)",
+            // stuck before the (diverging) second operand is evaluated
+            "true ((λx. x x) (λx. x x))",
+            "(λx. x) + ((λx. x x) (λx. x x))",
+        ],
+        Exp::ref_eval_vm,
+    );
+}
+
+#[test]
+fn test_vm_errors() {
+    assert!(matches!(
+        p("(λx. x 1) true").ref_eval_vm(Strategy::CallByValue),
+        Err(StlcError::StuckExpressionCbv(_))
+    ));
+    assert!(matches!(
+        p("1 + (λx. x)").ref_eval_vm(Strategy::CallByName),
+        Err(StlcError::StuckExpressionCbn(_))
+    ));
+    assert_eq!(
+        p("(λx. x) y").ref_eval_vm(Strategy::CallByName),
+        Err(StlcError::InvalidExpression("y".to_string()))
+    );
+    assert!(p("if 1 then 2 else 3")
+        .ref_eval_vm(Strategy::CallByValue)
+        .is_err());
+    assert!(p("λx. x").ref_compile(Strategy::NormalOrder).is_err());
+    // runs into the limit rather than overflowing the stack
+    let e = p("(λx. λy. incr y) ((λx. x x) (λx. x x)) 1");
+    assert!(matches!(
+        e.ref_eval_vm(Strategy::CallByValue),
+        Err(StlcError::ExceedEvalLimit(_))
+    ));
+    assert_eq!(e.ref_eval_vm(Strategy::CallByName), Ok((Exp::Nat(2), 3)));
+}
+
+#[test]
+fn test_compile_and_disassemble() {
+    let program = p("(λx. incr x) 1")
+        .ref_compile(Strategy::CallByValue)
+        .unwrap();
+    assert_eq!(program.blocks.len(), 2);
+    assert_eq!(
+        program.blocks[0].code,
+        vec![Op::Closure(1), Op::Nat(1), Op::Apply, Op::Return]
+    );
+    assert_eq!(
+        program.blocks[1].code,
+        vec![Op::Access(0), Op::Incr, Op::Return]
+    );
+    let listing = program.to_string();
+    assert!(listing.contains("block 1 (λx. incr (x)):"));
+    assert!(listing.contains("; x"));
+
+    // under call-by-name, the argument is delayed (unless a value or a variable)
+    let program = p("(λf. λx. f (f x)) (λx. x) (incr 1)")
+        .ref_compile(Strategy::CallByName)
+        .unwrap();
+    let thunks: Vec<_> = program
+        .blocks
+        .iter()
+        .filter(|b| b.kind == BlockKind::Thunk)
+        .map(|b| b.source.to_string())
+        .collect();
+    assert_eq!(thunks, vec!["(f) (x)", "incr (1)"]);
+    assert!(program
+        .blocks
+        .iter()
+        .any(|b| b.code.contains(&Op::Share(0))));
+}
+
+#[test]
+fn test_y_combinator() {
+    let times = YCombinator::ref_new(YCombinator::ref_gen_built_in_times());
+    let inputs = vec![Exp::Nat(3), Exp::Nat(3), Exp::Nat(4)];
+    // the same value, in the same number of steps
+    let vm = times
+        .clone()
+        .ref_eval_vm(inputs.clone(), Strategy::CallByName);
+    let small = times.ref_eval(inputs, Strategy::CallByName);
+    assert_eq!(vm, small);
+    assert_eq!(vm.unwrap().0, Exp::Nat(12));
+
+    // larger ones, way beyond what substitution could handle in time
+    let equal = YCombinator::ref_new(YCombinator::ref_gen_built_in_equal());
+    let inputs = vec![Exp::Nat(3000), Exp::Nat(3000)];
+    assert_eq!(
+        equal.ref_eval_vm(inputs, Strategy::CallByNeed).unwrap().0,
+        Exp::True
+    );
+}